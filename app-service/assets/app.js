@@ -6,11 +6,18 @@ logoutLink.addEventListener("click", (e) => {
     e.preventDefault();
 
     let url = logoutLink.href;
+    let authOrigin = new URL(url).origin;
 
-    fetch(url, {
+    // The auth service only accepts the logout together with its CSRF token
+    fetch(`${authOrigin}/csrf-token`, {
+        credentials: 'include',
+    }).then(response => response.json()).then(data => fetch(url, {
         method: 'POST',
         credentials: 'include', // This will include cookies in the request
-    }).then(response => {
+        headers: {
+            'X-CSRF-Token': data.csrfToken,
+        },
+    })).then(response => {
         if (response.ok) {
             loginLink.style.display = "block";
             logoutLink.style.display = "none";
@@ -32,6 +32,34 @@ signupLoginLink.addEventListener("click", (e) => {
 
 // -----------------------------------------------------
 
+// Requests that change state have to send back the CSRF token
+const csrfToken = fetch('/csrf-token')
+    .then(response => response.json())
+    .then(data => data.csrfToken);
+
+function postJson(url, body) {
+    return csrfToken.then(token => fetch(url, {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+            'X-CSRF-Token': token,
+        },
+        body: JSON.stringify(body),
+    }));
+}
+
+// /oauth/authorize sends users here to log in and passes the URL to return to
+const returnTo = new URLSearchParams(window.location.search).get("return_to");
+
+function onLoggedIn() {
+    if (returnTo !== null && returnTo.startsWith("/oauth/authorize")) {
+        window.location.assign(returnTo);
+    } else {
+        alert("You have successfully logged in.");
+    }
+}
+
+
 const loginForm = document.getElementById("login-form");
 const loginButton = document.getElementById("login-form-submit");
 const loginErrAlter = document.getElementById("login-err-alert");
@@ -42,13 +70,7 @@ loginButton.addEventListener("click", (e) => {
     const email = loginForm.email.value;
     const password = loginForm.password.value;
 
-    fetch('/login', {
-        method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-        },
-        body: JSON.stringify({ email, password }),
-    }).then(response => {
+    postJson('/login', { email, password }).then(response => {
         if (response.status === 206) {
             TwoFAForm.email.value = email;
             response.json().then(data => {
@@ -66,7 +88,7 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            onLoggedIn();
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -81,6 +103,77 @@ loginButton.addEventListener("click", (e) => {
     });
 });
 
+const magicLinkLink = document.getElementById("magic-link-link");
+
+magicLinkLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const email = loginForm.email.value;
+
+    postJson('/login/magic-link', { email }).then(response => {
+        response.json().then(data => {
+            if (response.ok) {
+                loginErrAlter.style.display = "none";
+                alert(data.message);
+            } else if (data.error !== undefined && data.error !== null && data.error !== "") {
+                loginErrAlter.innerHTML = `<span><strong>Error: </strong>${data.error}</span>`;
+                loginErrAlter.style.display = "block";
+            }
+        });
+    });
+});
+
+// Sign-in links from the magic link email open this page with the token in the query string
+const magicLinkParams = new URLSearchParams(window.location.search);
+const magicLinkToken = magicLinkParams.get("magic_link_token");
+
+if (magicLinkToken !== null) {
+    const email = magicLinkParams.get("email");
+    window.history.replaceState(null, "", window.location.pathname);
+
+    postJson('/login/magic-link/consume', { token: magicLinkToken }).then(response => {
+        if (response.status === 206) {
+            TwoFAForm.email.value = email;
+            response.json().then(data => {
+                TwoFAForm.login_attempt_id.value = data.loginAttemptId;
+            });
+
+            loginSection.style.display = "none";
+            twoFASection.style.display = "block";
+            signupSection.style.display = "none";
+        } else if (response.status === 200) {
+            onLoggedIn();
+        } else {
+            response.json().then(data => {
+                loginErrAlter.innerHTML = `<span><strong>Error: </strong>${data.error}</span>`;
+                loginErrAlter.style.display = "block";
+            });
+        }
+    });
+}
+
+// One button per external identity provider
+const oidcProviders = document.getElementById("oidc-providers");
+
+fetch('/login/oidc').then(response => {
+    if (response.ok) {
+        response.json().then(data => {
+            data.providers.forEach(provider => {
+                const params = new URLSearchParams();
+                if (returnTo !== null) {
+                    params.set("return_to", returnTo);
+                }
+
+                const button = document.createElement("a");
+                button.className = "btn btn-outline-dark d-block w-100 mb-2";
+                button.href = `/login/oidc/${encodeURIComponent(provider)}?${params}`;
+                button.textContent = `Sign in with ${provider}`;
+                oidcProviders.appendChild(button);
+            });
+        });
+    }
+});
+
 const signupForm = document.getElementById("signup-form");
 const signupButton = document.getElementById("signup-form-submit");
 const signupErrAlter = document.getElementById("signup-err-alert");
@@ -92,13 +185,7 @@ signupButton.addEventListener("click", (e) => {
     const password = signupForm.password.value;
     const requires2FA = signupForm.twoFA.checked;
 
-    fetch('/signup', {
-        method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-        },
-        body: JSON.stringify({ email, password, requires2FA }),
-    }).then(response => {
+    postJson('/signup', { email, password, requires2FA }).then(response => {
         if (response.ok) {
             signupForm.email.value = "";
             signupForm.password.value = "";
@@ -133,19 +220,13 @@ TwoFAButton.addEventListener("click", (e) => {
     const loginAttemptId = TwoFAForm.login_attempt_id.value;
     const TwoFACode = TwoFAForm.email_code.value;
 
-    fetch('/verify-2fa', {
-        method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-        },
-        body: JSON.stringify({ email, loginAttemptId, "2FACode": TwoFACode }),
-    }).then(response => {
+    postJson('/verify-2fa', { email, loginAttemptId, "2FACode": TwoFACode }).then(response => {
         if (response.ok) {
             TwoFAForm.email.value = "";
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            onLoggedIn();
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
             signupSection.style.display = "none";
@@ -161,4 +242,23 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+
+// Users with 2FA are sent back here after signing in with an external provider
+const oidcParams = new URLSearchParams(window.location.search);
+const oidcLoginAttemptId = oidcParams.get("login_attempt_id");
+
+if (oidcLoginAttemptId !== null) {
+    TwoFAForm.email.value = oidcParams.get("email");
+    TwoFAForm.login_attempt_id.value = oidcLoginAttemptId;
+
+    const cleanParams = new URLSearchParams();
+    if (returnTo !== null) {
+        cleanParams.set("return_to", returnTo);
+    }
+    window.history.replaceState(null, "", `${window.location.pathname}?${cleanParams}`);
+
+    loginSection.style.display = "none";
+    twoFASection.style.display = "block";
+    signupSection.style.display = "none";
+}
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS totp_secrets;
+ALTER TABLE users DROP COLUMN IF EXISTS two_fa_method;
@@ -0,0 +1,8 @@
+-- Only a SHA-256 hash of each code is stored. Codes are random and high-entropy,
+-- so a fast hash is enough and lets a code be looked up directly.
+CREATE TABLE IF NOT EXISTS recovery_codes(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   code_hash TEXT NOT NULL,
+   used_at TIMESTAMPTZ,
+   PRIMARY KEY (email, code_hash)
+);
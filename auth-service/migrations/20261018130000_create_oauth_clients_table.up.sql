@@ -0,0 +1,7 @@
+-- Client secrets are random and high-entropy, so only a SHA-256 hash is stored.
+-- Public clients, such as the mobile app, have no secret.
+CREATE TABLE IF NOT EXISTS oauth_clients(
+   client_id TEXT NOT NULL PRIMARY KEY,
+   client_secret_hash TEXT,
+   redirect_uris TEXT[] NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Auth tokens carry the generation they were issued in. Incrementing it invalidates every
+-- outstanding token of the user at once.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS token_generation BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,27 @@
+-- Users are granted roles, and roles grant permissions. Both end up in the user's auth tokens.
+CREATE TABLE IF NOT EXISTS roles(
+   name TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS permissions(
+   name TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions(
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   permission TEXT NOT NULL REFERENCES permissions(name) ON DELETE CASCADE,
+   PRIMARY KEY (role, permission)
+);
+
+CREATE TABLE IF NOT EXISTS user_roles(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   PRIMARY KEY (email, role)
+);
+
+-- Built-in roles, see `utils::constants::roles`
+INSERT INTO roles(name) VALUES ('admin') ON CONFLICT DO NOTHING;
+INSERT INTO permissions(name) VALUES ('users:read'), ('users:write') ON CONFLICT DO NOTHING;
+INSERT INTO role_permissions(role, permission)
+VALUES ('admin', 'users:read'), ('admin', 'users:write')
+ON CONFLICT DO NOTHING;
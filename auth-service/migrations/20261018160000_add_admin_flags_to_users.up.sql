@@ -0,0 +1,4 @@
+-- Disabled users can't sign in and their tokens are rejected until an admin enables them again.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disabled BOOLEAN NOT NULL DEFAULT FALSE;
+-- Set by an admin to make the user pick a new password before signing in with one again.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
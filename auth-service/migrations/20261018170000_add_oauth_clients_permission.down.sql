@@ -0,0 +1 @@
+DELETE FROM permissions WHERE name = 'oauth_clients:write';
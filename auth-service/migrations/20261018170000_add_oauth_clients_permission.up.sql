@@ -0,0 +1,5 @@
+-- Lets admins register OAuth clients, see `utils::constants::roles`
+INSERT INTO permissions(name) VALUES ('oauth_clients:write') ON CONFLICT DO NOTHING;
+INSERT INTO role_permissions(role, permission)
+VALUES ('admin', 'oauth_clients:write')
+ON CONFLICT DO NOTHING;
@@ -1,34 +1,100 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use crate::{
+    domain::{
+        AuthorizationCodeStore, BannedTokenStore, EmailClient, EmailVerificationTokenStore,
+        LoginLockoutStore, MagicLinkTokenStore, OAuthClientStore, OidcStateStore,
+        PasswordResetTokenStore, RateLimitStore, RecoveryCodeStore, RefreshTokenStore,
+        SessionStore, TotpSecretStore, TwoFACodeStore, UserStore,
+    },
+    services::oidc_client::OidcClient,
+};
 
 // Using a type alias to improve readability!
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>; // New!
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+pub type PasswordResetTokenStoreType = Arc<RwLock<dyn PasswordResetTokenStore + Send + Sync>>;
+pub type EmailVerificationTokenStoreType =
+    Arc<RwLock<dyn EmailVerificationTokenStore + Send + Sync>>;
+pub type TotpSecretStoreType = Arc<RwLock<dyn TotpSecretStore + Send + Sync>>;
+pub type RecoveryCodeStoreType = Arc<RwLock<dyn RecoveryCodeStore + Send + Sync>>;
+pub type MagicLinkTokenStoreType = Arc<RwLock<dyn MagicLinkTokenStore + Send + Sync>>;
+pub type OAuthClientStoreType = Arc<RwLock<dyn OAuthClientStore + Send + Sync>>;
+pub type AuthorizationCodeStoreType = Arc<RwLock<dyn AuthorizationCodeStore + Send + Sync>>;
+pub type OidcStateStoreType = Arc<RwLock<dyn OidcStateStore + Send + Sync>>;
+pub type OidcClientType = Arc<OidcClient>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+pub type LoginLockoutStoreType = Arc<RwLock<dyn LoginLockoutStore + Send + Sync>>;
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore + Send + Sync>>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    // Challenges started to turn 2FA off, kept apart so a pending login can't confirm one
+    pub disable_2fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType, // New!
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub totp_secret_store: TotpSecretStoreType,
+    pub recovery_code_store: RecoveryCodeStoreType,
+    pub magic_link_token_store: MagicLinkTokenStoreType,
+    pub oauth_client_store: OAuthClientStoreType,
+    pub authorization_code_store: AuthorizationCodeStoreType,
+    pub oidc_state_store: OidcStateStoreType,
+    pub oidc_client: OidcClientType,
+    pub session_store: SessionStoreType,
+    pub login_lockout_store: LoginLockoutStoreType,
+    pub rate_limit_store: RateLimitStoreType,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
+        disable_2fa_code_store: TwoFACodeStoreType,
         email_client: EmailClientType, // New!
+        refresh_token_store: RefreshTokenStoreType,
+        password_reset_token_store: PasswordResetTokenStoreType,
+        email_verification_token_store: EmailVerificationTokenStoreType,
+        totp_secret_store: TotpSecretStoreType,
+        recovery_code_store: RecoveryCodeStoreType,
+        magic_link_token_store: MagicLinkTokenStoreType,
+        oauth_client_store: OAuthClientStoreType,
+        authorization_code_store: AuthorizationCodeStoreType,
+        oidc_state_store: OidcStateStoreType,
+        oidc_client: OidcClientType,
+        session_store: SessionStoreType,
+        login_lockout_store: LoginLockoutStoreType,
+        rate_limit_store: RateLimitStoreType,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
+            disable_2fa_code_store,
             email_client, // New!
+            refresh_token_store,
+            password_reset_token_store,
+            email_verification_token_store,
+            totp_secret_store,
+            recovery_code_store,
+            magic_link_token_store,
+            oauth_client_store,
+            authorization_code_store,
+            oidc_state_store,
+            oidc_client,
+            session_store,
+            login_lockout_store,
+            rate_limit_store,
         }
     }
 }
@@ -1,4 +1,5 @@
-use super::{Email, Password, User};
+use super::{Email, Password, TwoFAMethod, User};
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{eyre, Context, Report, Result};
 use rand::Rng;
 use secrecy::Secret;
@@ -10,6 +11,63 @@ pub trait UserStore {
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+    // Also lifts a password reset required by `set_password_reset_required`
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    // Switches how the second factor is delivered and turns 2FA on for the user
+    async fn set_two_fa_method(
+        &mut self,
+        email: &Email,
+        method: TwoFAMethod,
+    ) -> Result<(), UserStoreError>;
+    // Turns 2FA on or off. The method the user had before is kept for when it is turned back on.
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError>;
+    // Turns 2FA off and goes back to email codes, so turning it back on doesn't revive a TOTP
+    // secret the user gave up
+    async fn disable_2fa(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    // Soft-deletes the user, who can be restored until the row is purged
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    async fn restore_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    // Invalidates every auth token issued to the user so far and returns the new generation
+    async fn increment_token_generation(&mut self, email: &Email) -> Result<i64, UserStoreError>;
+    // Roles take effect in auth tokens issued from then on
+    async fn add_role(&mut self, email: &Email, role: &str) -> Result<(), UserStoreError>;
+    // Also moves the user to a new token generation, tokens that still claim the role stop working
+    async fn remove_role(&mut self, email: &Email, role: &str) -> Result<(), UserStoreError>;
+    // Users ordered by email, optionally only those whose email contains `search` ignoring case
+    async fn list_users(
+        &self,
+        search: Option<&str>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<UserPage, UserStoreError>;
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError>;
+    async fn set_password_reset_required(
+        &mut self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError>;
+    // Permanently removes users soft-deleted before the given time and returns their emails
+    async fn purge_deleted_users(
+        &mut self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<Vec<Email>, UserStoreError>;
+}
+
+// One page of `UserStore::list_users`
+#[derive(Debug, PartialEq)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    // Users matching the search across all pages
+    pub total: u64,
 }
 
 #[derive(Debug, Error)]
@@ -20,6 +78,8 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Role not found")]
+    RoleNotFound,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -31,6 +91,7 @@ impl PartialEq for UserStoreError {
             (Self::UserAlreadyExists, Self::UserAlreadyExists)
                 | (Self::UserNotFound, Self::UserNotFound)
                 | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::RoleNotFound, Self::RoleNotFound)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -51,23 +112,44 @@ pub enum BannedTokenStoreError {
 // This trait represents the interface all concrete 2FA code stores should implement
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
+    // Codes are stored per login attempt, so a user can log in on several devices at once. Adding
+    // a code for an existing attempt replaces it. Past `MAX_PENDING_TWO_FA_LOGINS` attempts of
+    // the same user, the oldest is dropped.
     async fn add_code(
         &mut self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_all_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode), TwoFACodeStoreError>;
+    // Counts a code submitted for the login attempt and returns how many there have been. Codes
+    // are counted before they are checked, so guesses sent in parallel can't slip past the limit.
+    async fn record_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError>;
+    // Counts a resend of the login attempt's code and returns how many there have been. Fails
+    // with `ResendCooldownActive` if the code was already resent within the cooldown.
+    async fn record_resend(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError>;
 }
 
 #[derive(Debug, Error)]
 pub enum TwoFACodeStoreError {
     #[error("Login Attempt ID not found")]
     LoginAttemptIdNotFound,
+    #[error("2FA code was resent too recently")]
+    ResendCooldownActive,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -77,11 +159,427 @@ impl PartialEq for TwoFACodeStoreError {
         matches!(
             (self, other),
             (Self::LoginAttemptIdNotFound, Self::LoginAttemptIdNotFound)
+                | (Self::ResendCooldownActive, Self::ResendCooldownActive)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete refresh token stores should implement.
+// Refresh tokens are single-use: consuming a token marks it as used, and presenting a used
+// token again is treated as theft, which revokes every token in its family.
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        family: RefreshTokenFamily,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn use_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamily, RefreshTokenStoreError>;
+    async fn revoke_family(
+        &mut self,
+        family: &RefreshTokenFamily,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn revoke_all_families(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError>;
+    async fn remove_all_tokens(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token was already used")]
+    TokenReused(RefreshTokenFamily),
+    #[error("Refresh token family was revoked")]
+    FamilyRevoked,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::TokenReused(_), Self::TokenReused(_))
+                | (Self::FamilyRevoked, Self::FamilyRevoked)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+    // Consumes the token, so each reset token can only be used once
+    async fn use_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PasswordResetTokenStoreError {
+    #[error("Password reset token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasswordResetTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait MagicLinkTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: MagicLinkToken,
+    ) -> Result<(), MagicLinkTokenStoreError>;
+    // Consumes the token, so each sign-in link can only be used once
+    async fn use_token(&mut self, token: &MagicLinkToken)
+        -> Result<Email, MagicLinkTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum MagicLinkTokenStoreError {
+    #[error("Magic link token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for MagicLinkTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait EmailVerificationTokenStore {
+    // Fails with `CooldownActive` if a token was already issued for the email within the cooldown
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError>;
+    async fn use_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailVerificationTokenStoreError {
+    #[error("Email verification token not found")]
+    TokenNotFound,
+    #[error("Email verification token was issued too recently")]
+    CooldownActive,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailVerificationTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::CooldownActive, Self::CooldownActive)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Secrets for authenticator-app (TOTP) 2FA. A secret only becomes usable for login once
+// the user has confirmed it by submitting a valid code.
+#[async_trait::async_trait]
+pub trait TotpSecretStore {
+    // Fails with `SecretAlreadyConfirmed` if the user already has a confirmed secret
+    async fn add_secret(
+        &mut self,
+        email: Email,
+        secret: TotpSecret,
+    ) -> Result<(), TotpSecretStoreError>;
+    async fn get_secret(&self, email: &Email) -> Result<TotpEnrollment, TotpSecretStoreError>;
+    async fn confirm_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError>;
+    // Removes the user's secret, confirmed or not. Succeeds if the user has none.
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError>;
+    // Records the time step of an accepted code. Fails with `TimeStepAlreadyUsed` unless the
+    // step is newer than the last one recorded, so a code can't be replayed.
+    async fn use_time_step(
+        &mut self,
+        email: &Email,
+        time_step: u64,
+    ) -> Result<(), TotpSecretStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum TotpSecretStoreError {
+    #[error("TOTP secret not found")]
+    SecretNotFound,
+    #[error("TOTP secret is already confirmed")]
+    SecretAlreadyConfirmed,
+    #[error("TOTP time step was already used")]
+    TimeStepAlreadyUsed,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for TotpSecretStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SecretNotFound, Self::SecretNotFound)
+                | (Self::SecretAlreadyConfirmed, Self::SecretAlreadyConfirmed)
+                | (Self::TimeStepAlreadyUsed, Self::TimeStepAlreadyUsed)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Single-use codes that stand in for the second factor when the user has lost access to it
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore {
+    // Replaces every code issued to the user, invalidating the previous set
+    async fn replace_codes(
+        &mut self,
+        email: &Email,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError>;
+    // Burns the code and returns how many unused codes the user has left
+    async fn use_code(
+        &mut self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<usize, RecoveryCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RecoveryCodeStoreError {
+    #[error("Recovery code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RecoveryCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait OAuthClientStore {
+    // The secret of a confidential client is only stored hashed. Public clients have none.
+    async fn add_client(
+        &mut self,
+        client: OAuthClient,
+        client_secret: Option<OAuthClientSecret>,
+    ) -> Result<(), OAuthClientStoreError>;
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, OAuthClientStoreError>;
+    async fn validate_client_secret(
+        &self,
+        client_id: &str,
+        client_secret: &OAuthClientSecret,
+    ) -> Result<(), OAuthClientStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum OAuthClientStoreError {
+    #[error("OAuth client already exists")]
+    ClientAlreadyExists,
+    #[error("OAuth client not found")]
+    ClientNotFound,
+    #[error("Invalid client secret")]
+    InvalidClientSecret,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for OAuthClientStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ClientAlreadyExists, Self::ClientAlreadyExists)
+                | (Self::ClientNotFound, Self::ClientNotFound)
+                | (Self::InvalidClientSecret, Self::InvalidClientSecret)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait AuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError>;
+    // Consumes the code, so each authorization code can only be exchanged once
+    async fn use_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuthorizationCodeStoreError {
+    #[error("Authorization code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for AuthorizationCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait OidcStateStore {
+    async fn add_state(
+        &mut self,
+        state: OidcState,
+        login: OidcLogin,
+    ) -> Result<(), OidcStateStoreError>;
+    // Consumes the state, so each external login can only be completed once
+    async fn use_state(&mut self, state: &OidcState) -> Result<OidcLogin, OidcStateStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum OidcStateStoreError {
+    #[error("OIDC state not found")]
+    StateNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for OidcStateStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::StateNotFound, Self::StateNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait SessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError>;
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    // Records that the session is still in use and extends its lifetime
+    async fn touch_session(&mut self, id: &str) -> Result<(), SessionStoreError>;
+    async fn revoke_session(&mut self, id: &str) -> Result<(), SessionStoreError>;
+    async fn revoke_all_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Failed password logins per email, used to lock accounts against online password guessing.
+// Failures count until a successful login, or until none have been recorded for a day.
+#[async_trait::async_trait]
+pub trait LoginLockoutStore {
+    // Records a failed login and returns how many there have been in a row
+    async fn add_failed_attempt(&mut self, email: &Email) -> Result<u32, LoginLockoutStoreError>;
+    async fn lock(
+        &mut self,
+        email: &Email,
+        locked_until: DateTime<Utc>,
+    ) -> Result<(), LoginLockoutStoreError>;
+    // Returns when the lockout ends, if the account is currently locked
+    async fn get_lockout(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, LoginLockoutStoreError>;
+    // Forgets the failed logins and lifts any lockout
+    async fn reset(&mut self, email: &Email) -> Result<(), LoginLockoutStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum LoginLockoutStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// Token buckets for rate limiting, shared by every replica when backed by Redis
+#[async_trait::async_trait]
+pub trait RateLimitStore {
+    // Takes one request from the key's bucket. Fails with `LimitExceeded` and the time until the
+    // next request is allowed when the bucket is empty.
+    async fn take_token(&mut self, key: &str, limit: RateLimit) -> Result<(), RateLimitStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitStoreError {
+    #[error("Rate limit exceeded")]
+    LimitExceeded(std::time::Duration),
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RateLimitStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::LimitExceeded(_), Self::LimitExceeded(_))
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
 }
 
+// A bucket holds up to `capacity` requests and gets one back every `refill_interval`, so bursts
+// are allowed while the sustained rate stays bounded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub refill_interval: std::time::Duration,
+}
+
 #[derive(Debug, Clone)]
 pub struct LoginAttemptId(Secret<String>);
 
@@ -126,9 +624,10 @@ impl PartialEq for TwoFACode {
 impl TwoFACode {
     pub fn parse(code: Secret<String>) -> Result<Self> {
         use secrecy::ExposeSecret;
-        let code_as_u32 = code.expose_secret().parse::<u32>().wrap_err("Invalid 2FA code")?;
+        let value = code.expose_secret();
 
-        if (100_000..=999_999).contains(&code_as_u32) {
+        // Authenticator apps may produce codes with leading zeros
+        if value.len() == 6 && value.chars().all(|c| c.is_ascii_digit()) {
             Ok(Self(code))
         } else {
             Err(eyre!("Invalid 2FA code"))
@@ -151,3 +650,622 @@ impl AsRef<Secret<String>> for TwoFACode {
         &self.0
     }
 }
+
+pub const RANDOM_TOKEN_LENGTH: usize = 64;
+
+fn generate_random_token() -> Secret<String> {
+    let token: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(RANDOM_TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+    Secret::new(token)
+}
+
+fn is_valid_random_token(token: &Secret<String>) -> bool {
+    use secrecy::ExposeSecret;
+    let value = token.expose_secret();
+    value.len() == RANDOM_TOKEN_LENGTH && value.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[derive(Clone, Debug)]
+pub struct RefreshToken(Secret<String>);
+
+impl PartialEq for RefreshToken {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl RefreshToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        if is_valid_random_token(&token) {
+            Ok(Self(token))
+        } else {
+            Err(eyre!("Invalid refresh token"))
+        }
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        Self(generate_random_token())
+    }
+}
+
+impl AsRef<Secret<String>> for RefreshToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// A family groups every refresh token issued from a single login.
+// Rotating a token keeps the family, so reuse of any old token can revoke the whole chain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefreshTokenFamily {
+    pub id: String,
+    pub email: Email,
+}
+
+impl RefreshTokenFamily {
+    pub fn new(email: Email) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            email,
+        }
+    }
+}
+
+// A signed-in device. The ID is shared with the session's refresh token family and is put in
+// the `sid` claim of its auth tokens.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub email: Email,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl Session {
+    pub fn new(email: Email, ip: Option<String>, user_agent: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            email,
+            created_at: now,
+            last_seen: now,
+            ip,
+            user_agent,
+        }
+    }
+
+    pub fn refresh_token_family(&self) -> RefreshTokenFamily {
+        RefreshTokenFamily {
+            id: self.id.clone(),
+            email: self.email.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PasswordResetToken(Secret<String>);
+
+impl PartialEq for PasswordResetToken {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl PasswordResetToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        if is_valid_random_token(&token) {
+            Ok(Self(token))
+        } else {
+            Err(eyre!("Invalid password reset token"))
+        }
+    }
+}
+
+impl Default for PasswordResetToken {
+    fn default() -> Self {
+        Self(generate_random_token())
+    }
+}
+
+impl AsRef<Secret<String>> for PasswordResetToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MagicLinkToken(Secret<String>);
+
+impl PartialEq for MagicLinkToken {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl MagicLinkToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        if is_valid_random_token(&token) {
+            Ok(Self(token))
+        } else {
+            Err(eyre!("Invalid magic link token"))
+        }
+    }
+}
+
+impl Default for MagicLinkToken {
+    fn default() -> Self {
+        Self(generate_random_token())
+    }
+}
+
+impl AsRef<Secret<String>> for MagicLinkToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmailVerificationToken(Secret<String>);
+
+impl PartialEq for EmailVerificationToken {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl EmailVerificationToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        if is_valid_random_token(&token) {
+            Ok(Self(token))
+        } else {
+            Err(eyre!("Invalid email verification token"))
+        }
+    }
+}
+
+impl Default for EmailVerificationToken {
+    fn default() -> Self {
+        Self(generate_random_token())
+    }
+}
+
+impl AsRef<Secret<String>> for EmailVerificationToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TotpSecret(Secret<String>);
+
+impl PartialEq for TotpSecret {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl TotpSecret {
+    // Parses a base32 encoded secret of at least 128 bits, as required by RFC 4226
+    pub fn parse(secret: Secret<String>) -> Result<Self> {
+        use secrecy::ExposeSecret;
+        let bytes = totp_rs::Secret::Encoded(secret.expose_secret().to_owned())
+            .to_bytes()
+            .map_err(|_| eyre!("Invalid TOTP secret"))?;
+
+        if bytes.len() >= 16 {
+            Ok(Self(secret))
+        } else {
+            Err(eyre!("Invalid TOTP secret"))
+        }
+    }
+}
+
+impl Default for TotpSecret {
+    fn default() -> Self {
+        let bytes: [u8; 20] = rand::thread_rng().gen();
+        match totp_rs::Secret::Raw(bytes.to_vec()).to_encoded() {
+            totp_rs::Secret::Encoded(secret) => Self(Secret::new(secret)),
+            totp_rs::Secret::Raw(_) => unreachable!("secret was just encoded"),
+        }
+    }
+}
+
+impl AsRef<Secret<String>> for TotpSecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TotpEnrollment {
+    pub secret: TotpSecret,
+    pub confirmed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OAuthClient {
+    pub client_id: String,
+    // Redirect URIs are compared exactly, without any normalisation
+    pub redirect_uris: Vec<String>,
+    pub confidential: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct OAuthClientSecret(Secret<String>);
+
+impl PartialEq for OAuthClientSecret {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl OAuthClientSecret {
+    pub fn parse(secret: Secret<String>) -> Result<Self> {
+        if is_valid_random_token(&secret) {
+            Ok(Self(secret))
+        } else {
+            Err(eyre!("Invalid client secret"))
+        }
+    }
+}
+
+impl Default for OAuthClientSecret {
+    fn default() -> Self {
+        Self(generate_random_token())
+    }
+}
+
+impl AsRef<Secret<String>> for OAuthClientSecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AuthorizationCode(Secret<String>);
+
+impl PartialEq for AuthorizationCode {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AuthorizationCode {
+    pub fn parse(code: Secret<String>) -> Result<Self> {
+        if is_valid_random_token(&code) {
+            Ok(Self(code))
+        } else {
+            Err(eyre!("Invalid authorization code"))
+        }
+    }
+}
+
+impl Default for AuthorizationCode {
+    fn default() -> Self {
+        Self(generate_random_token())
+    }
+}
+
+impl AsRef<Secret<String>> for AuthorizationCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// What an authorization code was issued for. The token request has to match it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthorizationGrant {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub email: Email,
+    // S256 PKCE challenge sent to /oauth/authorize
+    pub code_challenge: String,
+    // Space separated scopes, `openid` asks for an ID token
+    pub scope: Option<String>,
+    // Echoed back in the ID token so the client can detect replays
+    pub nonce: Option<String>,
+}
+
+// `state` parameter of a login with an external OpenID Connect provider
+#[derive(Clone, Debug)]
+pub struct OidcState(Secret<String>);
+
+impl PartialEq for OidcState {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl OidcState {
+    pub fn parse(state: Secret<String>) -> Result<Self> {
+        if is_valid_random_token(&state) {
+            Ok(Self(state))
+        } else {
+            Err(eyre!("Invalid OIDC state"))
+        }
+    }
+}
+
+impl Default for OidcState {
+    fn default() -> Self {
+        Self(generate_random_token())
+    }
+}
+
+impl AsRef<Secret<String>> for OidcState {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// Token that cookie-authenticated requests have to echo in a header, see `utils::csrf`
+#[derive(Clone, Debug)]
+pub struct CsrfToken(Secret<String>);
+
+impl PartialEq for CsrfToken {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl CsrfToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        if is_valid_random_token(&token) {
+            Ok(Self(token))
+        } else {
+            Err(eyre!("Invalid CSRF token"))
+        }
+    }
+}
+
+impl Default for CsrfToken {
+    fn default() -> Self {
+        Self(generate_random_token())
+    }
+}
+
+impl AsRef<Secret<String>> for CsrfToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// What is needed to finish a login once the provider redirects back
+#[derive(Clone, Debug, PartialEq)]
+pub struct OidcLogin {
+    pub provider: String,
+    // Must come back in the provider's ID token
+    pub nonce: String,
+    // PKCE verifier for the provider's token endpoint
+    pub code_verifier: String,
+    pub return_to: Option<String>,
+}
+
+impl OidcLogin {
+    pub fn new(provider: String, return_to: Option<String>) -> Self {
+        use secrecy::ExposeSecret;
+        Self {
+            provider,
+            nonce: generate_random_token().expose_secret().to_owned(),
+            code_verifier: generate_random_token().expose_secret().to_owned(),
+            return_to,
+        }
+    }
+}
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_GROUP_LENGTH: usize = 5;
+
+// Recovery codes look like `x7k2m-q9p4d` and are compared case-insensitively
+#[derive(Clone, Debug)]
+pub struct RecoveryCode(Secret<String>);
+
+impl PartialEq for RecoveryCode {
+    fn eq(&self, other: &Self) -> bool {
+        use secrecy::ExposeSecret;
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl RecoveryCode {
+    pub fn parse(code: Secret<String>) -> Result<Self> {
+        use secrecy::ExposeSecret;
+        let code = code.expose_secret().trim().to_ascii_lowercase();
+
+        let is_valid = match code.split_once('-') {
+            Some((first, second)) => [first, second].iter().all(|group| {
+                group.len() == RECOVERY_CODE_GROUP_LENGTH
+                    && group
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+            }),
+            None => false,
+        };
+
+        if is_valid {
+            Ok(Self(Secret::new(code)))
+        } else {
+            Err(eyre!("Invalid recovery code"))
+        }
+    }
+
+    // Generates a fresh set of codes to hand out to the user
+    pub fn generate_set() -> Vec<Self> {
+        (0..RECOVERY_CODE_COUNT).map(|_| Self::default()).collect()
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        let mut group = || -> String {
+            (0..RECOVERY_CODE_GROUP_LENGTH)
+                .map(|_| char::from(rng.sample(rand::distributions::Alphanumeric)))
+                .map(|c| c.to_ascii_lowercase())
+                .collect()
+        };
+        let code = format!("{}-{}", group(), group());
+        Self(Secret::new(code))
+    }
+}
+
+impl AsRef<Secret<String>> for RecoveryCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn default_refresh_token_is_parsed_successfully() {
+        let token = RefreshToken::default();
+        let parsed = RefreshToken::parse(token.as_ref().clone()).unwrap();
+        assert_eq!(parsed, token);
+        assert_eq!(token.as_ref().expose_secret().len(), RANDOM_TOKEN_LENGTH);
+    }
+
+    #[test]
+    fn refresh_token_with_wrong_length_is_rejected() {
+        let token = Secret::new("abc123".to_owned());
+        assert!(RefreshToken::parse(token).is_err());
+    }
+
+    #[test]
+    fn refresh_token_with_invalid_characters_is_rejected() {
+        let token = Secret::new("!".repeat(RANDOM_TOKEN_LENGTH));
+        assert!(RefreshToken::parse(token).is_err());
+    }
+
+    #[test]
+    fn default_password_reset_token_is_parsed_successfully() {
+        let token = PasswordResetToken::default();
+        let parsed = PasswordResetToken::parse(token.as_ref().clone()).unwrap();
+        assert_eq!(parsed, token);
+    }
+
+    #[test]
+    fn default_email_verification_token_is_parsed_successfully() {
+        let token = EmailVerificationToken::default();
+        let parsed = EmailVerificationToken::parse(token.as_ref().clone()).unwrap();
+        assert_eq!(parsed, token);
+    }
+
+    #[test]
+    fn default_magic_link_token_is_parsed_successfully() {
+        let token = MagicLinkToken::default();
+        let parsed = MagicLinkToken::parse(token.as_ref().clone()).unwrap();
+        assert_eq!(parsed, token);
+    }
+
+    #[test]
+    fn default_authorization_code_is_parsed_successfully() {
+        let code = AuthorizationCode::default();
+        let parsed = AuthorizationCode::parse(code.as_ref().clone()).unwrap();
+        assert_eq!(parsed, code);
+    }
+
+    #[test]
+    fn default_client_secret_is_parsed_successfully() {
+        let secret = OAuthClientSecret::default();
+        let parsed = OAuthClientSecret::parse(secret.as_ref().clone()).unwrap();
+        assert_eq!(parsed, secret);
+    }
+
+    #[test]
+    fn default_oidc_state_is_parsed_successfully() {
+        let state = OidcState::default();
+        let parsed = OidcState::parse(state.as_ref().clone()).unwrap();
+        assert_eq!(parsed, state);
+    }
+
+    #[test]
+    fn default_csrf_token_is_parsed_successfully() {
+        let token = CsrfToken::default();
+        let parsed = CsrfToken::parse(token.as_ref().clone()).unwrap();
+        assert_eq!(parsed, token);
+    }
+
+    #[test]
+    fn new_oidc_logins_have_distinct_nonces_and_verifiers() {
+        let first = OidcLogin::new("google".to_owned(), None);
+        let second = OidcLogin::new("google".to_owned(), None);
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.code_verifier, second.code_verifier);
+        assert!(crate::utils::pkce::is_valid_code_verifier(&first.code_verifier));
+    }
+
+    #[test]
+    fn password_reset_token_with_wrong_length_is_rejected() {
+        let token = Secret::new("abc123".to_owned());
+        assert!(PasswordResetToken::parse(token).is_err());
+    }
+
+    #[test]
+    fn two_fa_code_with_leading_zero_is_accepted() {
+        assert!(TwoFACode::parse(Secret::new("012345".to_owned())).is_ok());
+        assert!(TwoFACode::parse(Secret::new("12345".to_owned())).is_err());
+        assert!(TwoFACode::parse(Secret::new("+12345".to_owned())).is_err());
+    }
+
+    #[test]
+    fn default_totp_secret_is_parsed_successfully() {
+        let secret = TotpSecret::default();
+        let parsed = TotpSecret::parse(secret.as_ref().clone()).unwrap();
+        assert_eq!(parsed, secret);
+    }
+
+    #[test]
+    fn short_or_malformed_totp_secret_is_rejected() {
+        assert!(TotpSecret::parse(Secret::new("JBSWY3DP".to_owned())).is_err());
+        assert!(TotpSecret::parse(Secret::new("not base32!".to_owned())).is_err());
+    }
+
+    #[test]
+    fn default_recovery_code_is_parsed_successfully() {
+        let code = RecoveryCode::default();
+        let parsed = RecoveryCode::parse(code.as_ref().clone()).unwrap();
+        assert_eq!(parsed, code);
+    }
+
+    #[test]
+    fn recovery_code_is_normalized_to_lowercase() {
+        let parsed = RecoveryCode::parse(Secret::new(" ABCDE-12345 ".to_owned())).unwrap();
+        assert_eq!(parsed.as_ref().expose_secret(), "abcde-12345");
+    }
+
+    #[test]
+    fn malformed_recovery_code_is_rejected() {
+        for code in ["abcde12345", "abcd-12345", "abcde-1234!", "abcde-12345-x"] {
+            assert!(RecoveryCode::parse(Secret::new(code.to_owned())).is_err());
+        }
+    }
+
+    #[test]
+    fn recovery_code_set_has_distinct_codes() {
+        let codes = RecoveryCode::generate_set();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        for (i, code) in codes.iter().enumerate() {
+            assert!(!codes[i + 1..].contains(code));
+        }
+    }
+}
@@ -13,6 +13,48 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    #[error("Account pending deletion")]
+    AccountPendingDeletion,
+    #[error("Account locked")]
+    AccountLocked,
+    #[error("Account disabled")]
+    AccountDisabled,
+    #[error("Password reset required")]
+    PasswordResetRequired,
+    #[error("Insufficient permissions")]
+    InsufficientPermissions,
+    #[error("Insufficient scope")]
+    InsufficientScope,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("TOTP already enabled")]
+    TotpAlreadyEnabled,
+    #[error("2FA already enabled")]
+    TwoFAAlreadyEnabled,
+    #[error("2FA not enabled")]
+    TwoFANotEnabled,
+    #[error("Invalid OAuth request")]
+    OAuthInvalidRequest,
+    #[error("Invalid OAuth client")]
+    OAuthInvalidClient,
+    #[error("OAuth client already exists")]
+    OAuthClientAlreadyExists,
+    #[error("Invalid OAuth grant")]
+    OAuthInvalidGrant,
+    #[error("Unsupported OAuth grant type")]
+    OAuthUnsupportedGrantType,
+    #[error("Unknown OIDC provider")]
+    OidcProviderNotFound,
+    #[error("OIDC login failed")]
+    OidcLoginFailed,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Too many requests")]
+    TooManyRequests,
+    #[error("CSRF check failed")]
+    CsrfCheckFailed,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -6,11 +6,21 @@ mod password;
 mod user;
 
 pub use data_stores::{
-    BannedTokenStore, BannedTokenStoreError, LoginAttemptId, TwoFACode, TwoFACodeStore,
-    TwoFACodeStoreError, UserStore, UserStoreError,
+    AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError, AuthorizationGrant,
+    BannedTokenStore, BannedTokenStoreError, CsrfToken, EmailVerificationToken,
+    EmailVerificationTokenStore, EmailVerificationTokenStoreError, LoginAttemptId,
+    LoginLockoutStore, LoginLockoutStoreError, MagicLinkToken, MagicLinkTokenStore,
+    MagicLinkTokenStoreError, OAuthClient, OAuthClientSecret, OAuthClientStore,
+    OAuthClientStoreError, OidcLogin, OidcState, OidcStateStore, OidcStateStoreError,
+    PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError, RateLimit,
+    RateLimitStore, RateLimitStoreError, RecoveryCode, RecoveryCodeStore, RecoveryCodeStoreError,
+    RefreshToken, RefreshTokenFamily, RefreshTokenStore, RefreshTokenStoreError, Session,
+    SessionStore, SessionStoreError, TotpEnrollment, TotpSecret, TotpSecretStore,
+    TotpSecretStoreError, TwoFACode, TwoFACodeStore, TwoFACodeStoreError, UserPage, UserStore,
+    UserStoreError,
 };
 pub use email::Email;
 pub use email_client::*;
 pub use error::AuthAPIError;
 pub use password::Password;
-pub use user::User;
+pub use user::{TwoFAMethod, User};
@@ -1,3 +1,6 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+
 use super::{Email, Password};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -5,14 +8,61 @@ pub struct User {
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    pub email_verified: bool,
+    pub two_fa_method: TwoFAMethod,
+    // Set while the account is awaiting deletion and can still be restored
+    pub deleted_at: Option<DateTime<Utc>>,
+    // Auth tokens from an older generation are rejected, see `increment_token_generation`
+    pub token_generation: i64,
+    // Roles granted to the user and the permissions they grant, both end up in auth tokens
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+    // Set by an admin, disabled users can't sign in and their tokens are rejected
+    pub disabled: bool,
+    // Set by an admin, password logins are refused until the password has been changed
+    pub password_reset_required: bool,
 }
 
 impl User {
+    // New users have to confirm they own their email address before they can log in
     pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
         Self {
             email,
             password,
             requires_2fa,
+            email_verified: false,
+            two_fa_method: TwoFAMethod::Email,
+            deleted_at: None,
+            token_generation: 0,
+            roles: Vec::new(),
+            permissions: Vec::new(),
+            disabled: false,
+            password_reset_required: false,
+        }
+    }
+}
+
+// How the second factor is delivered for users who have 2FA enabled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TwoFAMethod {
+    #[default]
+    Email,
+    Totp,
+}
+
+impl TwoFAMethod {
+    pub fn parse(method: &str) -> Result<Self> {
+        match method {
+            "email" => Ok(Self::Email),
+            "totp" => Ok(Self::Totp),
+            _ => Err(eyre!("Invalid 2FA method: {}", method)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Totp => "totp",
         }
     }
 }
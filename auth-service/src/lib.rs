@@ -1,7 +1,9 @@
 use axum::{
-    http::{Method, StatusCode},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::{self, AddExtension},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post},
     serve::Serve,
     Json, Router,
 };
@@ -10,15 +12,23 @@ use redis::{Client, RedisResult};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::error::Error;
+use std::{error::Error, net::SocketAddr};
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
 
 use app_state::AppState;
-use utils::tracing::{make_span_with_request_id, on_request, on_response};
+use utils::{
+    constants::rate_limits,
+    csrf::{CsrfGuard, CSRF_HEADER_NAME},
+    rate_limit::RateLimiter,
+    tracing::{make_span_with_request_id, on_request, on_response},
+};
 
 // Struct encapsulates our application related logic
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
@@ -47,6 +57,40 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::AccountPendingDeletion => {
+                (StatusCode::FORBIDDEN, "Account pending deletion")
+            }
+            AuthAPIError::AccountLocked => (StatusCode::LOCKED, "Account temporarily locked"),
+            AuthAPIError::AccountDisabled => (StatusCode::FORBIDDEN, "Account disabled"),
+            AuthAPIError::PasswordResetRequired => {
+                (StatusCode::FORBIDDEN, "Password reset required")
+            }
+            AuthAPIError::InsufficientPermissions => {
+                (StatusCode::FORBIDDEN, "Insufficient permissions")
+            }
+            AuthAPIError::InsufficientScope => (StatusCode::FORBIDDEN, "Insufficient scope"),
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::TotpAlreadyEnabled => (StatusCode::CONFLICT, "TOTP already enabled"),
+            AuthAPIError::TwoFAAlreadyEnabled => (StatusCode::CONFLICT, "2FA already enabled"),
+            AuthAPIError::TwoFANotEnabled => (StatusCode::CONFLICT, "2FA not enabled"),
+            AuthAPIError::OAuthClientAlreadyExists => {
+                (StatusCode::CONFLICT, "OAuth client already exists")
+            }
+            // OAuth clients expect the error codes from RFC 6749 section 5.2
+            AuthAPIError::OAuthInvalidRequest => (StatusCode::BAD_REQUEST, "invalid_request"),
+            AuthAPIError::OAuthInvalidClient => (StatusCode::UNAUTHORIZED, "invalid_client"),
+            AuthAPIError::OAuthInvalidGrant => (StatusCode::BAD_REQUEST, "invalid_grant"),
+            AuthAPIError::OAuthUnsupportedGrantType => {
+                (StatusCode::BAD_REQUEST, "unsupported_grant_type")
+            }
+            AuthAPIError::OidcProviderNotFound => {
+                (StatusCode::NOT_FOUND, "Unknown identity provider")
+            }
+            AuthAPIError::OidcLoginFailed => (StatusCode::UNAUTHORIZED, "External login failed"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            AuthAPIError::CsrfCheckFailed => (StatusCode::FORBIDDEN, "CSRF check failed"),
             AuthAPIError::UnexpectedError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
@@ -75,18 +119,34 @@ fn log_error_chain(e: &(dyn Error + 'static)) {
 impl Application {
     pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
         // Allow the app service(running on our local machine and in production) to call the auth service
-        let allowed_origins = [
+        let allowed_origins: Vec<HeaderValue> = vec![
             "http://localhost:8000".parse()?,
             // TODO: Replace [YOUR_DROPLET_IP] with your Droplet IP address
             "http://[YOUR_DROPLET_IP]:8000".parse()?,
         ];
 
         let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
+            // Allow GET, POST and DELETE requests
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            // Allow JSON bodies and the CSRF token to be sent
+            .allow_headers([header::CONTENT_TYPE, HeaderName::from_static(CSRF_HEADER_NAME)])
             // Allow cookies to be included in requests
             .allow_credentials(true)
-            .allow_origin(allowed_origins);
+            .allow_origin(allowed_origins.clone());
+
+        // The app service checks tokens from its backend and OAuth clients redeem codes from
+        // theirs, neither relies on cookies
+        let csrf = middleware::from_fn_with_state(
+            CsrfGuard::new(allowed_origins, &["/verify-token", "/oauth/token"]),
+            utils::csrf::csrf_protect,
+        );
+
+        let rate_limit = |route, limits| {
+            middleware::from_fn_with_state(
+                RateLimiter::new(app_state.rate_limit_store.clone(), route, limits),
+                utils::rate_limit::rate_limit,
+            )
+        };
 
         let router = Router::new()
             .nest_service(
@@ -94,12 +154,91 @@ impl Application {
                 ServeDir::new("assets").append_index_html_on_directories(true),
             )
             // TODO: Add authentication routes
-            .route("/signup", post(routes::signup))
-            .route("/login", post(routes::login))
+            .route("/csrf-token", get(routes::csrf_token))
+            .route(
+                "/signup",
+                post(routes::signup).layer(rate_limit("signup", rate_limits::SIGNUP)),
+            )
+            .route(
+                "/login",
+                post(routes::login).layer(rate_limit("login", rate_limits::LOGIN)),
+            )
             .route("/logout", post(routes::logout))
-            .route("/verify-2fa", post(routes::verify_2fa))
+            .route("/logout-all", post(routes::logout_all))
+            .route(
+                "/login/magic-link",
+                post(routes::request_magic_link)
+                    .layer(rate_limit("magic-link", rate_limits::MAGIC_LINK)),
+            )
+            .route(
+                "/login/magic-link/consume",
+                post(routes::consume_magic_link),
+            )
+            .route("/login/oidc", get(routes::oidc_providers))
+            .route("/login/oidc/:provider", get(routes::oidc_login))
+            .route("/login/oidc/:provider/callback", get(routes::oidc_callback))
+            .route(
+                "/verify-2fa",
+                post(routes::verify_2fa).layer(rate_limit("verify-2fa", rate_limits::VERIFY_2FA)),
+            )
+            .route(
+                "/resend-2fa",
+                post(routes::resend_2fa).layer(rate_limit("resend-2fa", rate_limits::RESEND_2FA)),
+            )
             .route("/verify-token", post(routes::verify_token))
+            .route("/refresh", post(routes::refresh))
+            .route("/sessions", get(routes::list_sessions))
+            .route("/sessions/:id", delete(routes::revoke_session))
+            .route(
+                "/forgot-password",
+                post(routes::forgot_password)
+                    .layer(rate_limit("forgot-password", rate_limits::FORGOT_PASSWORD)),
+            )
+            .route("/reset-password", post(routes::reset_password))
+            .route("/change-password", post(routes::change_password))
+            .route("/delete-account", post(routes::delete_account))
+            .route("/restore-account", post(routes::restore_account))
+            .route("/totp/enroll", post(routes::enroll_totp))
+            .route("/totp/confirm", post(routes::confirm_totp))
+            .route(
+                "/recovery-codes/regenerate",
+                post(routes::regenerate_recovery_codes),
+            )
+            .route("/settings/2fa/enable", post(routes::enable_2fa))
+            .route("/settings/2fa/disable", post(routes::disable_2fa))
+            .route(
+                "/settings/2fa/disable/confirm",
+                post(routes::confirm_disable_2fa),
+            )
+            .route("/oauth/authorize", get(routes::oauth_authorize))
+            .route("/oauth/token", post(routes::oauth_token))
+            .route(
+                "/.well-known/openid-configuration",
+                get(routes::openid_configuration),
+            )
+            .route("/.well-known/jwks.json", get(routes::jwks))
+            .route("/userinfo", get(routes::userinfo))
+            .route("/admin/users", get(routes::admin_list_users))
+            .route("/admin/users/:email", get(routes::admin_get_user))
+            .route("/admin/users/:email/disable", post(routes::admin_disable_user))
+            .route("/admin/users/:email/enable", post(routes::admin_enable_user))
+            .route("/admin/users/:email/force-2fa", post(routes::admin_force_2fa))
+            .route(
+                "/admin/users/:email/force-password-reset",
+                post(routes::admin_force_password_reset),
+            )
+            .route("/admin/users/:email/revoke-tokens", post(routes::admin_revoke_tokens))
+            .route("/admin/oauth-clients", post(routes::admin_register_oauth_client))
+            .route("/verify-email", post(routes::verify_email))
+            .route(
+                "/resend-verification-email",
+                post(routes::resend_verification_email).layer(rate_limit(
+                    "resend-verification-email",
+                    rate_limits::RESEND_VERIFICATION_EMAIL,
+                )),
+            )
             .with_state(app_state)
+            .layer(csrf)
             .layer(cors)
             .layer(
                 // Add a TraceLayer for HTTP requests to enable detailed tracing
@@ -113,7 +252,11 @@ impl Application {
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // Sessions record the address they were started from
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         // Create a new Application instance and return it
         Ok(Self { server, address })
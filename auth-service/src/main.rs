@@ -3,21 +3,39 @@ use auth_service::{
     domain::Email,
     get_postgres_pool, get_redis_client,
     services::{
+        account_purge::run_account_purge,
         data_stores::{
+            postgres_oauth_client_store::PostgresOAuthClientStore,
+            postgres_recovery_code_store::PostgresRecoveryCodeStore,
+            postgres_totp_secret_store::PostgresTotpSecretStore,
             postgres_user_store::PostgresUserStore,
+            redis_authorization_code_store::RedisAuthorizationCodeStore,
             redis_banned_token_store::RedisBannedTokenStore,
-            redis_two_fa_code_store::RedisTwoFACodeStore,
+            redis_email_verification_token_store::RedisEmailVerificationTokenStore,
+            redis_login_lockout_store::RedisLoginLockoutStore,
+            redis_magic_link_token_store::RedisMagicLinkTokenStore,
+            redis_oidc_state_store::RedisOidcStateStore,
+            redis_password_reset_token_store::RedisPasswordResetTokenStore,
+            redis_rate_limit_store::RedisRateLimitStore,
+            redis_refresh_token_store::RedisRefreshTokenStore,
+            redis_session_store::RedisSessionStore,
+            redis_two_fa_code_store::{RedisTwoFACodeStore, DISABLE_2FA_NAMESPACE},
         },
+        oidc_client::{OidcClient, OidcProviderConfig},
         postmark_email_client::PostmarkEmailClient,
+        signing_key_reload::run_signing_key_reload,
     },
     utils::{
-        constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME},
+        constants::{
+            prod, ACCOUNT_DELETION_GRACE_PERIOD, DATABASE_URL, JWT_SIGNING_KEYS_DIR,
+            OIDC_PROVIDERS, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME, TOTP_ENCRYPTION_KEY,
+        },
         tracing::init_tracing,
     },
     Application,
 };
 use reqwest::Client;
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -34,20 +52,88 @@ async fn main() {
     // This is needed for Docker to work, which we will add later on.
     // See: https://stackoverflow.com/questions/39525820/docker-port-forwarding-not-working
 
+    let totp_secret_store = Arc::new(RwLock::new(PostgresTotpSecretStore::new(
+        pg_pool.clone(),
+        TOTP_ENCRYPTION_KEY.clone(),
+    ))) as Arc<RwLock<dyn auth_service::domain::TotpSecretStore + Send + Sync>>;
+    let recovery_code_store =
+        Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())))
+            as Arc<RwLock<dyn auth_service::domain::RecoveryCodeStore + Send + Sync>>;
+    let oauth_client_store = Arc::new(RwLock::new(PostgresOAuthClientStore::new(pg_pool.clone())))
+        as Arc<RwLock<dyn auth_service::domain::OAuthClientStore + Send + Sync>>;
     let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)))
         as Arc<RwLock<dyn auth_service::domain::UserStore + Send + Sync>>;
     let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())))
         as Arc<RwLock<dyn auth_service::domain::BannedTokenStore + Send + Sync>>;
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)))
+    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())))
         as Arc<RwLock<dyn auth_service::domain::TwoFACodeStore + Send + Sync>>;
+    let disable_2fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::with_namespace(
+        redis_conn.clone(),
+        DISABLE_2FA_NAMESPACE,
+    ))) as Arc<RwLock<dyn auth_service::domain::TwoFACodeStore + Send + Sync>>;
+    let refresh_token_store =
+        Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_conn.clone())))
+            as Arc<RwLock<dyn auth_service::domain::RefreshTokenStore + Send + Sync>>;
+    let password_reset_token_store =
+        Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(redis_conn.clone())))
+            as Arc<RwLock<dyn auth_service::domain::PasswordResetTokenStore + Send + Sync>>;
+    let email_verification_token_store =
+        Arc::new(RwLock::new(RedisEmailVerificationTokenStore::new(redis_conn.clone())))
+            as Arc<RwLock<dyn auth_service::domain::EmailVerificationTokenStore + Send + Sync>>;
+    let magic_link_token_store =
+        Arc::new(RwLock::new(RedisMagicLinkTokenStore::new(redis_conn.clone())))
+            as Arc<RwLock<dyn auth_service::domain::MagicLinkTokenStore + Send + Sync>>;
+    let authorization_code_store =
+        Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(redis_conn.clone())))
+            as Arc<RwLock<dyn auth_service::domain::AuthorizationCodeStore + Send + Sync>>;
+    let oidc_state_store = Arc::new(RwLock::new(RedisOidcStateStore::new(redis_conn.clone())))
+        as Arc<RwLock<dyn auth_service::domain::OidcStateStore + Send + Sync>>;
+    let session_store = Arc::new(RwLock::new(RedisSessionStore::new(redis_conn.clone())))
+        as Arc<RwLock<dyn auth_service::domain::SessionStore + Send + Sync>>;
+    let login_lockout_store =
+        Arc::new(RwLock::new(RedisLoginLockoutStore::new(redis_conn.clone())))
+            as Arc<RwLock<dyn auth_service::domain::LoginLockoutStore + Send + Sync>>;
+    // Rate limits must hold across replicas, so their buckets live in Redis
+    let rate_limit_store = Arc::new(RwLock::new(RedisRateLimitStore::new(redis_conn)))
+        as Arc<RwLock<dyn auth_service::domain::RateLimitStore + Send + Sync>>;
     let email_client = Arc::new(configure_postmark_email_client());
+    let oidc_client = Arc::new(configure_oidc_client());
     let app_state = AppState::new(
         user_store,
         banned_token_store,
         two_fa_code_store,
+        disable_2fa_code_store,
         email_client,
+        refresh_token_store,
+        password_reset_token_store,
+        email_verification_token_store,
+        totp_secret_store,
+        recovery_code_store,
+        magic_link_token_store,
+        oauth_client_store,
+        authorization_code_store,
+        oidc_state_store,
+        oidc_client,
+        session_store,
+        login_lockout_store,
+        rate_limit_store,
     );
 
+    // Hard-delete accounts once they can no longer be restored
+    tokio::spawn(run_account_purge(
+        app_state.clone(),
+        *ACCOUNT_DELETION_GRACE_PERIOD,
+        prod::ACCOUNT_PURGE_INTERVAL,
+    ));
+
+    // Pick up rotated signing keys without a restart
+    if let Some(dir) = JWT_SIGNING_KEYS_DIR.as_ref() {
+        tokio::spawn(run_signing_key_reload(
+            dir.clone(),
+            prod::SIGNING_KEY_RELOAD_INTERVAL,
+        ));
+    }
+
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
         .expect("Failed to build app");
@@ -91,6 +177,18 @@ fn configure_postmark_email_client() -> PostmarkEmailClient {
     )
 }
 
+fn configure_oidc_client() -> OidcClient {
+    let providers: Vec<OidcProviderConfig> = serde_json::from_str(OIDC_PROVIDERS.expose_secret())
+        .expect("OIDC_PROVIDERS must be a JSON array of providers");
+
+    let http_client = Client::builder()
+        .timeout(prod::oidc_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    OidcClient::new(providers, http_client)
+}
+
 // async fn hello_handler() -> Html<&'static str> {
 //// DONE: Update this to a custom message!
 //   Html("<h1>🦀 Hello, Rustaceans! 🦀</h1>")
@@ -0,0 +1,337 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, OAuthClient, OAuthClientSecret, OAuthClientStoreError, User,
+        UserStoreError,
+    },
+    routes::{notify_2fa_change, revoke_all_tokens, send_password_reset_token},
+    utils::{
+        admin::Admin,
+        constants::{permissions, DEFAULT_ADMIN_USERS_PAGE_SIZE, MAX_ADMIN_USERS_PAGE_SIZE},
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ListUsersQuery {
+    // Only users whose email contains this, ignoring case
+    pub search: Option<String>,
+    // Pages start at 1
+    pub page: Option<u64>,
+    #[serde(rename = "perPage")]
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListUsersResponse {
+    pub users: Vec<AdminUserResponse>,
+    pub page: u64,
+    #[serde(rename = "perPage")]
+    pub per_page: u64,
+    // Users matching the search across all pages
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminUserResponse {
+    pub email: String,
+    #[serde(rename = "emailVerified")]
+    pub email_verified: bool,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: String,
+    pub roles: Vec<String>,
+    pub disabled: bool,
+    #[serde(rename = "passwordResetRequired")]
+    pub password_reset_required: bool,
+    // RFC 3339 timestamp, set while the account is awaiting deletion
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<String>,
+}
+
+impl From<User> for AdminUserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            email: user.email.as_ref().expose_secret().to_owned(),
+            email_verified: user.email_verified,
+            requires_2fa: user.requires_2fa,
+            two_fa_method: user.two_fa_method.as_str().to_owned(),
+            roles: user.roles,
+            disabled: user.disabled,
+            password_reset_required: user.password_reset_required,
+            deleted_at: user.deleted_at.map(|deleted_at| deleted_at.to_rfc3339()),
+        }
+    }
+}
+
+#[tracing::instrument(name = "Admin list users", skip_all)]
+pub async fn admin_list_users(
+    State(state): State<AppState>,
+    admin: Admin,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    admin.require(permissions::USERS_READ)?;
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_ADMIN_USERS_PAGE_SIZE)
+        .clamp(1, MAX_ADMIN_USERS_PAGE_SIZE);
+    let search = query.search.as_deref().filter(|search| !search.is_empty());
+
+    let user_page = state
+        .user_store
+        .read()
+        .await
+        .list_users(search, (page - 1).saturating_mul(per_page), per_page)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(ListUsersResponse {
+        users: user_page.users.into_iter().map(Into::into).collect(),
+        page,
+        per_page,
+        total: user_page.total,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Admin get user", skip_all)]
+pub async fn admin_get_user(
+    State(state): State<AppState>,
+    admin: Admin,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    admin.require(permissions::USERS_READ)?;
+
+    let user = get_user(&parse_email(email)?, &state).await?;
+
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// Disabled users are signed out everywhere and can't sign in until they are enabled again
+#[tracing::instrument(name = "Admin disable user", skip_all)]
+pub async fn admin_disable_user(
+    State(state): State<AppState>,
+    admin: Admin,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    admin.require(permissions::USERS_WRITE)?;
+    let email = parse_email(email)?;
+
+    set_disabled(&email, true, &state).await?;
+    revoke_all_tokens(&email, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let user = get_user(&email, &state).await?;
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+#[tracing::instrument(name = "Admin enable user", skip_all)]
+pub async fn admin_enable_user(
+    State(state): State<AppState>,
+    admin: Admin,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    admin.require(permissions::USERS_WRITE)?;
+    let email = parse_email(email)?;
+
+    set_disabled(&email, false, &state).await?;
+
+    let user = get_user(&email, &state).await?;
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// Turns 2FA on with the method the user had before, email codes unless they set up TOTP. Sessions
+// signed in without a second factor are ended.
+#[tracing::instrument(name = "Admin force 2FA", skip_all)]
+pub async fn admin_force_2fa(
+    State(state): State<AppState>,
+    admin: Admin,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    admin.require(permissions::USERS_WRITE)?;
+    let email = parse_email(email)?;
+
+    if !get_user(&email, &state).await?.requires_2fa {
+        state
+            .user_store
+            .write()
+            .await
+            .set_requires_2fa(&email, true)
+            .await
+            .map_err(map_user_store_error)?;
+        revoke_all_tokens(&email, &state)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+        notify_2fa_change(&email, true, &state).await;
+    }
+
+    let user = get_user(&email, &state).await?;
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// Signs the user out everywhere and refuses password logins until a new password is set, e.g.
+// with the reset token emailed to the user
+#[tracing::instrument(name = "Admin force password reset", skip_all)]
+pub async fn admin_force_password_reset(
+    State(state): State<AppState>,
+    admin: Admin,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    admin.require(permissions::USERS_WRITE)?;
+    let email = parse_email(email)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_password_reset_required(&email, true)
+        .await
+        .map_err(map_user_store_error)?;
+    revoke_all_tokens(&email, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    // The reset stands even if the email can't be sent, the user can ask for another token
+    if let Err(e) = send_password_reset_token(&email, &state).await {
+        tracing::error!("failed to send password reset token: {:?}", e);
+    }
+
+    let user = get_user(&email, &state).await?;
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// Invalidates every auth and refresh token of the user, like /logout-all
+#[tracing::instrument(name = "Admin revoke tokens", skip_all)]
+pub async fn admin_revoke_tokens(
+    State(state): State<AppState>,
+    admin: Admin,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    admin.require(permissions::USERS_WRITE)?;
+    let email = parse_email(email)?;
+
+    // Checked first, revoking refresh tokens and sessions of unknown users would succeed
+    get_user(&email, &state).await?;
+    revoke_all_tokens(&email, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterOAuthClientRequest {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "redirectUris")]
+    pub redirect_uris: Vec<String>,
+    pub confidential: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterOAuthClientResponse {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "redirectUris")]
+    pub redirect_uris: Vec<String>,
+    pub confidential: bool,
+    // Only stored as a hash, so this is the one time it can be read
+    #[serde(rename = "clientSecret", skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
+
+// Confidential clients get a generated secret, public clients authenticate with PKCE alone
+#[tracing::instrument(name = "Admin register OAuth client", skip_all)]
+pub async fn admin_register_oauth_client(
+    State(state): State<AppState>,
+    admin: Admin,
+    Json(request): Json<RegisterOAuthClientRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    admin.require(permissions::OAUTH_CLIENTS_WRITE)?;
+
+    // Redirect URIs must be absolute and may not have a fragment, see RFC 6749 section 3.1.2
+    let valid_redirect_uri =
+        |uri: &String| Url::parse(uri).is_ok_and(|url| url.fragment().is_none());
+
+    if request.client_id.trim().is_empty()
+        || request.redirect_uris.is_empty()
+        || !request.redirect_uris.iter().all(valid_redirect_uri)
+    {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let client = OAuthClient {
+        client_id: request.client_id,
+        redirect_uris: request.redirect_uris,
+        confidential: request.confidential,
+    };
+    let client_secret = client.confidential.then(OAuthClientSecret::default);
+
+    match state
+        .oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone(), client_secret.clone())
+        .await
+    {
+        Ok(()) => {}
+        Err(OAuthClientStoreError::ClientAlreadyExists) => {
+            return Err(AuthAPIError::OAuthClientAlreadyExists)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(RegisterOAuthClientResponse {
+        client_id: client.client_id,
+        redirect_uris: client.redirect_uris,
+        confidential: client.confidential,
+        client_secret: client_secret.map(|secret| secret.as_ref().expose_secret().to_owned()),
+    });
+
+    Ok((StatusCode::CREATED, response))
+}
+
+fn parse_email(email: String) -> Result<Email, AuthAPIError> {
+    Email::parse(Secret::new(email)).map_err(|_| AuthAPIError::InvalidCredentials)
+}
+
+fn map_user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+async fn get_user(email: &Email, state: &AppState) -> Result<User, AuthAPIError> {
+    state
+        .user_store
+        .read()
+        .await
+        .get_user(email)
+        .await
+        .map_err(map_user_store_error)
+}
+
+async fn set_disabled(email: &Email, disabled: bool, state: &AppState) -> Result<(), AuthAPIError> {
+    state
+        .user_store
+        .write()
+        .await
+        .set_disabled(email, disabled)
+        .await
+        .map_err(map_user_store_error)
+}
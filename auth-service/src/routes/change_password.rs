@@ -0,0 +1,88 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Password, UserStoreError},
+    routes::revoke_all_tokens,
+    utils::{auth::start_session, authenticated_user::AuthenticatedUser, client_info::ClientInfo},
+};
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    user: AuthenticatedUser,
+    Json(request): Json<ChangePasswordRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    if let Err(e) = user.require_full_access() {
+        return (jar, Err(e));
+    }
+
+    let email = user.email;
+
+    let current_password = match Password::parse(request.current_password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    let new_password = match Password::parse(request.new_password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    {
+        let mut user_store = state.user_store.write().await;
+
+        if let Err(e) = user_store.validate_user(&email, &current_password).await {
+            match e {
+                UserStoreError::UserNotFound | UserStoreError::InvalidCredentials => {
+                    return (jar, Err(AuthAPIError::IncorrectCredentials))
+                }
+                _ => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+            }
+        }
+
+        if let Err(e) = user_store.update_password(&email, new_password).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+    }
+
+    // Every token issued before the change stops working, including the one used here
+    if let Err(e) = revoke_all_tokens(&email, &state).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // Keep the caller signed in with a fresh session
+    let (auth_cookie, refresh_cookie) = match start_session(
+        &user,
+        client,
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
+        Ok(cookies) => cookies,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
@@ -0,0 +1,27 @@
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{domain::CsrfToken, utils::constants::AUTH_COOKIE_POLICY};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CsrfTokenResponse {
+    #[serde(rename = "csrfToken")]
+    pub csrf_token: String,
+}
+
+// Hands out the token pages send back in the `X-CSRF-Token` header, see `utils::csrf`. A token
+// the browser already has is kept, so pages open in other tabs keep working.
+#[tracing::instrument(name = "CSRF token", skip_all)]
+pub async fn csrf_token(jar: CookieJar) -> (CookieJar, Json<CsrfTokenResponse>) {
+    let token = jar
+        .get(AUTH_COOKIE_POLICY.csrf_cookie_name())
+        .and_then(|cookie| CsrfToken::parse(Secret::new(cookie.value().to_owned())).ok())
+        .unwrap_or_default();
+    let token = token.as_ref().expose_secret().to_owned();
+
+    let jar = jar.add(AUTH_COOKIE_POLICY.csrf_cookie(token.clone()));
+
+    (jar, Json(CsrfTokenResponse { csrf_token: token }))
+}
@@ -0,0 +1,122 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, Password, UserStoreError},
+    routes::revoke_all_tokens,
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        constants::{ACCOUNT_DELETION_GRACE_PERIOD, AUTH_COOKIE_POLICY},
+    },
+};
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: Secret<String>,
+}
+
+#[tracing::instrument(name = "Delete account", skip_all)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    user: AuthenticatedUser,
+    Json(request): Json<DeleteAccountRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    if let Err(e) = user.require_full_access() {
+        return (jar, Err(e));
+    }
+
+    let email = user.email;
+
+    let password = match Password::parse(request.password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    {
+        let mut user_store = state.user_store.write().await;
+
+        if let Err(e) = user_store.validate_user(&email, &password).await {
+            match e {
+                UserStoreError::UserNotFound | UserStoreError::InvalidCredentials => {
+                    return (jar, Err(AuthAPIError::IncorrectCredentials))
+                }
+                _ => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+            }
+        }
+
+        match user_store.delete_user(&email).await {
+            Ok(()) => {}
+            // The account was already deleted from another session
+            Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
+    }
+
+    // Sign out every session and invalidate every token, including the one used for this request
+    if let Err(e) = revoke_all_tokens(&email, &state).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    let jar = AUTH_COOKIE_POLICY.remove_cookies(jar);
+
+    (jar, Ok(StatusCode::OK))
+}
+
+#[derive(Deserialize)]
+pub struct RestoreAccountRequest {
+    pub email: Secret<String>,
+    pub password: Secret<String>,
+}
+
+#[tracing::instrument(name = "Restore account", skip_all)]
+pub async fn restore_account(
+    State(state): State<AppState>,
+    Json(request): Json<RestoreAccountRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = match Email::parse(request.email) {
+        Ok(email) => email,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let password = match Password::parse(request.password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let mut user_store = state.user_store.write().await;
+
+    if let Err(e) = user_store.validate_user(&email, &password).await {
+        match e {
+            UserStoreError::UserNotFound | UserStoreError::InvalidCredentials => {
+                return Err(AuthAPIError::IncorrectCredentials)
+            }
+            _ => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+    }
+
+    let user = match user_store.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    match user.deleted_at {
+        // Nothing to restore
+        None => return Ok(StatusCode::OK),
+        // Past the grace period the account is only waiting to be purged
+        Some(deleted_at) if deleted_at + *ACCOUNT_DELETION_GRACE_PERIOD <= Utc::now() => {
+            return Err(AuthAPIError::IncorrectCredentials)
+        }
+        Some(_) => {}
+    }
+
+    if let Err(e) = user_store.restore_user(&email).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    Ok(StatusCode::OK)
+}
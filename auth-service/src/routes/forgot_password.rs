@@ -0,0 +1,84 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, PasswordResetToken, UserStoreError},
+};
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ForgotPasswordResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Forgot password", skip_all)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = match Email::parse(request.email) {
+        Ok(email) => email,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    // The response must not reveal whether an account exists for the given email, so every
+    // failure past this point is logged and swallowed. The token is created and sent in the
+    // background, otherwise the response time would give existing accounts away.
+    match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if user.deleted_at.is_some() => {}
+        Ok(_) => {
+            let state = state.clone();
+            tokio::spawn(
+                async move {
+                    if let Err(e) = send_password_reset_token(&email, &state).await {
+                        tracing::error!("failed to send password reset token: {:?}", e);
+                    }
+                }
+                .in_current_span(),
+            );
+        }
+        Err(UserStoreError::UserNotFound) => {}
+        Err(e) => tracing::error!("failed to look up user for password reset: {:?}", e),
+    }
+
+    let response = Json(ForgotPasswordResponse {
+        message: "If an account exists for this email, a password reset token has been sent."
+            .to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Send password reset token", skip_all)]
+pub(crate) async fn send_password_reset_token(
+    email: &Email,
+    state: &AppState,
+) -> color_eyre::Result<()> {
+    let token = PasswordResetToken::default();
+
+    state
+        .password_reset_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await?;
+
+    state
+        .email_client
+        .send_email(
+            email,
+            "Password reset",
+            &format!(
+                "Use the following token to reset your password: {}",
+                token.as_ref().expose_secret()
+            ),
+        )
+        .await
+}
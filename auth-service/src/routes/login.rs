@@ -1,24 +1,35 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
-use serde::{Deserialize, Serialize};
+use chrono::Utc;
 use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode, UserStoreError},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuthAPIError, Email, LoginAttemptId, Password, TwoFACode, TwoFAMethod, User, UserStoreError,
+    },
+    utils::{
+        auth::{start_bearer_session, start_session, TOKEN_TTL_SECONDS},
+        client_info::ClientInfo,
+        constants::LOGIN_LOCKOUT_THRESHOLD,
+        lockout::lockout_duration,
+    },
 };
 
 #[derive(Deserialize)]
 pub struct LoginRequest {
     email: String,
     password: Secret<String>,
+    #[serde(default, rename = "tokenDelivery")]
+    token_delivery: TokenDelivery,
 }
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
     jar: CookieJar, // New!
+    client: ClientInfo,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     // Parse email and password
@@ -32,17 +43,40 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
+    // Locked accounts are rejected before the password is even checked
+    match state
+        .login_lockout_store
+        .read()
+        .await
+        .get_lockout(&email)
+        .await
+    {
+        Ok(None) => {}
+        Ok(Some(_)) => return (jar, Err(AuthAPIError::AccountLocked)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
     let user_store = &state.user_store.read().await;
 
     // Validate user credentials
     if let Err(e) = user_store.validate_user(&email, &password).await {
-        match e {
-            UserStoreError::UserNotFound => return (jar, Err(AuthAPIError::IncorrectCredentials)),
-            UserStoreError::InvalidCredentials => {
-                return (jar, Err(AuthAPIError::IncorrectCredentials))
-            }
+        // Unknown emails count too, so a lockout doesn't reveal whether an account exists
+        let user_exists = match e {
+            UserStoreError::UserNotFound => false,
+            UserStoreError::InvalidCredentials => true,
             _ => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
-        }
+        };
+
+        let error = match record_failed_login(&email, user_exists, &state).await {
+            Ok(true) => AuthAPIError::AccountLocked,
+            Ok(false) => AuthAPIError::IncorrectCredentials,
+            Err(e) => AuthAPIError::UnexpectedError(e),
+        };
+        return (jar, Err(error));
+    }
+
+    if let Err(e) = state.login_lockout_store.write().await.reset(&email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
     let user = match user_store.get_user(&email).await {
@@ -50,24 +84,81 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
+    if user.deleted_at.is_some() {
+        return (jar, Err(AuthAPIError::AccountPendingDeletion));
+    }
+
+    if user.disabled {
+        return (jar, Err(AuthAPIError::AccountDisabled));
+    }
+
+    if !user.email_verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
     // Handle request based on user's 2FA configuration
     match user.requires_2fa {
-        // We are now passing `&user.email` and `&state` to `handle_2fa`
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+        // We are now passing `&user` and `&state` to `handle_2fa`
+        true => handle_2fa(&user, &state, jar).await,
+        false => handle_no_2fa(&user, &state, client, request.token_delivery, jar).await,
+    }
+}
+
+// Counts a failed login and locks the account once there have been too many in a row. Returns
+// whether the account is now locked.
+#[tracing::instrument(name = "Record failed login", skip_all)]
+async fn record_failed_login(
+    email: &Email,
+    user_exists: bool,
+    state: &AppState,
+) -> color_eyre::Result<bool> {
+    let mut login_lockout_store = state.login_lockout_store.write().await;
+
+    let failed_attempts = login_lockout_store.add_failed_attempt(email).await?;
+    let Some(duration) = lockout_duration(failed_attempts, *LOGIN_LOCKOUT_THRESHOLD) else {
+        return Ok(false);
+    };
+
+    let locked_until = Utc::now() + duration;
+    login_lockout_store.lock(email, locked_until).await?;
+    drop(login_lockout_store);
+
+    // Only the first lockout is emailed, later ones are part of the same guessing attempt
+    if user_exists && failed_attempts == *LOGIN_LOCKOUT_THRESHOLD {
+        let content = format!(
+            "Your account has been locked after too many failed sign-in attempts. \
+            You can sign in again after {}, or reset your password to unlock it right away.",
+            locked_until.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+
+        // The lockout stands even if the user can't be told about it
+        if let Err(e) = state
+            .email_client
+            .send_email(email, "Account locked", &content)
+            .await
+        {
+            tracing::error!("failed to send account lockout email: {:?}", e);
+        }
     }
+
+    Ok(true)
 }
 
 #[tracing::instrument(name = "Handle 2FA", skip_all)]
-async fn handle_2fa(
-    email: &Email,    // New!
+pub(crate) async fn handle_2fa(
+    user: &User,
     state: &AppState, // New!
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    // First, we must generate a new random login attempt ID and 2FA code
+    if let Err(e) = check_password_reset(user) {
+        return (jar, Err(e));
+    }
+
+    // First, we must generate a new random login attempt ID and 2FA code. The code is stored
+    // under the login attempt ID, so pending logins on the user's other devices stay valid.
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFACode::default();
 
@@ -75,18 +166,21 @@ async fn handle_2fa(
         .two_fa_code_store
         .write()
         .await
-        .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
+        .add_code(user.email.clone(), login_attempt_id.clone(), two_fa_code.clone())
         .await
     {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    if let Err(e) = state
-        .email_client
-        .send_email(email, "2FA Code", two_fa_code.as_ref().expose_secret())
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    // TOTP users read the code from their authenticator app instead
+    if user.two_fa_method == TwoFAMethod::Email {
+        if let Err(e) = state
+            .email_client
+            .send_email(&user.email, "2FA Code", two_fa_code.as_ref().expose_secret())
+            .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)));
+        }
     }
 
     // Finally, we need to return the login attempt ID to the client
@@ -99,25 +193,55 @@ async fn handle_2fa(
 }
 
 #[tracing::instrument(name = "Handle no 2FA", skip_all)]
-async fn handle_no_2fa(
-    email: &Email,
+pub(crate) async fn handle_no_2fa(
+    user: &User,
+    state: &AppState,
+    client: ClientInfo,
+    token_delivery: TokenDelivery,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(email) {
-        Ok(cookie) => cookie,
+    if let Err(e) = check_password_reset(user) {
+        return (jar, Err(e));
+    }
+
+    if token_delivery == TokenDelivery::Body {
+        let result = issue_bearer_token(user, client, state)
+            .await
+            .map(|response| (StatusCode::OK, Json(LoginResponse::Token(response))));
+        return (jar, result);
+    }
+
+    let (auth_cookie, refresh_cookie) = match start_session(
+        user,
+        client,
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
+        Ok(cookies) => cookies,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
     (
         updated_jar,
         Ok((StatusCode::OK, Json(LoginResponse::RegularAuth))),
     )
 }
 
+// Checked on every way of signing in, the password may be known to someone else and any session
+// would outlive the reset an admin asked for. A new password can be set with /forgot-password.
+fn check_password_reset(user: &User) -> Result<(), AuthAPIError> {
+    match user.password_reset_required {
+        true => Err(AuthAPIError::PasswordResetRequired),
+        false => Ok(()),
+    }
+}
+
 // The login route can return 2 possible success responses.
 // This enum models each response!
 #[derive(Debug, Serialize)]
@@ -125,6 +249,7 @@ async fn handle_no_2fa(
 pub enum LoginResponse {
     RegularAuth,
     TwoFactorAuth(TwoFactorAuthResponse),
+    Token(BearerTokenResponse),
 }
 
 // If a user requires 2FA, this JSON body should be returned!
@@ -134,3 +259,41 @@ pub struct TwoFactorAuthResponse {
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
 }
+
+// How the auth token is handed to the client once it is signed in. Browsers get cookies, while
+// API clients and native apps can ask for the token in the response body and send it back in the
+// `Authorization: Bearer` header.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenDelivery {
+    #[default]
+    Cookie,
+    Body,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BearerTokenResponse {
+    #[serde(rename = "accessToken")]
+    pub access_token: String,
+    #[serde(rename = "tokenType")]
+    pub token_type: String,
+    #[serde(rename = "expiresIn")]
+    pub expires_in: i64,
+}
+
+#[tracing::instrument(name = "Issue bearer token", skip_all)]
+pub(crate) async fn issue_bearer_token(
+    user: &User,
+    client: ClientInfo,
+    state: &AppState,
+) -> Result<BearerTokenResponse, AuthAPIError> {
+    let access_token = start_bearer_session(user, client, state.session_store.clone())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(BearerTokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+    })
+}
@@ -1,37 +1,106 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse};
-use axum_extra::extract::cookie;
 use axum_extra::extract::CookieJar;
 use secrecy::Secret;
 
-use crate::{app_state::AppState, domain::AuthAPIError, utils::constants::JWT_COOKIE_NAME};
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, RefreshToken, RefreshTokenStoreError, SessionStoreError},
+    utils::{authenticated_user::AuthenticatedUser, constants::AUTH_COOKIE_POLICY},
+};
 
 #[tracing::instrument(name = "Logout", skip_all)]
 pub async fn logout(
     State(state): State<AppState>,
     jar: CookieJar,
+    user: AuthenticatedUser,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    // Retrieve JWT cookie from the `CookieJar`
-    // Return AuthAPIError::MissingToken is the cookie is not found
-    let Some(cookie) = jar.get(JWT_COOKIE_NAME) else {
-        return (jar, Err(AuthAPIError::MissingToken));
-    };
-
-    let token = cookie.value().to_owned();
-
-    // Validate JWT token and check if it's banned
-    use crate::utils::auth::validate_token;
-    if let Err(_) = validate_token(&token, state.banned_token_store.clone()).await {
-        return (jar, Err(AuthAPIError::InvalidToken));
-    }
-
     // Add token to banned token store
     let mut banned_store = state.banned_token_store.write().await;
-    if let Err(e) = banned_store.add_token(Secret::new(token)).await {
+    if let Err(e) = banned_store.add_token(Secret::new(user.token.value)).await {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    // Remove JWT cookie from the CookieJar
-    let jar = jar.remove(cookie::Cookie::build(JWT_COOKIE_NAME).build());
+    // Revoke the refresh token family so the session can't be silently resumed
+    if let Some(refresh_cookie) = jar.get(AUTH_COOKIE_POLICY.refresh_cookie_name()) {
+        if let Ok(refresh_token) =
+            RefreshToken::parse(Secret::new(refresh_cookie.value().to_owned()))
+        {
+            let mut refresh_token_store = state.refresh_token_store.write().await;
+            let family = match refresh_token_store.use_token(&refresh_token).await {
+                Ok(family) | Err(RefreshTokenStoreError::TokenReused(family)) => Some(family),
+                Err(
+                    RefreshTokenStoreError::TokenNotFound | RefreshTokenStoreError::FamilyRevoked,
+                ) => None,
+                Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+            };
+
+            if let Some(family) = family {
+                if let Err(e) = refresh_token_store.revoke_family(&family).await {
+                    return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+                }
+            }
+        }
+    }
+
+    // End the session, so it no longer shows up in the session list
+    if let Some(sid) = user.claims.sid {
+        match state.session_store.write().await.revoke_session(&sid).await {
+            Ok(()) | Err(SessionStoreError::SessionNotFound) => {}
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
+    }
+
+    // Remove JWT and refresh token cookies from the CookieJar
+    let jar = AUTH_COOKIE_POLICY.remove_cookies(jar);
 
     (jar, Ok(StatusCode::OK))
 }
+
+// Signs the user out everywhere, see `revoke_all_tokens`
+#[tracing::instrument(name = "Logout all", skip_all)]
+pub async fn logout_all(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    user: AuthenticatedUser,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    if let Err(e) = user.require_full_access() {
+        return (jar, Err(e));
+    }
+
+    if let Err(e) = revoke_all_tokens(&user.email, &state).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    let jar = AUTH_COOKIE_POLICY.remove_cookies(jar);
+
+    (jar, Ok(StatusCode::OK))
+}
+
+// Bumping the token generation invalidates every auth token issued so far, including ones that
+// aren't tied to a session such as OAuth access tokens
+#[tracing::instrument(name = "Revoke all tokens", skip_all)]
+pub(crate) async fn revoke_all_tokens(email: &Email, state: &AppState) -> color_eyre::Result<()> {
+    state
+        .user_store
+        .write()
+        .await
+        .increment_token_generation(email)
+        .await?;
+
+    // Refresh tokens are not covered by the generation, so revoke them along with the sessions
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_all_families(email)
+        .await?;
+
+    state
+        .session_store
+        .write()
+        .await
+        .revoke_all_sessions(email)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Context;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, MagicLinkToken, MagicLinkTokenStoreError, UserStoreError},
+    routes::{handle_2fa, handle_no_2fa, TokenDelivery},
+    utils::{client_info::ClientInfo, constants::AUTH_SERVICE_URL},
+};
+
+#[derive(Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MagicLinkResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Request magic link", skip_all)]
+pub async fn request_magic_link(
+    State(state): State<AppState>,
+    Json(request): Json<MagicLinkRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = match Email::parse(request.email) {
+        Ok(email) => email,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    // The response must not reveal whether an account exists for the given email,
+    // so every failure past this point is logged and swallowed.
+    match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if user.deleted_at.is_some() || !user.email_verified => {}
+        Ok(_) => {
+            if let Err(e) = send_magic_link(&email, &state).await {
+                tracing::error!("failed to send magic link: {:?}", e);
+            }
+        }
+        Err(UserStoreError::UserNotFound) => {}
+        Err(e) => tracing::error!("failed to look up user for magic link: {:?}", e),
+    }
+
+    let response = Json(MagicLinkResponse {
+        message: "If an account exists for this email, a sign-in link has been sent.".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ConsumeMagicLinkRequest {
+    pub token: Secret<String>,
+}
+
+// Signs the user in like a password login, so 2FA users continue into /verify-2fa
+#[tracing::instrument(name = "Consume magic link", skip_all)]
+pub async fn consume_magic_link(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(request): Json<ConsumeMagicLinkRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let token = match MagicLinkToken::parse(request.token) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let email = match state
+        .magic_link_token_store
+        .write()
+        .await
+        .use_token(&token)
+        .await
+    {
+        Ok(email) => email,
+        Err(MagicLinkTokenStoreError::TokenNotFound) => {
+            return (jar, Err(AuthAPIError::InvalidToken))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // The account may have been deleted after the link was sent
+    if user.deleted_at.is_some() {
+        return (jar, Err(AuthAPIError::AccountPendingDeletion));
+    }
+
+    if user.disabled {
+        return (jar, Err(AuthAPIError::AccountDisabled));
+    }
+
+    match user.requires_2fa {
+        true => handle_2fa(&user, &state, jar).await,
+        false => handle_no_2fa(&user, &state, client, TokenDelivery::Cookie, jar).await,
+    }
+}
+
+#[tracing::instrument(name = "Send magic link", skip_all)]
+async fn send_magic_link(email: &Email, state: &AppState) -> color_eyre::Result<()> {
+    let token = MagicLinkToken::default();
+
+    state
+        .magic_link_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await?;
+
+    // The login page needs the email to continue into /verify-2fa for 2FA users
+    let link = Url::parse_with_params(
+        &format!("{}/", AUTH_SERVICE_URL.as_str()),
+        &[
+            ("email", email.as_ref().expose_secret()),
+            ("magic_link_token", token.as_ref().expose_secret()),
+        ],
+    )
+    .wrap_err("failed to build magic link")?;
+
+    state
+        .email_client
+        .send_email(
+            email,
+            "Sign-in link",
+            &format!(
+                "Use the following link to sign in, it works once and expires in 10 minutes: {}",
+                link
+            ),
+        )
+        .await
+}
@@ -1,12 +1,44 @@
+mod admin;
+mod change_password;
+mod csrf_token;
+mod delete_account;
+mod forgot_password;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod oidc;
+mod oidc_login;
+mod recovery_codes;
+mod refresh;
+mod reset_password;
+mod sessions;
 mod signup;
+mod totp;
+mod two_fa_settings;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
 
 // We need to re-export these items from sub-modules
+pub use admin::*;
+pub use change_password::*;
+pub use csrf_token::*;
+pub use delete_account::*;
+pub use forgot_password::*;
 pub use login::*;
 pub use logout::*;
+pub use magic_link::*;
+pub use oauth::*;
+pub use oidc::*;
+pub use oidc_login::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use reset_password::*;
+pub use sessions::*;
 pub use signup::*;
+pub use totp::*;
+pub use two_fa_settings::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
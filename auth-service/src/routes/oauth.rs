@@ -0,0 +1,293 @@
+use axum::{
+    extract::{OriginalUri, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect},
+    Form, Json,
+};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Context;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, AuthorizationCode, AuthorizationCodeStoreError, AuthorizationGrant, Email,
+        OAuthClientSecret, OAuthClientStoreError, UserStoreError,
+    },
+    utils::{
+        auth::{generate_id_token, generate_oauth_access_token, validate_token, TOKEN_TTL_SECONDS},
+        constants::{AUTH_COOKIE_POLICY, AUTH_SERVICE_URL},
+        pkce::{is_valid_code_challenge, verify_code_challenge, PKCE_METHOD_S256},
+    },
+};
+
+#[derive(Deserialize)]
+pub struct AuthorizeRequest {
+    pub response_type: Option<String>,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub state: Option<String>,
+    pub scope: Option<String>,
+    pub nonce: Option<String>,
+}
+
+// Authorization endpoint of the authorization code grant. Users sign in through the regular
+// login page, which sends them back here once they have a `jwt` cookie. Clients are first-party,
+// so there is no consent screen.
+#[tracing::instrument(name = "OAuth authorize", skip_all)]
+pub async fn oauth_authorize(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    OriginalUri(uri): OriginalUri,
+    Query(request): Query<AuthorizeRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let client = match state
+        .oauth_client_store
+        .read()
+        .await
+        .get_client(&request.client_id)
+        .await
+    {
+        Ok(client) => client,
+        Err(OAuthClientStoreError::ClientNotFound) => return Err(AuthAPIError::OAuthInvalidClient),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // Errors are only sent back to the client once the redirect URI is known to be registered
+    if !client.redirect_uris.contains(&request.redirect_uri) {
+        return Err(AuthAPIError::OAuthInvalidRequest);
+    }
+
+    let redirect_with_error = |error: &str| {
+        redirect_to_client(
+            &request.redirect_uri,
+            &[("error", error)],
+            request.state.as_deref(),
+        )
+    };
+
+    if request.response_type.as_deref() != Some("code") {
+        return redirect_with_error("unsupported_response_type");
+    }
+
+    let code_challenge = match (&request.code_challenge, &request.code_challenge_method) {
+        (Some(challenge), Some(method))
+            if method == PKCE_METHOD_S256 && is_valid_code_challenge(challenge) =>
+        {
+            challenge.to_owned()
+        }
+        _ => return redirect_with_error("invalid_request"),
+    };
+
+    let Some(email) = authenticated_email(&jar, &state).await else {
+        return redirect_to_login(&uri.to_string());
+    };
+
+    let code = AuthorizationCode::default();
+    let grant = AuthorizationGrant {
+        client_id: client.client_id,
+        redirect_uri: request.redirect_uri.clone(),
+        email,
+        code_challenge,
+        scope: request.scope.clone(),
+        nonce: request.nonce.clone(),
+    };
+
+    if let Err(e) = state
+        .authorization_code_store
+        .write()
+        .await
+        .add_code(code.clone(), grant)
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    redirect_to_client(
+        &request.redirect_uri,
+        &[("code", code.as_ref().expose_secret())],
+        request.state.as_deref(),
+    )
+}
+
+#[derive(Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: Secret<String>,
+    pub redirect_uri: String,
+    pub client_id: String,
+    pub code_verifier: Secret<String>,
+    // Only sent by confidential clients
+    pub client_secret: Option<Secret<String>>,
+}
+
+// Field names follow RFC 6749 section 5.1 rather than the camelCase used elsewhere
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    // Only issued when the `openid` scope was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+// Token endpoint. Exchanges an authorization code and its PKCE verifier for an access token,
+// which is the same JWT the `jwt` cookie carries, and an ID token for OpenID Connect clients.
+#[tracing::instrument(name = "OAuth token", skip_all)]
+pub async fn oauth_token(
+    State(state): State<AppState>,
+    Form(request): Form<TokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    if request.grant_type != "authorization_code" {
+        return Err(AuthAPIError::OAuthUnsupportedGrantType);
+    }
+
+    authenticate_client(&request.client_id, request.client_secret, &state).await?;
+
+    let code = match AuthorizationCode::parse(request.code) {
+        Ok(code) => code,
+        Err(_) => return Err(AuthAPIError::OAuthInvalidGrant),
+    };
+
+    // The code is consumed before any other check, so a failed exchange can't be retried
+    let grant = match state
+        .authorization_code_store
+        .write()
+        .await
+        .use_code(&code)
+        .await
+    {
+        Ok(grant) => grant,
+        Err(AuthorizationCodeStoreError::CodeNotFound) => {
+            return Err(AuthAPIError::OAuthInvalidGrant)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if grant.client_id != request.client_id
+        || grant.redirect_uri != request.redirect_uri
+        || !verify_code_challenge(request.code_verifier.expose_secret(), &grant.code_challenge)
+    {
+        return Err(AuthAPIError::OAuthInvalidGrant);
+    }
+
+    // The account may have been deleted or disabled since the code was issued
+    let user = match state.user_store.read().await.get_user(&grant.email).await {
+        Ok(user) if user.deleted_at.is_none() && !user.disabled => user,
+        Ok(_) | Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::OAuthInvalidGrant),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let scope = grant.scope.clone().unwrap_or_default();
+    let access_token =
+        generate_oauth_access_token(&user, scope).map_err(AuthAPIError::UnexpectedError)?;
+
+    let openid = grant
+        .scope
+        .as_deref()
+        .is_some_and(|scope| scope.split_whitespace().any(|scope| scope == "openid"));
+
+    let id_token = if openid {
+        Some(
+            generate_id_token(&user, &grant.client_id, grant.nonce)
+                .map_err(AuthAPIError::UnexpectedError)?,
+        )
+    } else {
+        None
+    };
+
+    let response = Json(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        id_token,
+    });
+
+    Ok((
+        StatusCode::OK,
+        [(header::CACHE_CONTROL, "no-store")],
+        response,
+    ))
+}
+
+// Public clients authenticate with PKCE alone, confidential clients also need their secret
+async fn authenticate_client(
+    client_id: &str,
+    client_secret: Option<Secret<String>>,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let client_store = state.oauth_client_store.read().await;
+
+    let client = match client_store.get_client(client_id).await {
+        Ok(client) => client,
+        Err(OAuthClientStoreError::ClientNotFound) => return Err(AuthAPIError::OAuthInvalidClient),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if !client.confidential {
+        return Ok(());
+    }
+
+    let client_secret = client_secret
+        .and_then(|secret| OAuthClientSecret::parse(secret).ok())
+        .ok_or(AuthAPIError::OAuthInvalidClient)?;
+
+    match client_store
+        .validate_client_secret(client_id, &client_secret)
+        .await
+    {
+        Ok(()) => Ok(()),
+        Err(OAuthClientStoreError::ClientNotFound | OAuthClientStoreError::InvalidClientSecret) => {
+            Err(AuthAPIError::OAuthInvalidClient)
+        }
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+async fn authenticated_email(jar: &CookieJar, state: &AppState) -> Option<Email> {
+    let cookie = jar.get(AUTH_COOKIE_POLICY.auth_cookie_name())?;
+
+    let claims = validate_token(
+        cookie.value(),
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    .ok()?;
+
+    Email::parse(Secret::new(claims.sub)).ok()
+}
+
+fn redirect_to_client(
+    redirect_uri: &str,
+    params: &[(&str, &str)],
+    state: Option<&str>,
+) -> Result<Redirect, AuthAPIError> {
+    let mut url = Url::parse(redirect_uri)
+        .wrap_err("registered redirect URI is not a valid URL")
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    url.query_pairs_mut()
+        .extend_pairs(params)
+        .extend_pairs(state.map(|state| ("state", state)));
+
+    Ok(Redirect::to(url.as_str()))
+}
+
+// The login page returns to `return_to` after a successful login
+fn redirect_to_login(return_to: &str) -> Result<Redirect, AuthAPIError> {
+    let url = Url::parse_with_params(
+        &format!("{}/", AUTH_SERVICE_URL.as_str()),
+        &[("return_to", return_to)],
+    )
+    .wrap_err("failed to build login URL")
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(Redirect::to(url.as_str()))
+}
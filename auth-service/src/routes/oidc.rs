@@ -0,0 +1,94 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, UserStoreError},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        constants::AUTH_SERVICE_URL,
+        pkce::PKCE_METHOD_S256,
+        signing_key::{read_key_ring, SIGNING_ALGORITHM},
+    },
+};
+
+// Field names follow OpenID Connect Discovery 1.0 section 3
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub response_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    pub scopes_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+}
+
+#[tracing::instrument(name = "OpenID configuration", skip_all)]
+pub async fn openid_configuration() -> impl IntoResponse {
+    let issuer = AUTH_SERVICE_URL.as_str();
+    let to_strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+
+    Json(OpenIdConfiguration {
+        issuer: issuer.to_owned(),
+        authorization_endpoint: format!("{}/oauth/authorize", issuer),
+        token_endpoint: format!("{}/oauth/token", issuer),
+        userinfo_endpoint: format!("{}/userinfo", issuer),
+        jwks_uri: format!("{}/.well-known/jwks.json", issuer),
+        response_types_supported: to_strings(&["code"]),
+        subject_types_supported: to_strings(&["public"]),
+        id_token_signing_alg_values_supported: to_strings(&[&format!("{:?}", SIGNING_ALGORITHM)]),
+        scopes_supported: to_strings(&["openid", "email"]),
+        token_endpoint_auth_methods_supported: to_strings(&["client_secret_post", "none"]),
+        code_challenge_methods_supported: to_strings(&[PKCE_METHOD_S256]),
+        grant_types_supported: to_strings(&["authorization_code"]),
+    })
+}
+
+// Public keys for verifying the ID and access tokens this service signs. Keys that are about to
+// be promoted or were recently retired are included, so verifiers can cache the set.
+#[tracing::instrument(name = "JWKS", skip_all)]
+pub async fn jwks() -> Result<impl IntoResponse, AuthAPIError> {
+    let jwks = read_key_ring()
+        .map_err(AuthAPIError::UnexpectedError)?
+        .jwks();
+
+    Ok(Json(jwks))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    pub email: String,
+    pub email_verified: bool,
+}
+
+// OpenID Connect clients don't have the `jwt` cookie and send their access token in the
+// `Authorization: Bearer` header
+#[tracing::instrument(name = "User info", skip_all)]
+pub async fn userinfo(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = match state.user_store.read().await.get_user(&user.email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let email = user.email.as_ref().expose_secret().to_owned();
+
+    let response = Json(UserInfoResponse {
+        sub: email.clone(),
+        email,
+        email_verified: user.email_verified,
+    });
+
+    Ok((StatusCode::OK, response))
+}
@@ -0,0 +1,285 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Redirect},
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::{eyre, Context};
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, OidcLogin, OidcState, OidcStateStoreError, Password, User,
+        UserStoreError,
+    },
+    routes::{handle_2fa, handle_no_2fa, LoginResponse, TokenDelivery},
+    utils::{
+        client_info::ClientInfo,
+        constants::{AUTH_COOKIE_POLICY, AUTH_SERVICE_URL},
+    },
+};
+
+// The state cookie is only sent to the login routes
+const OIDC_LOGIN_PATH: &str = "/login/oidc";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcProvidersResponse {
+    pub providers: Vec<String>,
+}
+
+// Lets the login page show a button for every configured provider
+#[tracing::instrument(name = "List OIDC providers", skip_all)]
+pub async fn oidc_providers(State(state): State<AppState>) -> impl IntoResponse {
+    Json(OidcProvidersResponse {
+        providers: state.oidc_client.provider_names(),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct OidcLoginRequest {
+    pub return_to: Option<String>,
+}
+
+// Starts a login with an external provider by sending the user to its authorization endpoint
+#[tracing::instrument(name = "OIDC login", skip_all)]
+pub async fn oidc_login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(provider_name): Path<String>,
+    Query(request): Query<OidcLoginRequest>,
+) -> (CookieJar, Result<Redirect, AuthAPIError>) {
+    let Some(provider) = state.oidc_client.get_provider(&provider_name) else {
+        return (jar, Err(AuthAPIError::OidcProviderNotFound));
+    };
+
+    // Same rule as the login page, so this can't be used as an open redirect
+    let return_to = request
+        .return_to
+        .filter(|return_to| return_to.starts_with("/oauth/authorize"));
+
+    let oidc_state = OidcState::default();
+    let login = OidcLogin::new(provider.name.clone(), return_to);
+
+    let url = match state
+        .oidc_client
+        .authorization_url(
+            provider,
+            &callback_url(&provider.name),
+            oidc_state.as_ref().expose_secret(),
+            &login.nonce,
+            &login.code_verifier,
+        )
+        .await
+    {
+        Ok(url) => url,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    if let Err(e) = state
+        .oidc_state_store
+        .write()
+        .await
+        .add_state(oidc_state.clone(), login)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    // Ties the callback to the browser that started the login
+    let cookie = AUTH_COOKIE_POLICY.oidc_state_cookie(
+        oidc_state.as_ref().expose_secret().to_owned(),
+        OIDC_LOGIN_PATH,
+    );
+
+    (jar.add(cookie), Ok(Redirect::to(url.as_str())))
+}
+
+#[derive(Deserialize)]
+pub struct OidcCallbackRequest {
+    pub code: Option<Secret<String>>,
+    pub state: Option<Secret<String>>,
+    // Set instead of `code` when the user cancelled or the provider refused the request
+    pub error: Option<String>,
+}
+
+// The provider sends the user back here. The user is then logged in like with a password,
+// including the 2FA step.
+#[tracing::instrument(name = "OIDC callback", skip_all)]
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Path(provider_name): Path<String>,
+    Query(request): Query<OidcCallbackRequest>,
+) -> (CookieJar, Result<Redirect, AuthAPIError>) {
+    let cookie_state = jar
+        .get(AUTH_COOKIE_POLICY.oidc_state_cookie_name())
+        .map(|cookie| cookie.value().to_owned());
+    let jar = AUTH_COOKIE_POLICY.remove_oidc_state_cookie(jar, OIDC_LOGIN_PATH);
+
+    let (user, return_to) =
+        match authenticate_external_user(&state, &provider_name, cookie_state, request).await {
+            Ok(result) => result,
+            Err(e) => return (jar, Err(e)),
+        };
+
+    if user.requires_2fa {
+        let (jar, result) = handle_2fa(&user, &state, jar).await;
+
+        let redirect = result.and_then(|(_, Json(response))| match response {
+            LoginResponse::TwoFactorAuth(response) => {
+                redirect_to_2fa(&user.email, &response.login_attempt_id, return_to)
+            }
+            _ => Err(AuthAPIError::UnexpectedError(eyre!(
+                "expected a 2FA login response"
+            ))),
+        });
+
+        return (jar, redirect);
+    }
+
+    let (jar, result) = handle_no_2fa(&user, &state, client, TokenDelivery::Cookie, jar).await;
+
+    (
+        jar,
+        result.map(|_| Redirect::to(return_to.as_deref().unwrap_or("/"))),
+    )
+}
+
+async fn authenticate_external_user(
+    state: &AppState,
+    provider_name: &str,
+    cookie_state: Option<String>,
+    request: OidcCallbackRequest,
+) -> Result<(User, Option<String>), AuthAPIError> {
+    let provider = state
+        .oidc_client
+        .get_provider(provider_name)
+        .ok_or(AuthAPIError::OidcProviderNotFound)?;
+
+    if let Some(error) = request.error {
+        tracing::warn!("{} login failed: {}", provider.name, error);
+        return Err(AuthAPIError::OidcLoginFailed);
+    }
+
+    let (Some(code), Some(request_state)) = (request.code, request.state) else {
+        return Err(AuthAPIError::OidcLoginFailed);
+    };
+
+    if cookie_state.as_deref() != Some(request_state.expose_secret().as_str()) {
+        return Err(AuthAPIError::OidcLoginFailed);
+    }
+
+    let oidc_state = OidcState::parse(request_state).map_err(|_| AuthAPIError::OidcLoginFailed)?;
+
+    let login = match state
+        .oidc_state_store
+        .write()
+        .await
+        .use_state(&oidc_state)
+        .await
+    {
+        Ok(login) if login.provider == provider.name => login,
+        Ok(_) | Err(OidcStateStoreError::StateNotFound) => {
+            return Err(AuthAPIError::OidcLoginFailed)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let claims = state
+        .oidc_client
+        .exchange_code(
+            provider,
+            &callback_url(&provider.name),
+            &code,
+            &login.code_verifier,
+        )
+        .await
+        .map_err(|e| {
+            tracing::warn!("{} login failed: {:?}", provider.name, e);
+            AuthAPIError::OidcLoginFailed
+        })?;
+
+    if claims.nonce.as_deref() != Some(login.nonce.as_str()) {
+        return Err(AuthAPIError::OidcLoginFailed);
+    }
+
+    // Only an address the provider has verified may be linked to an account
+    let email = match (claims.email, claims.email_verified) {
+        (Some(email), Some(true)) => {
+            Email::parse(Secret::new(email)).map_err(|_| AuthAPIError::OidcLoginFailed)?
+        }
+        _ => return Err(AuthAPIError::EmailNotVerified),
+    };
+
+    let user = find_or_create_user(state, email).await?;
+
+    Ok((user, login.return_to))
+}
+
+// Links the login to the account with the same email, or creates the account on first login
+async fn find_or_create_user(state: &AppState, email: Email) -> Result<User, AuthAPIError> {
+    let mut user_store = state.user_store.write().await;
+
+    match user_store.get_user(&email).await {
+        Ok(user) if user.deleted_at.is_some() => Err(AuthAPIError::AccountPendingDeletion),
+        Ok(user) if user.disabled => Err(AuthAPIError::AccountDisabled),
+        // Anyone can sign up with an address they don't own, so unverified accounts are never
+        // linked. Otherwise whoever created the account would share it with the real owner.
+        Ok(user) if !user.email_verified => Err(AuthAPIError::EmailNotVerified),
+        Ok(user) => Ok(user),
+        Err(UserStoreError::UserNotFound) => {
+            // Nobody knows the password, a password can be set with /forgot-password
+            let password = Password::parse(Secret::new(uuid::Uuid::new_v4().to_string()))
+                .map_err(AuthAPIError::UnexpectedError)?;
+
+            let user = User {
+                email_verified: true,
+                ..User::new(email, password, false)
+            };
+
+            user_store
+                .add_user(user.clone())
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+            Ok(user)
+        }
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+fn callback_url(provider_name: &str) -> String {
+    format!(
+        "{}{}/{}/callback",
+        AUTH_SERVICE_URL.as_str(),
+        OIDC_LOGIN_PATH,
+        provider_name
+    )
+}
+
+// The login page shows the 2FA form when it is opened with a login attempt ID
+fn redirect_to_2fa(
+    email: &Email,
+    login_attempt_id: &str,
+    return_to: Option<String>,
+) -> Result<Redirect, AuthAPIError> {
+    let mut url = Url::parse_with_params(
+        &format!("{}/", AUTH_SERVICE_URL.as_str()),
+        &[
+            ("email", email.as_ref().expose_secret().as_str()),
+            ("login_attempt_id", login_attempt_id),
+        ],
+    )
+    .wrap_err("failed to build 2FA URL")
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    if let Some(return_to) = return_to {
+        url.query_pairs_mut().append_pair("return_to", &return_to);
+    }
+
+    Ok(Redirect::to(url.as_str()))
+}
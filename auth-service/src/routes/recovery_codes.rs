@@ -0,0 +1,93 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, Password, RecoveryCode, UserStoreError},
+    utils::authenticated_user::AuthenticatedUser,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
+
+impl From<Vec<RecoveryCode>> for RecoveryCodesResponse {
+    fn from(codes: Vec<RecoveryCode>) -> Self {
+        Self {
+            recovery_codes: codes
+                .iter()
+                .map(|code| code.as_ref().expose_secret().to_owned())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    pub password: Secret<String>,
+}
+
+// Issues a fresh set of recovery codes, invalidating the previous set
+#[tracing::instrument(name = "Regenerate recovery codes", skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<RegenerateRecoveryCodesRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    user.require_full_access()?;
+
+    let email = user.email;
+
+    let password = match Password::parse(request.password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    {
+        let user_store = state.user_store.read().await;
+
+        if let Err(e) = user_store.validate_user(&email, &password).await {
+            match e {
+                UserStoreError::UserNotFound | UserStoreError::InvalidCredentials => {
+                    return Err(AuthAPIError::IncorrectCredentials)
+                }
+                _ => return Err(AuthAPIError::UnexpectedError(e.into())),
+            }
+        }
+
+        match user_store.get_user(&email).await {
+            Ok(user) if user.requires_2fa => {}
+            Ok(_) => return Err(AuthAPIError::TwoFANotEnabled),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+    }
+
+    let codes = issue_recovery_codes(&email, &state).await?;
+
+    Ok((StatusCode::OK, Json(RecoveryCodesResponse::from(codes))))
+}
+
+// Generates and stores a new set of recovery codes. The plaintext codes are only ever
+// returned here, so they must be shown to the user straight away.
+#[tracing::instrument(name = "Issue recovery codes", skip_all)]
+pub(crate) async fn issue_recovery_codes(
+    email: &Email,
+    state: &AppState,
+) -> Result<Vec<RecoveryCode>, AuthAPIError> {
+    let codes = RecoveryCode::generate_set();
+
+    if let Err(e) = state
+        .recovery_code_store
+        .write()
+        .await
+        .replace_codes(email, codes.clone())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    Ok(codes)
+}
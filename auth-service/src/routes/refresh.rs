@@ -0,0 +1,85 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, RefreshToken, RefreshTokenStoreError, SessionStoreError, UserStoreError,
+    },
+    utils::{
+        auth::{generate_auth_cookie, generate_refresh_cookie},
+        constants::AUTH_COOKIE_POLICY,
+    },
+};
+
+#[tracing::instrument(name = "Refresh", skip_all)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let Some(cookie) = jar.get(AUTH_COOKIE_POLICY.refresh_cookie_name()) else {
+        return (jar, Err(AuthAPIError::MissingToken));
+    };
+
+    let token = match RefreshToken::parse(Secret::new(cookie.value().to_owned())) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let mut refresh_token_store = state.refresh_token_store.write().await;
+
+    let family = match refresh_token_store.use_token(&token).await {
+        Ok(family) => family,
+        Err(RefreshTokenStoreError::TokenReused(family)) => {
+            // A used token showing up again means it was most likely stolen,
+            // so we revoke every token descended from the same login.
+            tracing::warn!("refresh token reuse detected, revoking token family");
+            if let Err(e) = refresh_token_store.revoke_family(&family).await {
+                return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+            }
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+        Err(RefreshTokenStoreError::TokenNotFound | RefreshTokenStoreError::FamilyRevoked) => {
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // Release the lock before issuing the rotated token through the same store
+    drop(refresh_token_store);
+
+    // The family belongs to a session, which may have been revoked from another device
+    match state
+        .session_store
+        .write()
+        .await
+        .touch_session(&family.id)
+        .await
+    {
+        Ok(()) => {}
+        Err(SessionStoreError::SessionNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    // The new auth token carries the user's current token generation
+    let user = match state.user_store.read().await.get_user(&family.email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let auth_cookie = match generate_auth_cookie(&user, &family.id) {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let refresh_cookie =
+        match generate_refresh_cookie(family, state.refresh_token_store.clone()).await {
+            Ok(cookie) => cookie,
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+        };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+    (updated_jar, Ok(StatusCode::OK))
+}
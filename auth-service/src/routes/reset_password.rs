@@ -0,0 +1,66 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Password, PasswordResetToken, PasswordResetTokenStoreError},
+    routes::revoke_all_tokens,
+};
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[tracing::instrument(name = "Reset password", skip_all)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = match PasswordResetToken::parse(request.token) {
+        Ok(token) => token,
+        Err(_) => return Err(AuthAPIError::InvalidToken),
+    };
+
+    let new_password = match Password::parse(request.new_password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let email = match state
+        .password_reset_token_store
+        .write()
+        .await
+        .use_token(&token)
+        .await
+    {
+        Ok(email) => email,
+        Err(PasswordResetTokenStoreError::TokenNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if let Err(e) = state
+        .user_store
+        .write()
+        .await
+        .update_password(&email, new_password)
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    // Proving ownership of the email lifts a lockout from failed logins
+    if let Err(e) = state.login_lockout_store.write().await.reset(&email).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    // Sign out every existing session, since whoever holds them may not own the account
+    if let Err(e) = revoke_all_tokens(&email, &state).await {
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,121 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Session, SessionStoreError},
+    utils::{authenticated_user::AuthenticatedUser, constants::AUTH_COOKIE_POLICY},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    // RFC 3339 timestamps
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "lastSeen")]
+    pub last_seen: String,
+    pub ip: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    // Whether this is the session making the request
+    pub current: bool,
+}
+
+// Lists the devices the user is signed in on, most recently used first
+#[tracing::instrument(name = "List sessions", skip_all)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    user.require_full_access()?;
+
+    let mut sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen));
+
+    let sessions = sessions
+        .into_iter()
+        .map(|session| to_response(session, user.claims.sid.as_deref()))
+        .collect();
+
+    Ok((StatusCode::OK, Json(SessionsResponse { sessions })))
+}
+
+// Signs the user out on one device. Its auth token stops working right away and its refresh
+// token can no longer be used.
+#[tracing::instrument(name = "Revoke session", skip_all)]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    if let Err(e) = user.require_full_access() {
+        return (jar, Err(e));
+    }
+
+    let mut session_store = state.session_store.write().await;
+
+    // Other users' sessions are reported as missing, so their IDs can't be probed
+    let session = match session_store.get_session(&id).await {
+        Ok(session) if session.email == user.email => session,
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => {
+            return (jar, Err(AuthAPIError::SessionNotFound))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if let Err(e) = session_store.revoke_session(&session.id).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    drop(session_store);
+
+    if let Err(e) = state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_family(&session.refresh_token_family())
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    // Revoking the current session is the same as logging out
+    let jar = if user.claims.sid.as_deref() == Some(session.id.as_str()) {
+        AUTH_COOKIE_POLICY.remove_cookies(jar)
+    } else {
+        jar
+    };
+
+    (jar, Ok(StatusCode::OK))
+}
+
+fn to_response(session: Session, current_sid: Option<&str>) -> SessionResponse {
+    SessionResponse {
+        current: current_sid == Some(session.id.as_str()),
+        id: session.id,
+        created_at: session.created_at.to_rfc3339(),
+        last_seen: session.last_seen.to_rfc3339(),
+        ip: session.ip,
+        user_agent: session.user_agent,
+    }
+}
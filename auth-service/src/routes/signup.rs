@@ -5,6 +5,7 @@ use secrecy::Secret;
 use crate::{
     app_state::AppState,
     domain::{AuthAPIError, Email, Password, User},
+    routes::{issue_recovery_codes, send_verification_email, RecoveryCodesResponse},
 };
 
 #[tracing::instrument(name = "Signup", skip_all)]
@@ -23,7 +24,8 @@ pub async fn signup(
         Err(_) => return Err(AuthAPIError::InvalidCredentials),
     };
 
-    let user = User::new(email, password, request.requires_2fa);
+    let requires_2fa = request.requires_2fa;
+    let user = User::new(email.clone(), password, requires_2fa);
 
     let mut user_store = state.user_store.write().await;
 
@@ -37,8 +39,31 @@ pub async fn signup(
         };
     }
 
+    drop(user_store);
+
+    // The account exists at this point, so a failed email is only logged.
+    // The user can request another one through /resend-verification-email.
+    if let Err(e) = send_verification_email(&email, &state).await {
+        tracing::error!("failed to send verification email: {:?}", e);
+    }
+
+    // Accounts that start with 2FA get their recovery codes straight away. A failure is
+    // only logged since the user can regenerate them through /recovery-codes/regenerate.
+    let recovery_codes = if requires_2fa {
+        match issue_recovery_codes(&email, &state).await {
+            Ok(codes) => Some(RecoveryCodesResponse::from(codes).recovery_codes),
+            Err(e) => {
+                tracing::error!("failed to issue recovery codes: {:?}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes,
     });
 
     Ok((StatusCode::CREATED, response))
@@ -55,4 +80,10 @@ pub struct SignupRequest {
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct SignupResponse {
     pub message: String,
+    #[serde(
+        rename = "recoveryCodes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recovery_codes: Option<Vec<String>>,
 }
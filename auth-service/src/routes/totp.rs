@@ -0,0 +1,174 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::Utc;
+use color_eyre::eyre::Context;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, TotpSecret, TotpSecretStoreError, TwoFACode, TwoFAMethod},
+    routes::{issue_recovery_codes, RecoveryCodesResponse},
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        totp::{generate_totp_uri, verify_totp_code},
+    },
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnrollTotpResponse {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+// Starts authenticator-app enrollment. The secret is only used for login once confirmed.
+#[tracing::instrument(name = "Enroll TOTP", skip_all)]
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    user.require_full_access()?;
+
+    let email = user.email;
+    let secret = TotpSecret::default();
+
+    match state
+        .totp_secret_store
+        .write()
+        .await
+        .add_secret(email.clone(), secret.clone())
+        .await
+    {
+        Ok(()) => {}
+        Err(TotpSecretStoreError::SecretAlreadyConfirmed) => {
+            return Err(AuthAPIError::TotpAlreadyEnabled)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let otpauth_uri = generate_totp_uri(&secret, &email).map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(EnrollTotpResponse {
+        secret: secret.as_ref().expose_secret().to_owned(),
+        otpauth_uri,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub code: Secret<String>,
+}
+
+// Confirms the enrolled secret with a code from the authenticator app and switches the
+// user's 2FA method to TOTP. A new set of recovery codes is returned.
+#[tracing::instrument(name = "Confirm TOTP", skip_all)]
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    user.require_full_access()?;
+
+    let email = user.email;
+
+    let code = match TwoFACode::parse(request.code) {
+        Ok(code) => code,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let enrollment = match state
+        .totp_secret_store
+        .read()
+        .await
+        .get_secret(&email)
+        .await
+    {
+        Ok(enrollment) => enrollment,
+        Err(TotpSecretStoreError::SecretNotFound) => {
+            return Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if enrollment.confirmed {
+        return Err(AuthAPIError::TotpAlreadyEnabled);
+    }
+
+    check_totp_code(&email, &enrollment.secret, &code, &state).await?;
+
+    if let Err(e) = state
+        .totp_secret_store
+        .write()
+        .await
+        .confirm_secret(&email)
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    if let Err(e) = state
+        .user_store
+        .write()
+        .await
+        .set_two_fa_method(&email, TwoFAMethod::Totp)
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    let codes = issue_recovery_codes(&email, &state).await?;
+
+    Ok((StatusCode::OK, Json(RecoveryCodesResponse::from(codes))))
+}
+
+// Checks a login code against the user's confirmed TOTP secret
+#[tracing::instrument(name = "Verify TOTP login code", skip_all)]
+pub(crate) async fn verify_totp_login_code(
+    email: &Email,
+    code: &TwoFACode,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let enrollment = match state.totp_secret_store.read().await.get_secret(email).await {
+        Ok(enrollment) if enrollment.confirmed => enrollment,
+        Ok(_) | Err(TotpSecretStoreError::SecretNotFound) => {
+            return Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    check_totp_code(email, &enrollment.secret, code, state).await
+}
+
+async fn check_totp_code(
+    email: &Email,
+    secret: &TotpSecret,
+    code: &TwoFACode,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let now: u64 = Utc::now()
+        .timestamp()
+        .try_into()
+        .wrap_err("failed to cast current time to u64")
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let time_step = match verify_totp_code(secret, email, code, now) {
+        Ok(Some(time_step)) => time_step,
+        Ok(None) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+    };
+
+    // Each code is accepted at most once
+    match state
+        .totp_secret_store
+        .write()
+        .await
+        .use_time_step(email, time_step)
+        .await
+    {
+        Ok(()) => Ok(()),
+        Err(TotpSecretStoreError::TimeStepAlreadyUsed) => Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
@@ -0,0 +1,208 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, LoginAttemptId, Password, TwoFACode, TwoFAMethod, UserStoreError,
+    },
+    routes::{
+        issue_recovery_codes, verify_login_attempt, ParsedSecondFactor, RecoveryCodesResponse,
+        TwoFactorAuthResponse,
+    },
+    utils::authenticated_user::AuthenticatedUser,
+};
+
+#[derive(Deserialize)]
+pub struct Disable2FARequest {
+    pub password: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmDisable2FARequest {
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: String,
+}
+
+// Turns 2FA on for the signed-in user, who gets a fresh set of recovery codes like at signup
+#[tracing::instrument(name = "Enable 2FA", skip_all)]
+pub async fn enable_2fa(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    user.require_full_access()?;
+
+    let email = user.email;
+
+    {
+        let mut user_store = state.user_store.write().await;
+
+        match user_store.get_user(&email).await {
+            Ok(user) if user.requires_2fa => return Err(AuthAPIError::TwoFAAlreadyEnabled),
+            Ok(_) => {}
+            Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+
+        if let Err(e) = user_store.set_requires_2fa(&email, true).await {
+            return Err(AuthAPIError::UnexpectedError(e.into()));
+        }
+    }
+
+    let codes = issue_recovery_codes(&email, &state).await?;
+    notify_2fa_change(&email, true, &state).await;
+
+    Ok((StatusCode::OK, Json(RecoveryCodesResponse::from(codes))))
+}
+
+// First step of turning 2FA off. Once the password checks out, a 2FA challenge is started like
+// at login, which has to be completed through `confirm_disable_2fa`.
+#[tracing::instrument(name = "Disable 2FA", skip_all)]
+pub async fn disable_2fa(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<Disable2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    user.require_full_access()?;
+
+    let email = user.email;
+
+    let password = match Password::parse(request.password) {
+        Ok(password) => password,
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+
+    let user = {
+        let user_store = state.user_store.read().await;
+
+        if let Err(e) = user_store.validate_user(&email, &password).await {
+            match e {
+                UserStoreError::UserNotFound | UserStoreError::InvalidCredentials => {
+                    return Err(AuthAPIError::IncorrectCredentials)
+                }
+                _ => return Err(AuthAPIError::UnexpectedError(e.into())),
+            }
+        }
+
+        match user_store.get_user(&email).await {
+            Ok(user) if user.requires_2fa => user,
+            Ok(_) => return Err(AuthAPIError::TwoFANotEnabled),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+    };
+
+    let login_attempt_id = LoginAttemptId::default();
+    let two_fa_code = TwoFACode::default();
+
+    // Kept apart from login attempts, so only this challenge can confirm turning 2FA off
+    if let Err(e) = state
+        .disable_2fa_code_store
+        .write()
+        .await
+        .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    // TOTP users read the code from their authenticator app instead
+    if user.two_fa_method == TwoFAMethod::Email {
+        state
+            .email_client
+            .send_email(&email, "2FA Code", two_fa_code.as_ref().expose_secret())
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+    }
+
+    let response = Json(TwoFactorAuthResponse {
+        message: "2FA required".to_owned(),
+        login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
+    });
+
+    Ok((StatusCode::PARTIAL_CONTENT, response))
+}
+
+// Turns 2FA off once the code of the challenge started by `disable_2fa` checks out. The TOTP
+// secret and recovery codes are removed as well, turning 2FA back on starts over with email codes
+// and a new authenticator can be enrolled.
+#[tracing::instrument(name = "Confirm disabling 2FA", skip_all)]
+pub async fn confirm_disable_2fa(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<ConfirmDisable2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    user.require_full_access()?;
+
+    let email = user.email;
+
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(request.login_attempt_id))
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let two_fa_code = TwoFACode::parse(Secret::new(request.two_fa_code))
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    verify_login_attempt(
+        &email,
+        &login_attempt_id,
+        ParsedSecondFactor::TwoFACode(two_fa_code),
+        &state.disable_2fa_code_store,
+        &state,
+    )
+    .await?;
+
+    // The user is switched to email codes first, so a failure below can't leave them required
+    // to enter codes from a secret that is gone
+    if let Err(e) = state.user_store.write().await.disable_2fa(&email).await {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    if let Err(e) = state
+        .totp_secret_store
+        .write()
+        .await
+        .remove_secret(&email)
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    if let Err(e) = state
+        .recovery_code_store
+        .write()
+        .await
+        .replace_codes(&email, Vec::new())
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    notify_2fa_change(&email, false, &state).await;
+
+    Ok(StatusCode::OK)
+}
+
+// Lets the user know in case someone else changed the setting
+#[tracing::instrument(name = "Notify 2FA change", skip_all)]
+pub(crate) async fn notify_2fa_change(email: &Email, enabled: bool, state: &AppState) {
+    let (subject, content) = if enabled {
+        (
+            "Two-factor authentication enabled",
+            "Two-factor authentication has been turned on for your account. \
+            If this wasn't you, reset your password right away.",
+        )
+    } else {
+        (
+            "Two-factor authentication disabled",
+            "Two-factor authentication has been turned off for your account. \
+            If this wasn't you, reset your password and turn it back on right away.",
+        )
+    };
+
+    // The change stands even if the user can't be told about it
+    if let Err(e) = state.email_client.send_email(email, subject, content).await {
+        tracing::error!("failed to send 2FA change notification: {:?}", e);
+    }
+}
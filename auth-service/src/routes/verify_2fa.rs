@@ -1,18 +1,27 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 
 use crate::{
-    app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode, TwoFACodeStoreError},
-    utils::auth::generate_auth_cookie,
+    app_state::{AppState, TwoFACodeStoreType},
+    domain::{
+        AuthAPIError, Email, LoginAttemptId, RecoveryCode, RecoveryCodeStoreError, TwoFACode,
+        TwoFACodeStoreError, TwoFAMethod, User, UserStoreError,
+    },
+    routes::{issue_bearer_token, verify_totp_login_code, TokenDelivery, TwoFactorAuthResponse},
+    utils::{
+        auth::start_session,
+        client_info::ClientInfo,
+        constants::{MAX_TWO_FA_ATTEMPTS, MAX_TWO_FA_RESENDS},
+    },
 };
 
 #[tracing::instrument(name = "Verify 2FA", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
     jar: CookieJar,
+    client: ClientInfo,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let email = match Email::parse(Secret::new(request.email)) {
@@ -25,52 +34,280 @@ pub async fn verify_2fa(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let two_fa_code = match TwoFACode::parse(Secret::new(request.two_fa_code)) {
-        Ok(code) => code,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    let second_factor = match request.second_factor {
+        SecondFactor::TwoFACode(code) => match TwoFACode::parse(Secret::new(code)) {
+            Ok(code) => ParsedSecondFactor::TwoFACode(code),
+            Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        },
+        SecondFactor::RecoveryCode(code) => match RecoveryCode::parse(Secret::new(code)) {
+            Ok(code) => ParsedSecondFactor::RecoveryCode(code),
+            Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        },
+    };
+
+    let user = match verify_login_attempt(
+        &email,
+        &login_attempt_id,
+        second_factor,
+        &state.two_fa_code_store,
+        &state,
+    )
+    .await
+    {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    if request.token_delivery == TokenDelivery::Body {
+        let result = issue_bearer_token(&user, client, &state)
+            .await
+            .map(|response| (StatusCode::OK, Json(response)).into_response());
+        return (jar, result);
+    }
+
+    // Generate and set JWT and refresh token cookies for a new session
+    let (auth_cookie, refresh_cookie) = match start_session(
+        &user,
+        client,
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
+        Ok(cookies) => cookies,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let two_fa_code_store = state.two_fa_code_store.read().await;
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+    (updated_jar, Ok(StatusCode::OK.into_response()))
+}
+
+// Checks the second factor submitted for an attempt pending in the given store and returns the
+// user it was started for. The attempt is used up once the second factor is verified.
+#[tracing::instrument(name = "Verify login attempt", skip_all)]
+pub(crate) async fn verify_login_attempt(
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+    second_factor: ParsedSecondFactor,
+    code_store: &TwoFACodeStoreType,
+    state: &AppState,
+) -> Result<User, AuthAPIError> {
+    let mut two_fa_code_store = code_store.write().await;
 
-    let code_tuple = match two_fa_code_store.get_code(&email).await {
+    let code_tuple = match two_fa_code_store.get_code(login_attempt_id).await {
         Ok(tuple) => tuple,
-        Err(e) => match e {
-            TwoFACodeStoreError::LoginAttemptIdNotFound => {
-                return (jar, Err(AuthAPIError::IncorrectCredentials));
-            }
-            _ => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
-        },
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            return Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
     };
 
-    if code_tuple.0 != login_attempt_id || code_tuple.1 != two_fa_code {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    if code_tuple.0 != *email {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    // Bound the guesses per login attempt, otherwise a six-digit code could be brute-forced
+    // while it is valid. Once they are used up the code is gone and the user has to log in again.
+    let attempts = two_fa_code_store
+        .record_attempt(login_attempt_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Only reached by guesses sent in parallel, otherwise the last wrong one removed the code
+    if attempts > MAX_TWO_FA_ATTEMPTS {
+        two_fa_code_store
+            .remove_code(login_attempt_id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        return Err(AuthAPIError::IncorrectCredentials);
     }
 
-    // Drop the read lock and get a write lock to remove the code
     drop(two_fa_code_store);
+
+    let user = match state.user_store.read().await.get_user(email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // The account may have been disabled since the login attempt was started
+    if user.disabled {
+        return Err(AuthAPIError::AccountDisabled);
+    }
+
+    let verification =
+        verify_second_factor(email, second_factor, &code_tuple.1, &user, state).await;
+
+    // The code is removed once verified, or once the last allowed guess was wrong
+    let used_up = match &verification {
+        Ok(()) => true,
+        Err(AuthAPIError::IncorrectCredentials) => attempts >= MAX_TWO_FA_ATTEMPTS,
+        Err(_) => false,
+    };
+
+    if used_up {
+        code_store
+            .write()
+            .await
+            .remove_code(login_attempt_id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    verification.map(|()| user)
+}
+
+// Emails a fresh code for a pending login attempt, e.g. when the first one never arrived. The
+// attempt ID and its remaining guesses are kept, so resending doesn't reset the guess limit.
+#[tracing::instrument(name = "Resend 2FA code", skip_all)]
+pub async fn resend_2fa(
+    State(state): State<AppState>,
+    Json(request): Json<Resend2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email =
+        Email::parse(Secret::new(request.email)).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(request.login_attempt_id))
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // The user is looked up before the 2FA code store is locked, the same order as in login.
+    // Taking the locks in opposite orders could deadlock with a writer queued for the user store.
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
     let mut two_fa_code_store = state.two_fa_code_store.write().await;
 
-    // Remove the 2FA code after successful verification
-    if let Err(e) = two_fa_code_store.remove_code(&email).await {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    match two_fa_code_store.get_code(&login_attempt_id).await {
+        Ok((stored_email, _)) if stored_email == email => {}
+        Ok(_) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            return Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
     }
 
-    // Generate and set JWT cookie
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
-        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    // TOTP codes come from the user's authenticator app, there is nothing to resend
+    if user.two_fa_method != TwoFAMethod::Email {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let resends = match two_fa_code_store.record_resend(&login_attempt_id).await {
+        Ok(resends) => resends,
+        Err(TwoFACodeStoreError::ResendCooldownActive) => {
+            return Err(AuthAPIError::TooManyRequests)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
     };
 
-    let updated_jar = jar.add(auth_cookie);
-    (updated_jar, Ok(StatusCode::OK.into_response()))
+    if resends > MAX_TWO_FA_RESENDS {
+        return Err(AuthAPIError::TooManyRequests);
+    }
+
+    let two_fa_code = TwoFACode::default();
+
+    two_fa_code_store
+        .add_code(email, login_attempt_id.clone(), two_fa_code.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(two_fa_code_store);
+
+    state
+        .email_client
+        .send_email(
+            &user.email,
+            "2FA Code",
+            two_fa_code.as_ref().expose_secret(),
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(TwoFactorAuthResponse {
+        message: "2FA code resent".to_owned(),
+        login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Verify second factor", skip_all)]
+async fn verify_second_factor(
+    email: &Email,
+    second_factor: ParsedSecondFactor,
+    stored_code: &TwoFACode,
+    user: &User,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    match second_factor {
+        ParsedSecondFactor::TwoFACode(two_fa_code) => {
+            // Email codes are checked against the stored code, TOTP codes against the user's secret
+            match user.two_fa_method {
+                TwoFAMethod::Email => {
+                    if *stored_code != two_fa_code {
+                        return Err(AuthAPIError::IncorrectCredentials);
+                    }
+                }
+                TwoFAMethod::Totp => verify_totp_login_code(email, &two_fa_code, state).await?,
+            }
+        }
+        ParsedSecondFactor::RecoveryCode(recovery_code) => {
+            // The code is burned here, so it can never be used again
+            let remaining_codes = match state
+                .recovery_code_store
+                .write()
+                .await
+                .use_code(email, &recovery_code)
+                .await
+            {
+                Ok(remaining_codes) => remaining_codes,
+                Err(RecoveryCodeStoreError::CodeNotFound) => {
+                    return Err(AuthAPIError::IncorrectCredentials)
+                }
+                Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+            };
+
+            tracing::warn!(
+                security_event = "recovery_code_used",
+                user = %email.as_ref().expose_secret(),
+                remaining_codes,
+                "recovery code used in place of a 2FA code"
+            );
+        }
+    }
+
+    Ok(())
 }
 
-// TODO: implement the Verify2FARequest struct. See the verify-2fa route contract in step 1 for the expected JSON body.
 #[derive(Debug, Deserialize)]
 pub struct Verify2FARequest {
     pub email: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    #[serde(flatten)]
+    pub second_factor: SecondFactor,
+    #[serde(default, rename = "tokenDelivery")]
+    pub token_delivery: TokenDelivery,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Resend2FARequest {
+    pub email: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
+
+// A recovery code can be sent in place of the 2FA code
+#[derive(Debug, Deserialize)]
+pub enum SecondFactor {
     #[serde(rename = "2FACode")]
-    pub two_fa_code: String,
+    TwoFACode(String),
+    #[serde(rename = "recoveryCode")]
+    RecoveryCode(String),
+}
+
+pub(crate) enum ParsedSecondFactor {
+    TwoFACode(TwoFACode),
+    RecoveryCode(RecoveryCode),
 }
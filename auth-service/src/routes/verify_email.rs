@@ -0,0 +1,141 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuthAPIError, Email, EmailVerificationToken, EmailVerificationTokenStoreError,
+        UserStoreError,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: Secret<String>,
+}
+
+#[tracing::instrument(name = "Verify email", skip_all)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = match EmailVerificationToken::parse(request.token) {
+        Ok(token) => token,
+        Err(_) => return Err(AuthAPIError::InvalidToken),
+    };
+
+    let email = match state
+        .email_verification_token_store
+        .write()
+        .await
+        .use_token(&token)
+        .await
+    {
+        Ok(email) => email,
+        Err(EmailVerificationTokenStoreError::TokenNotFound) => {
+            return Err(AuthAPIError::InvalidToken)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    match state
+        .user_store
+        .write()
+        .await
+        .mark_email_verified(&email)
+        .await
+    {
+        Ok(()) => Ok(StatusCode::OK),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ResendVerificationEmailRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ResendVerificationEmailResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Resend verification email", skip_all)]
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    Json(request): Json<ResendVerificationEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = match Email::parse(request.email) {
+        Ok(email) => email,
+        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => Some(user),
+        Err(UserStoreError::UserNotFound) => None,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // Unknown, deleted and already verified addresses get the same response, but no email.
+    // The email is sent in the background and its failures, including an active cooldown, are
+    // only logged, so neither the status code nor the response time gives the account away.
+    if let Some(user) = user.filter(|user| !user.email_verified && user.deleted_at.is_none()) {
+        tokio::spawn(
+            async move {
+                match send_verification_email(&user.email, &state).await {
+                    Ok(()) => {}
+                    Err(AuthAPIError::TooManyRequests) => {
+                        tracing::info!("verification email was sent too recently, not resending")
+                    }
+                    Err(e) => tracing::error!("failed to resend verification email: {:?}", e),
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    let response = Json(ResendVerificationEmailResponse {
+        message: "If this email is awaiting verification, a new verification token has been sent."
+            .to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Send verification email", skip_all)]
+pub(crate) async fn send_verification_email(
+    email: &Email,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let token = EmailVerificationToken::default();
+
+    match state
+        .email_verification_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await
+    {
+        Ok(()) => {}
+        Err(EmailVerificationTokenStoreError::CooldownActive) => {
+            return Err(AuthAPIError::TooManyRequests)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    state
+        .email_client
+        .send_email(
+            email,
+            "Verify your email",
+            &format!(
+                "Use the following token to verify your email address: {}",
+                token.as_ref().expose_secret()
+            ),
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
@@ -1,21 +1,59 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde::Deserialize;
 
-use crate::{app_state::AppState, utils::auth::validate_token};
+use crate::{
+    app_state::AppState,
+    utils::{auth::validate_token, authenticated_user::AuthToken},
+};
 
 #[derive(Debug, Deserialize)]
 pub struct VerifyTokenRequest {
     token: String,
+    // The token is only accepted if it carries the role and permission, when given
+    role: Option<String>,
+    permission: Option<String>,
 }
 
 #[tracing::instrument(name = "Verify token", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
-    Json(body): Json<VerifyTokenRequest>,
-) -> impl IntoResponse {
+    auth_token: Option<AuthToken>,
+    body: Result<Json<VerifyTokenRequest>, JsonRejection>,
+) -> Response {
+    let (token, role, permission) = match (body, auth_token) {
+        (Ok(Json(body)), _) => (body.token, body.role, body.permission),
+        // Without a body, the token the request itself was made with is checked
+        (Err(JsonRejection::MissingJsonContentType(_)), Some(auth_token)) => {
+            (auth_token.value, None, None)
+        }
+        (Err(rejection), _) => return rejection.into_response(),
+    };
+
     // Validate the token and check if it's banned
-    match validate_token(&body.token, state.banned_token_store.clone()).await {
-        Ok(_) => StatusCode::OK,
-        Err(_) => StatusCode::UNAUTHORIZED,
+    match validate_token(
+        &token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => {
+            let has_role = role.is_none_or(|role| claims.roles.contains(&role));
+            let has_permission =
+                permission.is_none_or(|permission| claims.permissions.contains(&permission));
+
+            if has_role && has_permission {
+                StatusCode::OK.into_response()
+            } else {
+                StatusCode::FORBIDDEN.into_response()
+            }
+        }
+        Err(_) => StatusCode::UNAUTHORIZED.into_response(),
     }
 }
@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::eyre::{Context, Result};
+
+use crate::app_state::AppState;
+
+// Periodically hard-deletes accounts whose restoration window has passed
+pub async fn run_account_purge(
+    state: AppState,
+    grace_period: chrono::Duration,
+    interval: Duration,
+) {
+    let mut interval = tokio::time::interval(interval);
+
+    loop {
+        interval.tick().await;
+
+        match purge_deleted_accounts(&state, grace_period).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!("purged {} deleted accounts", purged),
+            Err(e) => tracing::error!("failed to purge deleted accounts: {:?}", e),
+        }
+    }
+}
+
+#[tracing::instrument(name = "Purge deleted accounts", skip_all)]
+pub async fn purge_deleted_accounts(
+    state: &AppState,
+    grace_period: chrono::Duration,
+) -> Result<usize> {
+    let purged = state
+        .user_store
+        .write()
+        .await
+        .purge_deleted_users(Utc::now() - grace_period)
+        .await
+        .wrap_err("failed to purge deleted users")?;
+
+    // Remove everything else stored under the purged email addresses
+    for email in &purged {
+        state
+            .refresh_token_store
+            .write()
+            .await
+            .remove_all_tokens(email)
+            .await
+            .wrap_err("failed to remove refresh tokens of purged user")?;
+
+        state
+            .session_store
+            .write()
+            .await
+            .revoke_all_sessions(email)
+            .await
+            .wrap_err("failed to remove sessions of purged user")?;
+
+        state
+            .two_fa_code_store
+            .write()
+            .await
+            .remove_all_codes(email)
+            .await
+            .wrap_err("failed to remove 2FA codes of purged user")?;
+    }
+
+    Ok(purged.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use secrecy::Secret;
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{
+        domain::{Email, Password, RefreshToken, RefreshTokenFamily, User, UserStoreError},
+        services::{
+            data_stores::{
+                HashmapAuthorizationCodeStore, HashmapEmailVerificationTokenStore,
+                HashmapLoginLockoutStore, HashmapMagicLinkTokenStore, HashmapOAuthClientStore,
+                HashmapOidcStateStore, HashmapPasswordResetTokenStore, HashmapRateLimitStore,
+                HashmapRecoveryCodeStore, HashmapRefreshTokenStore, HashmapSessionStore,
+                HashmapTotpSecretStore, HashmapTwoFACodeStore, HashmapUserStore,
+                HashsetBannedTokenStore,
+            },
+            mock_email_client::MockEmailClient,
+            oidc_client::OidcClient,
+        },
+    };
+
+    fn app_state() -> AppState {
+        AppState::new(
+            Arc::new(RwLock::new(HashmapUserStore::default())),
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default())),
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+            Arc::new(RwLock::new(HashmapTwoFACodeStore::default())),
+            Arc::new(MockEmailClient),
+            Arc::new(RwLock::new(HashmapRefreshTokenStore::default())),
+            Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default())),
+            Arc::new(RwLock::new(HashmapEmailVerificationTokenStore::default())),
+            Arc::new(RwLock::new(HashmapTotpSecretStore::default())),
+            Arc::new(RwLock::new(HashmapRecoveryCodeStore::default())),
+            Arc::new(RwLock::new(HashmapMagicLinkTokenStore::default())),
+            Arc::new(RwLock::new(HashmapOAuthClientStore::default())),
+            Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default())),
+            Arc::new(RwLock::new(HashmapOidcStateStore::default())),
+            Arc::new(OidcClient::new(vec![], reqwest::Client::new())),
+            Arc::new(RwLock::new(HashmapSessionStore::default())),
+            Arc::new(RwLock::new(HashmapLoginLockoutStore::default())),
+            Arc::new(RwLock::new(HashmapRateLimitStore::default())),
+        )
+    }
+
+    async fn add_user(state: &AppState, email: &str) -> Email {
+        let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_owned())).unwrap();
+
+        state
+            .user_store
+            .write()
+            .await
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+
+        email
+    }
+
+    #[tokio::test]
+    async fn test_purge_removes_deleted_accounts_past_grace_period() {
+        let state = app_state();
+        let deleted_email = add_user(&state, "deleted@example.com").await;
+        let active_email = add_user(&state, "active@example.com").await;
+        let refresh_token = RefreshToken::default();
+
+        state
+            .refresh_token_store
+            .write()
+            .await
+            .add_token(
+                refresh_token.clone(),
+                RefreshTokenFamily::new(deleted_email.clone()),
+            )
+            .await
+            .unwrap();
+        state
+            .user_store
+            .write()
+            .await
+            .delete_user(&deleted_email)
+            .await
+            .unwrap();
+
+        // Still within the grace period
+        let purged = purge_deleted_accounts(&state, chrono::Duration::days(1))
+            .await
+            .unwrap();
+        assert_eq!(purged, 0);
+
+        let purged = purge_deleted_accounts(&state, chrono::Duration::seconds(-1))
+            .await
+            .unwrap();
+        assert_eq!(purged, 1);
+
+        let user_store = state.user_store.read().await;
+        assert_eq!(
+            user_store.get_user(&deleted_email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        assert!(user_store.get_user(&active_email).await.is_ok());
+
+        assert!(state
+            .refresh_token_store
+            .write()
+            .await
+            .use_token(&refresh_token)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::{
+    AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError, AuthorizationGrant,
+};
+
+#[derive(Default)]
+pub struct HashmapAuthorizationCodeStore {
+    codes: HashMap<String, AuthorizationGrant>,
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for HashmapAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        self.codes
+            .insert(code.as_ref().expose_secret().to_owned(), grant);
+        Ok(())
+    }
+
+    async fn use_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        self.codes
+            .remove(code.as_ref().expose_secret())
+            .ok_or(AuthorizationCodeStoreError::CodeNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use secrecy::Secret;
+
+    fn grant() -> AuthorizationGrant {
+        AuthorizationGrant {
+            client_id: "mobile-app".to_owned(),
+            redirect_uri: "https://example.com/callback".to_owned(),
+            email: Email::parse(Secret::new("test@example.com".to_string())).unwrap(),
+            code_challenge: "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM".to_owned(),
+            scope: Some("openid email".to_owned()),
+            nonce: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_code_can_only_be_used_once() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+
+        store.add_code(code.clone(), grant()).await.unwrap();
+
+        assert_eq!(store.use_code(&code).await, Ok(grant()));
+        assert_eq!(
+            store.use_code(&code).await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_use_code_not_found() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+
+        assert_eq!(
+            store.use_code(&AuthorizationCode::default()).await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use secrecy::ExposeSecret;
+
+use crate::{
+    domain::{
+        Email, EmailVerificationToken, EmailVerificationTokenStore,
+        EmailVerificationTokenStoreError,
+    },
+    utils::constants::EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashmapEmailVerificationTokenStore {
+    tokens: HashMap<String, Email>,
+    last_issued: HashMap<Email, DateTime<Utc>>,
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for HashmapEmailVerificationTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let now = Utc::now();
+
+        if let Some(last_issued) = self.last_issued.get(&email) {
+            if now - *last_issued < Duration::seconds(EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS) {
+                return Err(EmailVerificationTokenStoreError::CooldownActive);
+            }
+        }
+
+        self.last_issued.insert(email.clone(), now);
+        self.tokens
+            .insert(token.as_ref().expose_secret().to_owned(), email);
+        Ok(())
+    }
+
+    async fn use_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError> {
+        self.tokens
+            .remove(token.as_ref().expose_secret())
+            .ok_or(EmailVerificationTokenStoreError::TokenNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    #[tokio::test]
+    async fn test_add_and_use_token() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let token = EmailVerificationToken::default();
+
+        store.add_token(email.clone(), token.clone()).await.unwrap();
+
+        let result = store.use_token(&token).await.unwrap();
+        assert_eq!(result, email);
+
+        // Tokens are single-use
+        let result = store.use_token(&token).await;
+        assert_eq!(result, Err(EmailVerificationTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_add_token_during_cooldown_fails() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+
+        store
+            .add_token(email.clone(), EmailVerificationToken::default())
+            .await
+            .unwrap();
+
+        let result = store
+            .add_token(email, EmailVerificationToken::default())
+            .await;
+        assert_eq!(
+            result,
+            Err(EmailVerificationTokenStoreError::CooldownActive)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_token_after_cooldown_succeeds() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+
+        store.last_issued.insert(
+            email.clone(),
+            Utc::now() - Duration::seconds(EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS + 1),
+        );
+
+        let result = store
+            .add_token(email, EmailVerificationToken::default())
+            .await;
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::domain::{Email, LoginLockoutStore, LoginLockoutStoreError};
+
+#[derive(Default)]
+pub struct HashmapLoginLockoutStore {
+    failed_attempts: HashMap<Email, u32>,
+    lockouts: HashMap<Email, DateTime<Utc>>,
+}
+
+#[async_trait::async_trait]
+impl LoginLockoutStore for HashmapLoginLockoutStore {
+    async fn add_failed_attempt(&mut self, email: &Email) -> Result<u32, LoginLockoutStoreError> {
+        let failed_attempts = self.failed_attempts.entry(email.clone()).or_default();
+        *failed_attempts += 1;
+        Ok(*failed_attempts)
+    }
+
+    async fn lock(
+        &mut self,
+        email: &Email,
+        locked_until: DateTime<Utc>,
+    ) -> Result<(), LoginLockoutStoreError> {
+        self.lockouts.insert(email.clone(), locked_until);
+        Ok(())
+    }
+
+    async fn get_lockout(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, LoginLockoutStoreError> {
+        Ok(self
+            .lockouts
+            .get(email)
+            .filter(|locked_until| **locked_until > Utc::now())
+            .copied())
+    }
+
+    async fn reset(&mut self, email: &Email) -> Result<(), LoginLockoutStoreError> {
+        self.failed_attempts.remove(email);
+        self.lockouts.remove(email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use secrecy::Secret;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_failed_attempt_counts_up() {
+        let mut store = HashmapLoginLockoutStore::default();
+
+        assert_eq!(store.add_failed_attempt(&email()).await.unwrap(), 1);
+        assert_eq!(store.add_failed_attempt(&email()).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_lockout_ends_at_the_given_time() {
+        let mut store = HashmapLoginLockoutStore::default();
+        assert_eq!(store.get_lockout(&email()).await.unwrap(), None);
+
+        let locked_until = Utc::now() + Duration::minutes(1);
+        store.lock(&email(), locked_until).await.unwrap();
+        assert_eq!(
+            store.get_lockout(&email()).await.unwrap(),
+            Some(locked_until)
+        );
+
+        store
+            .lock(&email(), Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(store.get_lockout(&email()).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_reset_forgets_failed_attempts_and_lockout() {
+        let mut store = HashmapLoginLockoutStore::default();
+        store.add_failed_attempt(&email()).await.unwrap();
+        store
+            .lock(&email(), Utc::now() + Duration::minutes(1))
+            .await
+            .unwrap();
+
+        store.reset(&email()).await.unwrap();
+
+        assert_eq!(store.get_lockout(&email()).await.unwrap(), None);
+        assert_eq!(store.add_failed_attempt(&email()).await.unwrap(), 1);
+    }
+}
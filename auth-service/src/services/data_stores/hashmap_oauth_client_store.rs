@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::domain::{OAuthClient, OAuthClientSecret, OAuthClientStore, OAuthClientStoreError};
+
+#[derive(Default)]
+pub struct HashmapOAuthClientStore {
+    clients: HashMap<String, (OAuthClient, Option<OAuthClientSecret>)>,
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for HashmapOAuthClientStore {
+    async fn add_client(
+        &mut self,
+        client: OAuthClient,
+        client_secret: Option<OAuthClientSecret>,
+    ) -> Result<(), OAuthClientStoreError> {
+        if self.clients.contains_key(&client.client_id) {
+            return Err(OAuthClientStoreError::ClientAlreadyExists);
+        }
+
+        self.clients
+            .insert(client.client_id.clone(), (client, client_secret));
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, OAuthClientStoreError> {
+        self.clients
+            .get(client_id)
+            .map(|(client, _)| client.clone())
+            .ok_or(OAuthClientStoreError::ClientNotFound)
+    }
+
+    async fn validate_client_secret(
+        &self,
+        client_id: &str,
+        client_secret: &OAuthClientSecret,
+    ) -> Result<(), OAuthClientStoreError> {
+        match self.clients.get(client_id) {
+            Some((_, Some(secret))) if secret == client_secret => Ok(()),
+            Some(_) => Err(OAuthClientStoreError::InvalidClientSecret),
+            None => Err(OAuthClientStoreError::ClientNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(confidential: bool) -> OAuthClient {
+        OAuthClient {
+            client_id: "mobile-app".to_owned(),
+            redirect_uris: vec!["https://example.com/callback".to_owned()],
+            confidential,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_client() {
+        let mut store = HashmapOAuthClientStore::default();
+
+        store.add_client(client(false), None).await.unwrap();
+
+        assert_eq!(store.get_client("mobile-app").await, Ok(client(false)));
+        assert_eq!(
+            store.add_client(client(false), None).await,
+            Err(OAuthClientStoreError::ClientAlreadyExists)
+        );
+        assert_eq!(
+            store.get_client("unknown").await,
+            Err(OAuthClientStoreError::ClientNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_client_secret() {
+        let mut store = HashmapOAuthClientStore::default();
+        let secret = OAuthClientSecret::default();
+
+        store
+            .add_client(client(true), Some(secret.clone()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.validate_client_secret("mobile-app", &secret).await,
+            Ok(())
+        );
+        assert_eq!(
+            store
+                .validate_client_secret("mobile-app", &OAuthClientSecret::default())
+                .await,
+            Err(OAuthClientStoreError::InvalidClientSecret)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_public_client_has_no_valid_secret() {
+        let mut store = HashmapOAuthClientStore::default();
+
+        store.add_client(client(false), None).await.unwrap();
+
+        assert_eq!(
+            store
+                .validate_client_secret("mobile-app", &OAuthClientSecret::default())
+                .await,
+            Err(OAuthClientStoreError::InvalidClientSecret)
+        );
+    }
+}
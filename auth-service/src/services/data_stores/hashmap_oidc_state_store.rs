@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::{OidcLogin, OidcState, OidcStateStore, OidcStateStoreError};
+
+#[derive(Default)]
+pub struct HashmapOidcStateStore {
+    states: HashMap<String, OidcLogin>,
+}
+
+#[async_trait::async_trait]
+impl OidcStateStore for HashmapOidcStateStore {
+    async fn add_state(
+        &mut self,
+        state: OidcState,
+        login: OidcLogin,
+    ) -> Result<(), OidcStateStoreError> {
+        self.states
+            .insert(state.as_ref().expose_secret().to_owned(), login);
+        Ok(())
+    }
+
+    async fn use_state(&mut self, state: &OidcState) -> Result<OidcLogin, OidcStateStoreError> {
+        self.states
+            .remove(state.as_ref().expose_secret())
+            .ok_or(OidcStateStoreError::StateNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_state_can_only_be_used_once() {
+        let mut store = HashmapOidcStateStore::default();
+        let state = OidcState::default();
+        let login = OidcLogin::new("google".to_owned(), None);
+
+        store.add_state(state.clone(), login.clone()).await.unwrap();
+
+        assert_eq!(store.use_state(&state).await, Ok(login));
+        assert_eq!(
+            store.use_state(&state).await,
+            Err(OidcStateStoreError::StateNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_use_state_not_found() {
+        let mut store = HashmapOidcStateStore::default();
+
+        assert_eq!(
+            store.use_state(&OidcState::default()).await,
+            Err(OidcStateStoreError::StateNotFound)
+        );
+    }
+}
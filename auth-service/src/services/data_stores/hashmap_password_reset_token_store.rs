@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::{
+    Email, PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError,
+};
+
+#[derive(Default)]
+pub struct HashmapPasswordResetTokenStore {
+    tokens: HashMap<String, Email>,
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashmapPasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        self.tokens
+            .insert(token.as_ref().expose_secret().to_owned(), email);
+        Ok(())
+    }
+
+    async fn use_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        self.tokens
+            .remove(token.as_ref().expose_secret())
+            .ok_or(PasswordResetTokenStoreError::TokenNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    #[tokio::test]
+    async fn test_add_and_use_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let token = PasswordResetToken::default();
+
+        store.add_token(email.clone(), token.clone()).await.unwrap();
+
+        let result = store.use_token(&token).await.unwrap();
+        assert_eq!(result, email);
+    }
+
+    #[tokio::test]
+    async fn test_use_token_not_found() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+
+        let result = store.use_token(&PasswordResetToken::default()).await;
+        assert_eq!(result, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_token_can_only_be_used_once() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let token = PasswordResetToken::default();
+
+        store.add_token(email, token.clone()).await.unwrap();
+        store.use_token(&token).await.unwrap();
+
+        let result = store.use_token(&token).await;
+        assert_eq!(result, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::domain::{RateLimit, RateLimitStore, RateLimitStoreError};
+
+// Only suitable for a single node, every replica would keep its own buckets
+#[derive(Default)]
+pub struct HashmapRateLimitStore {
+    buckets: HashMap<String, Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    limit: RateLimit,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let refilled = now.duration_since(self.updated_at).as_secs_f64()
+            / self.limit.refill_interval.as_secs_f64();
+        self.tokens = (self.tokens + refilled).min(self.limit.capacity as f64);
+        self.updated_at = now;
+    }
+
+    fn is_full(&self, now: Instant) -> bool {
+        now.duration_since(self.updated_at).as_secs_f64()
+            >= self.limit.refill_interval.as_secs_f64() * self.limit.capacity as f64
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashmapRateLimitStore {
+    async fn take_token(&mut self, key: &str, limit: RateLimit) -> Result<(), RateLimitStoreError> {
+        let now = Instant::now();
+
+        // Full buckets are the same as missing ones, so drop them before the map grows too large
+        if self.buckets.len() >= MAX_BUCKETS {
+            self.buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+
+        let bucket = self.buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: limit.capacity as f64,
+            updated_at: now,
+            limit,
+        });
+        bucket.limit = limit;
+        bucket.refill(now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let retry_after = limit.refill_interval.as_secs_f64() * (1.0 - bucket.tokens);
+        Err(RateLimitStoreError::LimitExceeded(Duration::from_secs_f64(
+            retry_after,
+        )))
+    }
+}
+
+const MAX_BUCKETS: usize = 10_000;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit {
+        capacity: 2,
+        refill_interval: Duration::from_secs(60),
+    };
+
+    #[tokio::test]
+    async fn test_take_token_until_bucket_is_empty() {
+        let mut store = HashmapRateLimitStore::default();
+
+        assert_eq!(store.take_token("key", LIMIT).await, Ok(()));
+        assert_eq!(store.take_token("key", LIMIT).await, Ok(()));
+
+        match store.take_token("key", LIMIT).await {
+            Err(RateLimitStoreError::LimitExceeded(retry_after)) => {
+                assert!(retry_after <= LIMIT.refill_interval);
+                assert!(retry_after > Duration::ZERO);
+            }
+            result => panic!("expected the limit to be exceeded, got {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_buckets_are_independent() {
+        let mut store = HashmapRateLimitStore::default();
+
+        store.take_token("key", LIMIT).await.unwrap();
+        store.take_token("key", LIMIT).await.unwrap();
+
+        assert_eq!(store.take_token("other", LIMIT).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_bucket_refills_over_time() {
+        let mut store = HashmapRateLimitStore::default();
+
+        store.take_token("key", LIMIT).await.unwrap();
+        store.take_token("key", LIMIT).await.unwrap();
+
+        let bucket = store.buckets.get_mut("key").unwrap();
+        bucket.updated_at -= LIMIT.refill_interval;
+
+        assert_eq!(store.take_token("key", LIMIT).await, Ok(()));
+
+        match store.take_token("key", LIMIT).await {
+            Err(RateLimitStoreError::LimitExceeded(retry_after)) => {
+                assert!(retry_after <= LIMIT.refill_interval);
+                assert!(retry_after > Duration::ZERO);
+            }
+            result => panic!("expected the limit to be exceeded, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use crate::domain::{Email, RecoveryCode, RecoveryCodeStore, RecoveryCodeStoreError};
+
+#[derive(Default)]
+pub struct HashmapRecoveryCodeStore {
+    // Only unused codes are kept
+    codes: HashMap<Email, Vec<RecoveryCode>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for HashmapRecoveryCodeStore {
+    async fn replace_codes(
+        &mut self,
+        email: &Email,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        self.codes.insert(email.clone(), codes);
+        Ok(())
+    }
+
+    async fn use_code(
+        &mut self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<usize, RecoveryCodeStoreError> {
+        let Some(codes) = self.codes.get_mut(email) else {
+            return Err(RecoveryCodeStoreError::CodeNotFound);
+        };
+
+        let Some(index) = codes.iter().position(|stored| stored == code) else {
+            return Err(RecoveryCodeStoreError::CodeNotFound);
+        };
+
+        codes.remove(index);
+        Ok(codes.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_use_code_burns_it() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let codes = RecoveryCode::generate_set();
+
+        store.replace_codes(&email(), codes.clone()).await.unwrap();
+
+        assert_eq!(
+            store.use_code(&email(), &codes[0]).await,
+            Ok(codes.len() - 1)
+        );
+        assert_eq!(
+            store.use_code(&email(), &codes[0]).await,
+            Err(RecoveryCodeStoreError::CodeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replace_codes_invalidates_previous_set() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let old_codes = RecoveryCode::generate_set();
+        let new_codes = RecoveryCode::generate_set();
+
+        store
+            .replace_codes(&email(), old_codes.clone())
+            .await
+            .unwrap();
+        store
+            .replace_codes(&email(), new_codes.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.use_code(&email(), &old_codes[0]).await,
+            Err(RecoveryCodeStoreError::CodeNotFound)
+        );
+        assert!(store.use_code(&email(), &new_codes[0]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_use_code_for_unknown_user() {
+        let mut store = HashmapRecoveryCodeStore::default();
+
+        assert_eq!(
+            store.use_code(&email(), &RecoveryCode::default()).await,
+            Err(RecoveryCodeStoreError::CodeNotFound)
+        );
+    }
+}
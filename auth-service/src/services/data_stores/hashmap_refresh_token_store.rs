@@ -0,0 +1,214 @@
+use std::collections::{HashMap, HashSet};
+
+use secrecy::ExposeSecret;
+
+use crate::domain::{
+    Email, RefreshToken, RefreshTokenFamily, RefreshTokenStore, RefreshTokenStoreError,
+};
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+    // Maps a refresh token to its family and whether it has already been used
+    tokens: HashMap<String, (RefreshTokenFamily, bool)>,
+    revoked_families: HashSet<String>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        family: RefreshTokenFamily,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.tokens
+            .insert(token.as_ref().expose_secret().to_owned(), (family, false));
+        Ok(())
+    }
+
+    async fn use_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamily, RefreshTokenStoreError> {
+        let Some((family, used)) = self.tokens.get_mut(token.as_ref().expose_secret()) else {
+            return Err(RefreshTokenStoreError::TokenNotFound);
+        };
+
+        if self.revoked_families.contains(&family.id) {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        if *used {
+            return Err(RefreshTokenStoreError::TokenReused(family.clone()));
+        }
+
+        *used = true;
+        Ok(family.clone())
+    }
+
+    async fn revoke_family(
+        &mut self,
+        family: &RefreshTokenFamily,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.revoked_families.insert(family.id.clone());
+        Ok(())
+    }
+
+    async fn revoke_all_families(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let family_ids = self
+            .tokens
+            .values()
+            .filter(|(family, _)| &family.email == email)
+            .map(|(family, _)| family.id.clone());
+
+        self.revoked_families.extend(family_ids);
+        Ok(())
+    }
+
+    async fn remove_all_tokens(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let revoked_families = &mut self.revoked_families;
+        self.tokens.retain(|_, (family, _)| {
+            if &family.email == email {
+                revoked_families.remove(&family.id);
+                return false;
+            }
+            true
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn family() -> RefreshTokenFamily {
+        RefreshTokenFamily::new(Email::parse(Secret::new("test@example.com".to_string())).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_add_and_use_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let family = family();
+
+        store
+            .add_token(token.clone(), family.clone())
+            .await
+            .unwrap();
+
+        let result = store.use_token(&token).await.unwrap();
+        assert_eq!(result, family);
+    }
+
+    #[tokio::test]
+    async fn test_use_token_not_found() {
+        let mut store = HashmapRefreshTokenStore::default();
+
+        let result = store.use_token(&RefreshToken::default()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_use_token_twice_is_detected_as_reuse() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+
+        store.add_token(token.clone(), family()).await.unwrap();
+        store.use_token(&token).await.unwrap();
+
+        let result = store.use_token(&token).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenReused(family())));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family_invalidates_every_token_in_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let family = family();
+        let first_token = RefreshToken::default();
+        let second_token = RefreshToken::default();
+
+        store
+            .add_token(first_token.clone(), family.clone())
+            .await
+            .unwrap();
+        store
+            .add_token(second_token.clone(), family.clone())
+            .await
+            .unwrap();
+
+        store.revoke_family(&family).await.unwrap();
+
+        assert_eq!(
+            store.use_token(&first_token).await,
+            Err(RefreshTokenStoreError::FamilyRevoked)
+        );
+        assert_eq!(
+            store.use_token(&second_token).await,
+            Err(RefreshTokenStoreError::FamilyRevoked)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_families_only_affects_given_user() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let other_email = Email::parse(Secret::new("other@example.com".to_string())).unwrap();
+        let first_token = RefreshToken::default();
+        let second_token = RefreshToken::default();
+        let other_token = RefreshToken::default();
+
+        store
+            .add_token(first_token.clone(), RefreshTokenFamily::new(email.clone()))
+            .await
+            .unwrap();
+        store
+            .add_token(second_token.clone(), RefreshTokenFamily::new(email.clone()))
+            .await
+            .unwrap();
+        store
+            .add_token(other_token.clone(), RefreshTokenFamily::new(other_email))
+            .await
+            .unwrap();
+
+        store.revoke_all_families(&email).await.unwrap();
+
+        assert_eq!(
+            store.use_token(&first_token).await,
+            Err(RefreshTokenStoreError::FamilyRevoked)
+        );
+        assert_eq!(
+            store.use_token(&second_token).await,
+            Err(RefreshTokenStoreError::FamilyRevoked)
+        );
+        assert!(store.use_token(&other_token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_remove_all_tokens_only_affects_given_user() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let other_email = Email::parse(Secret::new("other@example.com".to_string())).unwrap();
+        let token = RefreshToken::default();
+        let other_token = RefreshToken::default();
+
+        store
+            .add_token(token.clone(), RefreshTokenFamily::new(email.clone()))
+            .await
+            .unwrap();
+        store
+            .add_token(other_token.clone(), RefreshTokenFamily::new(other_email))
+            .await
+            .unwrap();
+        store.revoke_all_families(&email).await.unwrap();
+
+        store.remove_all_tokens(&email).await.unwrap();
+
+        assert_eq!(
+            store.use_token(&token).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+        assert!(store.revoked_families.is_empty());
+        assert!(store.use_token(&other_token).await.is_ok());
+    }
+}
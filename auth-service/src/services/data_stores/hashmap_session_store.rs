@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{Email, Session, SessionStore, SessionStoreError};
+
+#[derive(Default)]
+pub struct HashmapSessionStore {
+    sessions: HashMap<String, Session>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        self.sessions
+            .get(id)
+            .cloned()
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        Ok(self
+            .sessions
+            .values()
+            .filter(|session| &session.email == email)
+            .cloned()
+            .collect())
+    }
+
+    async fn touch_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        let session = self
+            .sessions
+            .get_mut(id)
+            .ok_or(SessionStoreError::SessionNotFound)?;
+
+        session.last_seen = Utc::now();
+        Ok(())
+    }
+
+    async fn revoke_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        self.sessions
+            .remove(id)
+            .map(|_| ())
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn revoke_all_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        self.sessions.retain(|_, session| &session.email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_session() {
+        let mut store = HashmapSessionStore::default();
+        let session = Session::new(
+            email("test@example.com"),
+            Some("127.0.0.1".to_owned()),
+            Some("Firefox".to_owned()),
+        );
+
+        store.add_session(session.clone()).await.unwrap();
+
+        assert_eq!(store.get_session(&session.id).await, Ok(session));
+        assert_eq!(
+            store.get_session("unknown").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_sessions_only_returns_the_users_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let session = Session::new(email("test@example.com"), None, None);
+        let other_session = Session::new(email("other@example.com"), None, None);
+
+        store.add_session(session.clone()).await.unwrap();
+        store.add_session(other_session).await.unwrap();
+
+        assert_eq!(
+            store.get_sessions(&email("test@example.com")).await,
+            Ok(vec![session])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_touch_session_updates_last_seen() {
+        let mut store = HashmapSessionStore::default();
+        let session = Session::new(email("test@example.com"), None, None);
+        store.add_session(session.clone()).await.unwrap();
+
+        store.touch_session(&session.id).await.unwrap();
+
+        let touched = store.get_session(&session.id).await.unwrap();
+        assert_eq!(touched.created_at, session.created_at);
+        assert!(touched.last_seen >= session.last_seen);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_session_is_gone() {
+        let mut store = HashmapSessionStore::default();
+        let session = Session::new(email("test@example.com"), None, None);
+        store.add_session(session.clone()).await.unwrap();
+
+        store.revoke_session(&session.id).await.unwrap();
+
+        assert_eq!(
+            store.get_session(&session.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert_eq!(
+            store.touch_session(&session.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert_eq!(
+            store.revoke_session(&session.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let first = Session::new(email("test@example.com"), None, None);
+        let second = Session::new(email("test@example.com"), None, None);
+        let other_session = Session::new(email("other@example.com"), None, None);
+
+        store.add_session(first).await.unwrap();
+        store.add_session(second).await.unwrap();
+        store.add_session(other_session.clone()).await.unwrap();
+
+        store
+            .revoke_all_sessions(&email("test@example.com"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_sessions(&email("test@example.com")).await,
+            Ok(vec![])
+        );
+        assert_eq!(
+            store.get_session(&other_session.id).await,
+            Ok(other_session)
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use crate::domain::{Email, TotpEnrollment, TotpSecret, TotpSecretStore, TotpSecretStoreError};
+
+#[derive(Default)]
+pub struct HashmapTotpSecretStore {
+    enrollments: HashMap<Email, TotpEnrollment>,
+    last_used_time_steps: HashMap<Email, u64>,
+}
+
+#[async_trait::async_trait]
+impl TotpSecretStore for HashmapTotpSecretStore {
+    async fn add_secret(
+        &mut self,
+        email: Email,
+        secret: TotpSecret,
+    ) -> Result<(), TotpSecretStoreError> {
+        if matches!(self.enrollments.get(&email), Some(enrollment) if enrollment.confirmed) {
+            return Err(TotpSecretStoreError::SecretAlreadyConfirmed);
+        }
+
+        self.last_used_time_steps.remove(&email);
+        self.enrollments.insert(
+            email,
+            TotpEnrollment {
+                secret,
+                confirmed: false,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_secret(&self, email: &Email) -> Result<TotpEnrollment, TotpSecretStoreError> {
+        self.enrollments
+            .get(email)
+            .cloned()
+            .ok_or(TotpSecretStoreError::SecretNotFound)
+    }
+
+    async fn confirm_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        match self.enrollments.get_mut(email) {
+            Some(enrollment) => {
+                enrollment.confirmed = true;
+                Ok(())
+            }
+            None => Err(TotpSecretStoreError::SecretNotFound),
+        }
+    }
+
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        self.enrollments.remove(email);
+        self.last_used_time_steps.remove(email);
+        Ok(())
+    }
+
+    async fn use_time_step(
+        &mut self,
+        email: &Email,
+        time_step: u64,
+    ) -> Result<(), TotpSecretStoreError> {
+        if !self.enrollments.contains_key(email) {
+            return Err(TotpSecretStoreError::SecretNotFound);
+        }
+
+        match self.last_used_time_steps.get(email) {
+            Some(last_used) if *last_used >= time_step => {
+                Err(TotpSecretStoreError::TimeStepAlreadyUsed)
+            }
+            _ => {
+                self.last_used_time_steps.insert(email.clone(), time_step);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_confirm_secret() {
+        let mut store = HashmapTotpSecretStore::default();
+        let secret = TotpSecret::default();
+
+        assert_eq!(
+            store.get_secret(&email()).await,
+            Err(TotpSecretStoreError::SecretNotFound)
+        );
+
+        store.add_secret(email(), secret.clone()).await.unwrap();
+        assert_eq!(
+            store.get_secret(&email()).await,
+            Ok(TotpEnrollment {
+                secret: secret.clone(),
+                confirmed: false
+            })
+        );
+
+        store.confirm_secret(&email()).await.unwrap();
+        assert!(store.get_secret(&email()).await.unwrap().confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_secret_can_be_replaced() {
+        let mut store = HashmapTotpSecretStore::default();
+        let new_secret = TotpSecret::default();
+
+        store
+            .add_secret(email(), TotpSecret::default())
+            .await
+            .unwrap();
+        store.add_secret(email(), new_secret.clone()).await.unwrap();
+
+        assert_eq!(store.get_secret(&email()).await.unwrap().secret, new_secret);
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_secret_cannot_be_replaced() {
+        let mut store = HashmapTotpSecretStore::default();
+
+        store
+            .add_secret(email(), TotpSecret::default())
+            .await
+            .unwrap();
+        store.confirm_secret(&email()).await.unwrap();
+
+        assert_eq!(
+            store.add_secret(email(), TotpSecret::default()).await,
+            Err(TotpSecretStoreError::SecretAlreadyConfirmed)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_removed_secret_can_be_replaced() {
+        let mut store = HashmapTotpSecretStore::default();
+
+        store
+            .add_secret(email(), TotpSecret::default())
+            .await
+            .unwrap();
+        store.confirm_secret(&email()).await.unwrap();
+
+        store.remove_secret(&email()).await.unwrap();
+        assert_eq!(
+            store.get_secret(&email()).await,
+            Err(TotpSecretStoreError::SecretNotFound)
+        );
+        // Removing a secret that isn't there is fine
+        store.remove_secret(&email()).await.unwrap();
+
+        store
+            .add_secret(email(), TotpSecret::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_time_step_cannot_be_reused() {
+        let mut store = HashmapTotpSecretStore::default();
+
+        store
+            .add_secret(email(), TotpSecret::default())
+            .await
+            .unwrap();
+
+        store.use_time_step(&email(), 100).await.unwrap();
+        assert_eq!(
+            store.use_time_step(&email(), 100).await,
+            Err(TotpSecretStoreError::TimeStepAlreadyUsed)
+        );
+        assert_eq!(
+            store.use_time_step(&email(), 99).await,
+            Err(TotpSecretStoreError::TimeStepAlreadyUsed)
+        );
+        assert!(store.use_time_step(&email(), 101).await.is_ok());
+    }
+}
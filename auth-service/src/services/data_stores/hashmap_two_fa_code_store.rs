@@ -1,10 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
-use crate::domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError};
+use chrono::{DateTime, Duration, Utc};
+use secrecy::ExposeSecret;
+
+use crate::{
+    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    utils::constants::{MAX_PENDING_TWO_FA_LOGINS, TWO_FA_RESEND_COOLDOWN_SECONDS},
+};
 
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    // Keyed by login attempt ID
+    codes: HashMap<String, (Email, TwoFACode)>,
+    // Each user's pending login attempts, oldest first
+    pending: HashMap<Email, VecDeque<String>>,
+    attempts: HashMap<String, u32>,
+    resends: HashMap<String, (u32, DateTime<Utc>)>,
+}
+
+impl HashmapTwoFACodeStore {
+    fn forget(&mut self, login_attempt_id: &str) {
+        self.codes.remove(login_attempt_id);
+        self.attempts.remove(login_attempt_id);
+        self.resends.remove(login_attempt_id);
+    }
 }
 
 #[async_trait::async_trait]
@@ -15,36 +34,104 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        self.codes.insert(email, (login_attempt_id, code));
+        let login_attempt_id = login_attempt_id.as_ref().expose_secret().to_owned();
+        let pending = self.pending.entry(email.clone()).or_default();
+
+        if !pending.contains(&login_attempt_id) {
+            pending.push_back(login_attempt_id.clone());
+        }
+
+        let mut dropped = Vec::new();
+        while pending.len() > MAX_PENDING_TWO_FA_LOGINS {
+            dropped.extend(pending.pop_front());
+        }
+
+        for id in dropped {
+            self.forget(&id);
+        }
+
+        self.codes.insert(login_attempt_id, (email, code));
+        Ok(())
+    }
+
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let login_attempt_id = login_attempt_id.as_ref().expose_secret();
+
+        if let Some((email, _)) = self.codes.get(login_attempt_id) {
+            if let Some(pending) = self.pending.get_mut(email) {
+                pending.retain(|id| id != login_attempt_id);
+            }
+        }
+
+        self.forget(login_attempt_id);
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        self.codes.remove(email);
+    async fn remove_all_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        for id in self.pending.remove(email).unwrap_or_default() {
+            self.forget(&id);
+        }
         Ok(())
     }
 
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        match self.codes.get(email) {
-            Some((login_attempt_id, code)) => Ok((login_attempt_id.clone(), code.clone())),
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode), TwoFACodeStoreError> {
+        match self.codes.get(login_attempt_id.as_ref().expose_secret()) {
+            Some((email, code)) => Ok((email.clone(), code.clone())),
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn record_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let attempts = self
+            .attempts
+            .entry(login_attempt_id.as_ref().expose_secret().to_owned())
+            .or_default();
+        *attempts += 1;
+        Ok(*attempts)
+    }
+
+    async fn record_resend(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let now = Utc::now();
+        let (resends, last_resent) = self
+            .resends
+            .entry(login_attempt_id.as_ref().expose_secret().to_owned())
+            .or_insert((0, DateTime::<Utc>::MIN_UTC));
+
+        if now - *last_resent < Duration::seconds(TWO_FA_RESEND_COOLDOWN_SECONDS) {
+            return Err(TwoFACodeStoreError::ResendCooldownActive);
+        }
+
+        *resends += 1;
+        *last_resent = now;
+        Ok(*resends)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::Email;
     use secrecy::Secret;
 
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).unwrap()
+    }
+
     #[tokio::test]
     async fn test_add_and_get_code() {
         let mut store = HashmapTwoFACodeStore::default();
-        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let email = email("test@example.com");
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
 
@@ -53,35 +140,134 @@ mod tests {
             .await
             .unwrap();
 
-        let (retrieved_id, retrieved_code) = store.get_code(&email).await.unwrap();
-        assert_eq!(retrieved_id, login_attempt_id);
+        let (retrieved_email, retrieved_code) = store.get_code(&login_attempt_id).await.unwrap();
+        assert_eq!(retrieved_email, email);
         assert_eq!(retrieved_code, code);
     }
 
     #[tokio::test]
     async fn test_get_code_not_found() {
         let store = HashmapTwoFACodeStore::default();
-        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
 
-        let result = store.get_code(&email).await;
+        let result = store.get_code(&LoginAttemptId::default()).await;
         assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
     }
 
     #[tokio::test]
     async fn test_remove_code() {
         let mut store = HashmapTwoFACodeStore::default();
-        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
         let login_attempt_id = LoginAttemptId::default();
-        let code = TwoFACode::default();
+        let other_login_attempt_id = LoginAttemptId::default();
+
+        for id in [&login_attempt_id, &other_login_attempt_id] {
+            store
+                .add_code(email("test@example.com"), id.clone(), TwoFACode::default())
+                .await
+                .unwrap();
+        }
+
+        store.remove_code(&login_attempt_id).await.unwrap();
 
+        let result = store.get_code(&login_attempt_id).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+        assert!(store.get_code(&other_login_attempt_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_oldest_pending_login_is_dropped() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let login_attempt_ids: Vec<_> = (0..=MAX_PENDING_TWO_FA_LOGINS)
+            .map(|_| LoginAttemptId::default())
+            .collect();
+
+        for id in &login_attempt_ids {
+            store
+                .add_code(email("test@example.com"), id.clone(), TwoFACode::default())
+                .await
+                .unwrap();
+        }
+
+        let result = store.get_code(&login_attempt_ids[0]).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+        for id in &login_attempt_ids[1..] {
+            assert!(store.get_code(id).await.is_ok());
+        }
+
+        // Replacing the code of a pending attempt doesn't count as another attempt
+        store
+            .add_code(
+                email("test@example.com"),
+                login_attempt_ids[1].clone(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
+        assert!(store.get_code(&login_attempt_ids[2]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_remove_all_codes() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let login_attempt_id = LoginAttemptId::default();
+        let other_login_attempt_id = LoginAttemptId::default();
+
+        store
+            .add_code(
+                email("test@example.com"),
+                login_attempt_id.clone(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
         store
-            .add_code(email.clone(), login_attempt_id, code)
+            .add_code(
+                email("other@example.com"),
+                other_login_attempt_id.clone(),
+                TwoFACode::default(),
+            )
             .await
             .unwrap();
 
-        store.remove_code(&email).await.unwrap();
+        store
+            .remove_all_codes(&email("test@example.com"))
+            .await
+            .unwrap();
 
-        let result = store.get_code(&email).await;
+        let result = store.get_code(&login_attempt_id).await;
         assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+        assert!(store.get_code(&other_login_attempt_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_record_attempt_counts_per_login_attempt() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let login_attempt_id = LoginAttemptId::default();
+
+        assert_eq!(store.record_attempt(&login_attempt_id).await, Ok(1));
+        assert_eq!(store.record_attempt(&login_attempt_id).await, Ok(2));
+
+        let other_login_attempt_id = LoginAttemptId::default();
+        assert_eq!(store.record_attempt(&other_login_attempt_id).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_record_resend_enforces_cooldown() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let login_attempt_id = LoginAttemptId::default();
+
+        assert_eq!(store.record_resend(&login_attempt_id).await, Ok(1));
+        assert_eq!(
+            store.record_resend(&login_attempt_id).await,
+            Err(TwoFACodeStoreError::ResendCooldownActive)
+        );
+
+        // Once the cooldown is over the code can be resent again
+        let (_, last_resent) = store
+            .resends
+            .get_mut(login_attempt_id.as_ref().expose_secret())
+            .unwrap();
+        *last_resent -= Duration::seconds(TWO_FA_RESEND_COOLDOWN_SECONDS + 1);
+
+        assert_eq!(store.record_resend(&login_attempt_id).await, Ok(2));
     }
 }
@@ -1,10 +1,41 @@
 use std::collections::HashMap;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+
+use crate::{
+    domain::{Email, Password, TwoFAMethod, User, UserPage, UserStore, UserStoreError},
+    utils::constants::roles,
+};
 
-#[derive(Default)]
 pub struct HashmapUserStore {
     users: HashMap<Email, User>,
+    // Permissions granted by each role
+    roles: HashMap<String, Vec<String>>,
+}
+
+impl Default for HashmapUserStore {
+    fn default() -> Self {
+        let admin_permissions = roles::ADMIN_PERMISSIONS.map(str::to_owned).to_vec();
+
+        Self {
+            users: HashMap::new(),
+            roles: HashMap::from([(roles::ADMIN.to_owned(), admin_permissions)]),
+        }
+    }
+}
+
+impl HashmapUserStore {
+    fn update_permissions(&self, user: &mut User) {
+        let mut permissions: Vec<String> = user
+            .roles
+            .iter()
+            .flat_map(|role| self.roles.get(role).into_iter().flatten().cloned())
+            .collect();
+        permissions.sort();
+        permissions.dedup();
+        user.permissions = permissions;
+    }
 }
 
 #[async_trait::async_trait]
@@ -40,6 +71,215 @@ impl UserStore for HashmapUserStore {
             None => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.password = password;
+                user.password_reset_required = false;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.email_verified = true;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_two_fa_method(
+        &mut self,
+        email: &Email,
+        method: TwoFAMethod,
+    ) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.two_fa_method = method;
+                user.requires_2fa = true;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.requires_2fa = requires_2fa;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn disable_2fa(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.requires_2fa = false;
+                user.two_fa_method = TwoFAMethod::Email;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) if user.deleted_at.is_none() => {
+                user.deleted_at = Some(Utc::now());
+                Ok(())
+            }
+            _ => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn restore_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) if user.deleted_at.is_some() => {
+                user.deleted_at = None;
+                Ok(())
+            }
+            _ => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn increment_token_generation(&mut self, email: &Email) -> Result<i64, UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.token_generation += 1;
+                Ok(user.token_generation)
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn add_role(&mut self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        if !self.roles.contains_key(role) {
+            return Err(UserStoreError::RoleNotFound);
+        }
+
+        let mut user = self
+            .users
+            .get(email)
+            .cloned()
+            .ok_or(UserStoreError::UserNotFound)?;
+        if !user.roles.iter().any(|r| r == role) {
+            user.roles.push(role.to_owned());
+            user.roles.sort();
+        }
+        self.update_permissions(&mut user);
+        self.users.insert(email.clone(), user);
+        Ok(())
+    }
+
+    async fn remove_role(&mut self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        let mut user = self
+            .users
+            .get(email)
+            .cloned()
+            .ok_or(UserStoreError::UserNotFound)?;
+        if user.roles.iter().any(|r| r == role) {
+            user.roles.retain(|r| r != role);
+            user.token_generation += 1;
+        }
+        self.update_permissions(&mut user);
+        self.users.insert(email.clone(), user);
+        Ok(())
+    }
+
+    async fn list_users(
+        &self,
+        search: Option<&str>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<UserPage, UserStoreError> {
+        let search = search.map(str::to_lowercase);
+        let mut users: Vec<&User> = self
+            .users
+            .values()
+            .filter(|user| match &search {
+                Some(search) => user
+                    .email
+                    .as_ref()
+                    .expose_secret()
+                    .to_lowercase()
+                    .contains(search),
+                None => true,
+            })
+            .collect();
+        users.sort_by(|a, b| {
+            a.email
+                .as_ref()
+                .expose_secret()
+                .cmp(b.email.as_ref().expose_secret())
+        });
+
+        Ok(UserPage {
+            total: users.len() as u64,
+            users: users
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.disabled = disabled;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_password_reset_required(
+        &mut self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.password_reset_required = required;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn purge_deleted_users(
+        &mut self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<Vec<Email>, UserStoreError> {
+        let purged: Vec<Email> = self
+            .users
+            .values()
+            .filter(|user| matches!(user.deleted_at, Some(deleted_at) if deleted_at < deleted_before))
+            .map(|user| user.email.clone())
+            .collect();
+
+        for email in &purged {
+            self.users.remove(email);
+        }
+
+        Ok(purged)
+    }
 }
 
 #[cfg(test)]
@@ -54,6 +294,14 @@ mod tests {
             email: Email::parse(Secret::new("test@example.com".to_string())).unwrap(),
             password: Password::parse(Secret::new("password123".to_string())).unwrap(),
             requires_2fa: false,
+            email_verified: false,
+            two_fa_method: TwoFAMethod::Email,
+            deleted_at: None,
+            token_generation: 0,
+            roles: Vec::new(),
+            permissions: Vec::new(),
+            disabled: false,
+            password_reset_required: false,
         };
 
         // Test successful addition
@@ -73,6 +321,14 @@ mod tests {
             email: Email::parse(Secret::new("test@example.com".to_string())).unwrap(),
             password: Password::parse(Secret::new("password123".to_string())).unwrap(),
             requires_2fa: true,
+            email_verified: false,
+            two_fa_method: TwoFAMethod::Email,
+            deleted_at: None,
+            token_generation: 0,
+            roles: Vec::new(),
+            permissions: Vec::new(),
+            disabled: false,
+            password_reset_required: false,
         };
 
         // Test user not found
@@ -100,6 +356,14 @@ mod tests {
             email: Email::parse(Secret::new("test@example.com".to_string())).unwrap(),
             password: Password::parse(Secret::new("password123".to_string())).unwrap(),
             requires_2fa: false,
+            email_verified: false,
+            two_fa_method: TwoFAMethod::Email,
+            deleted_at: None,
+            token_generation: 0,
+            roles: Vec::new(),
+            permissions: Vec::new(),
+            disabled: false,
+            password_reset_required: false,
         };
 
         // Test user not found
@@ -136,4 +400,360 @@ mod tests {
             Err(UserStoreError::InvalidCredentials)
         );
     }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let old_password = Password::parse(Secret::new("password123".to_string())).unwrap();
+        let new_password = Password::parse(Secret::new("newpassword123".to_string())).unwrap();
+
+        // Test user not found
+        assert_eq!(
+            store.update_password(&email, new_password.clone()).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        store
+            .add_user(User::new(email.clone(), old_password.clone(), false))
+            .await
+            .unwrap();
+
+        assert!(store
+            .update_password(&email, new_password.clone())
+            .await
+            .is_ok());
+
+        // Only the new password is accepted afterwards
+        assert!(store.validate_user(&email, &new_password).await.is_ok());
+        assert_eq!(
+            store.validate_user(&email, &old_password).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mark_email_verified() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+
+        // Test user not found
+        assert_eq!(
+            store.mark_email_verified(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        store
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+        assert!(!store.get_user(&email).await.unwrap().email_verified);
+
+        store.mark_email_verified(&email).await.unwrap();
+        assert!(store.get_user(&email).await.unwrap().email_verified);
+    }
+
+    #[tokio::test]
+    async fn test_set_two_fa_method() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+
+        // Test user not found
+        assert_eq!(
+            store.set_two_fa_method(&email, TwoFAMethod::Totp).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        store
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+
+        store
+            .set_two_fa_method(&email, TwoFAMethod::Totp)
+            .await
+            .unwrap();
+
+        let user = store.get_user(&email).await.unwrap();
+        assert_eq!(user.two_fa_method, TwoFAMethod::Totp);
+        assert!(user.requires_2fa);
+    }
+
+    #[tokio::test]
+    async fn test_set_requires_2fa() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+
+        // Test user not found
+        assert_eq!(
+            store.set_requires_2fa(&email, true).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        store
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+        store
+            .set_two_fa_method(&email, TwoFAMethod::Totp)
+            .await
+            .unwrap();
+
+        store.set_requires_2fa(&email, false).await.unwrap();
+
+        // The method is kept for when 2FA is turned back on
+        let user = store.get_user(&email).await.unwrap();
+        assert!(!user.requires_2fa);
+        assert_eq!(user.two_fa_method, TwoFAMethod::Totp);
+
+        store.set_requires_2fa(&email, true).await.unwrap();
+        assert!(store.get_user(&email).await.unwrap().requires_2fa);
+    }
+
+    #[tokio::test]
+    async fn test_disable_2fa() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+
+        // Test user not found
+        assert_eq!(store.disable_2fa(&email).await, Err(UserStoreError::UserNotFound));
+
+        store
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+        store
+            .set_two_fa_method(&email, TwoFAMethod::Totp)
+            .await
+            .unwrap();
+
+        store.disable_2fa(&email).await.unwrap();
+
+        let user = store.get_user(&email).await.unwrap();
+        assert!(!user.requires_2fa);
+        assert_eq!(user.two_fa_method, TwoFAMethod::Email);
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_restore_user() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+
+        // Test user not found
+        assert_eq!(
+            store.delete_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        store
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+
+        // Restoring an active user is not possible
+        assert_eq!(
+            store.restore_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        store.delete_user(&email).await.unwrap();
+        assert!(store.get_user(&email).await.unwrap().deleted_at.is_some());
+
+        store.restore_user(&email).await.unwrap();
+        assert!(store.get_user(&email).await.unwrap().deleted_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_purge_deleted_users() {
+        let mut store = HashmapUserStore::default();
+        let deleted_email = Email::parse(Secret::new("deleted@example.com".to_string())).unwrap();
+        let active_email = Email::parse(Secret::new("active@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+
+        store
+            .add_user(User::new(deleted_email.clone(), password.clone(), false))
+            .await
+            .unwrap();
+        store
+            .add_user(User::new(active_email.clone(), password, false))
+            .await
+            .unwrap();
+        store.delete_user(&deleted_email).await.unwrap();
+
+        // Users deleted after the cutoff are kept
+        let cutoff = Utc::now() - chrono::Duration::days(1);
+        assert_eq!(store.purge_deleted_users(cutoff).await, Ok(vec![]));
+
+        let purged = store
+            .purge_deleted_users(Utc::now() + chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(purged, vec![deleted_email.clone()]);
+
+        assert_eq!(
+            store.get_user(&deleted_email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        assert!(store.get_user(&active_email).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_increment_token_generation() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+
+        assert_eq!(
+            store.increment_token_generation(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        store
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+        assert_eq!(store.get_user(&email).await.unwrap().token_generation, 0);
+
+        assert_eq!(store.increment_token_generation(&email).await, Ok(1));
+        assert_eq!(store.increment_token_generation(&email).await, Ok(2));
+        assert_eq!(store.get_user(&email).await.unwrap().token_generation, 2);
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_role() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+
+        assert_eq!(
+            store.add_role(&email, roles::ADMIN).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        store
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.add_role(&email, "unknown").await,
+            Err(UserStoreError::RoleNotFound)
+        );
+
+        store.add_role(&email, roles::ADMIN).await.unwrap();
+        store.add_role(&email, roles::ADMIN).await.unwrap();
+        let user = store.get_user(&email).await.unwrap();
+        assert_eq!(user.roles, vec![roles::ADMIN.to_owned()]);
+        assert_eq!(
+            user.permissions,
+            roles::ADMIN_PERMISSIONS.map(str::to_owned).to_vec()
+        );
+
+        store.remove_role(&email, roles::ADMIN).await.unwrap();
+        let user = store.get_user(&email).await.unwrap();
+        assert!(user.roles.is_empty());
+        assert!(user.permissions.is_empty());
+        // Tokens issued while the user had the role are invalidated
+        assert_eq!(user.token_generation, 1);
+
+        // Removing a role the user doesn't have changes nothing
+        store.remove_role(&email, roles::ADMIN).await.unwrap();
+        assert_eq!(store.get_user(&email).await.unwrap().token_generation, 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_users() {
+        let mut store = HashmapUserStore::default();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+
+        for email in ["carol@example.com", "alice@example.com", "bob@test.com"] {
+            let email = Email::parse(Secret::new(email.to_string())).unwrap();
+            store
+                .add_user(User::new(email, password.clone(), false))
+                .await
+                .unwrap();
+        }
+
+        let emails = |page: UserPage| {
+            page.users
+                .into_iter()
+                .map(|user| user.email.as_ref().expose_secret().clone())
+                .collect::<Vec<_>>()
+        };
+
+        // Users come ordered by email, and the total covers every page
+        let page = store.list_users(None, 0, 2).await.unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(emails(page), vec!["alice@example.com", "bob@test.com"]);
+
+        let page = store.list_users(None, 2, 2).await.unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(emails(page), vec!["carol@example.com"]);
+
+        let page = store.list_users(Some("EXAMPLE"), 0, 10).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(emails(page), vec!["alice@example.com", "carol@example.com"]);
+
+        let page = store.list_users(Some("nobody"), 0, 10).await.unwrap();
+        assert_eq!(page.total, 0);
+        assert!(page.users.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_set_disabled() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+
+        assert_eq!(
+            store.set_disabled(&email, true).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        store
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+        assert!(!store.get_user(&email).await.unwrap().disabled);
+
+        store.set_disabled(&email, true).await.unwrap();
+        assert!(store.get_user(&email).await.unwrap().disabled);
+
+        store.set_disabled(&email, false).await.unwrap();
+        assert!(!store.get_user(&email).await.unwrap().disabled);
+    }
+
+    #[tokio::test]
+    async fn test_password_reset_required_until_password_updated() {
+        let mut store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_string())).unwrap();
+        let new_password = Password::parse(Secret::new("newpassword123".to_string())).unwrap();
+
+        assert_eq!(
+            store.set_password_reset_required(&email, true).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        store
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+
+        store
+            .set_password_reset_required(&email, true)
+            .await
+            .unwrap();
+        let user = store.get_user(&email).await.unwrap();
+        assert!(user.password_reset_required);
+
+        // Changing the password lifts the requirement
+        store.update_password(&email, new_password).await.unwrap();
+        let user = store.get_user(&email).await.unwrap();
+        assert!(!user.password_reset_required);
+    }
 }
@@ -2,18 +2,11 @@ use crate::domain::{BannedTokenStore, BannedTokenStoreError};
 use secrecy::{ExposeSecret, Secret};
 use std::collections::HashSet;
 
+#[derive(Default)]
 pub struct HashsetBannedTokenStore {
     tokens: HashSet<String>,
 }
 
-impl Default for HashsetBannedTokenStore {
-    fn default() -> Self {
-        Self {
-            tokens: HashSet::new(),
-        }
-    }
-}
-
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
     async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
@@ -65,7 +58,10 @@ mod tests {
         assert!(store.contains_token(&token1).await.unwrap());
         assert!(store.contains_token(&token2).await.unwrap());
         assert!(store.contains_token(&token3).await.unwrap());
-        assert!(!store.contains_token(&Secret::new("unknown".to_string())).await.unwrap());
+        assert!(!store
+            .contains_token(&Secret::new("unknown".to_string()))
+            .await
+            .unwrap());
     }
 
     #[tokio::test]
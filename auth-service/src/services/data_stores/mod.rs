@@ -1,13 +1,61 @@
+pub mod hashmap_authorization_code_store;
+pub mod hashmap_email_verification_token_store;
+pub mod hashmap_login_lockout_store;
+pub mod hashmap_magic_link_token_store;
+pub mod hashmap_oauth_client_store;
+pub mod hashmap_oidc_state_store;
+pub mod hashmap_password_reset_token_store;
+pub mod hashmap_rate_limit_store;
+pub mod hashmap_recovery_code_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_session_store;
+pub mod hashmap_totp_secret_store;
 pub mod hashmap_two_fa_code_store;
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
+pub mod postgres_oauth_client_store;
+pub mod postgres_recovery_code_store;
+pub mod postgres_totp_secret_store;
 pub mod postgres_user_store;
+pub mod redis_authorization_code_store;
 pub mod redis_banned_token_store;
+pub mod redis_email_verification_token_store;
+pub mod redis_login_lockout_store;
+pub mod redis_magic_link_token_store;
+pub mod redis_oidc_state_store;
+pub mod redis_password_reset_token_store;
+pub mod redis_rate_limit_store;
+pub mod redis_refresh_token_store;
+pub mod redis_session_store;
 pub mod redis_two_fa_code_store;
 
+pub use hashmap_authorization_code_store::HashmapAuthorizationCodeStore;
+pub use hashmap_email_verification_token_store::HashmapEmailVerificationTokenStore;
+pub use hashmap_login_lockout_store::HashmapLoginLockoutStore;
+pub use hashmap_magic_link_token_store::HashmapMagicLinkTokenStore;
+pub use hashmap_oauth_client_store::HashmapOAuthClientStore;
+pub use hashmap_oidc_state_store::HashmapOidcStateStore;
+pub use hashmap_password_reset_token_store::HashmapPasswordResetTokenStore;
+pub use hashmap_rate_limit_store::HashmapRateLimitStore;
+pub use hashmap_recovery_code_store::HashmapRecoveryCodeStore;
+pub use hashmap_refresh_token_store::HashmapRefreshTokenStore;
+pub use hashmap_session_store::HashmapSessionStore;
+pub use hashmap_totp_secret_store::HashmapTotpSecretStore;
 pub use hashmap_two_fa_code_store::HashmapTwoFACodeStore;
 pub use hashmap_user_store::HashmapUserStore;
 pub use hashset_banned_token_store::HashsetBannedTokenStore;
+pub use postgres_oauth_client_store::PostgresOAuthClientStore;
+pub use postgres_recovery_code_store::PostgresRecoveryCodeStore;
+pub use postgres_totp_secret_store::PostgresTotpSecretStore;
 pub use postgres_user_store::PostgresUserStore;
+pub use redis_authorization_code_store::RedisAuthorizationCodeStore;
 pub use redis_banned_token_store::RedisBannedTokenStore;
+pub use redis_email_verification_token_store::RedisEmailVerificationTokenStore;
+pub use redis_login_lockout_store::RedisLoginLockoutStore;
+pub use redis_magic_link_token_store::RedisMagicLinkTokenStore;
+pub use redis_oidc_state_store::RedisOidcStateStore;
+pub use redis_password_reset_token_store::RedisPasswordResetTokenStore;
+pub use redis_rate_limit_store::RedisRateLimitStore;
+pub use redis_refresh_token_store::RedisRefreshTokenStore;
+pub use redis_session_store::RedisSessionStore;
 pub use redis_two_fa_code_store::RedisTwoFACodeStore;
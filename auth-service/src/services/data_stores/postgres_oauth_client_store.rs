@@ -0,0 +1,100 @@
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::domain::{OAuthClient, OAuthClientSecret, OAuthClientStore, OAuthClientStoreError};
+
+pub struct PostgresOAuthClientStore {
+    pool: PgPool,
+}
+
+impl PostgresOAuthClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for PostgresOAuthClientStore {
+    #[tracing::instrument(name = "Adding OAuth client to PostgreSQL", skip_all)]
+    async fn add_client(
+        &mut self,
+        client: OAuthClient,
+        client_secret: Option<OAuthClientSecret>,
+    ) -> Result<(), OAuthClientStoreError> {
+        let client_secret_hash = client_secret.as_ref().map(compute_secret_hash);
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO oauth_clients (client_id, client_secret_hash, redirect_uris)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (client_id) DO NOTHING
+            "#,
+            client.client_id,
+            client_secret_hash,
+            &client.redirect_uris
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(OAuthClientStoreError::ClientAlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving OAuth client from PostgreSQL", skip_all)]
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, OAuthClientStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT client_id, client_secret_hash IS NOT NULL AS "confidential!", redirect_uris
+            FROM oauth_clients
+            WHERE client_id = $1
+            "#,
+            client_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?
+        .map(|row| OAuthClient {
+            client_id: row.client_id,
+            redirect_uris: row.redirect_uris,
+            confidential: row.confidential,
+        })
+        .ok_or(OAuthClientStoreError::ClientNotFound)
+    }
+
+    #[tracing::instrument(name = "Validating OAuth client secret in PostgreSQL", skip_all)]
+    async fn validate_client_secret(
+        &self,
+        client_id: &str,
+        client_secret: &OAuthClientSecret,
+    ) -> Result<(), OAuthClientStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT client_secret_hash
+            FROM oauth_clients
+            WHERE client_id = $1
+            "#,
+            client_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?
+        .ok_or(OAuthClientStoreError::ClientNotFound)?;
+
+        match row.client_secret_hash {
+            Some(hash) if hash == compute_secret_hash(client_secret) => Ok(()),
+            _ => Err(OAuthClientStoreError::InvalidClientSecret),
+        }
+    }
+}
+
+fn compute_secret_hash(secret: &OAuthClientSecret) -> String {
+    format!(
+        "{:x}",
+        Sha256::digest(secret.as_ref().expose_secret().as_bytes())
+    )
+}
@@ -0,0 +1,212 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::domain::{Email, TotpEnrollment, TotpSecret, TotpSecretStore, TotpSecretStoreError};
+
+// Length of the AES-256-GCM nonce prepended to every encrypted secret
+const NONCE_LENGTH: usize = 12;
+
+pub struct PostgresTotpSecretStore {
+    pool: PgPool,
+    encryption_key: Secret<String>,
+}
+
+impl PostgresTotpSecretStore {
+    // `encryption_key` is a base64 encoded 256-bit key used to encrypt secrets at rest
+    pub fn new(pool: PgPool, encryption_key: Secret<String>) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpSecretStore for PostgresTotpSecretStore {
+    #[tracing::instrument(name = "Adding TOTP secret to PostgreSQL", skip_all)]
+    async fn add_secret(
+        &mut self,
+        email: Email,
+        secret: TotpSecret,
+    ) -> Result<(), TotpSecretStoreError> {
+        let encrypted_secret = encrypt_secret(&self.encryption_key, &email, &secret)
+            .map_err(TotpSecretStoreError::UnexpectedError)?;
+
+        // A confirmed secret is never overwritten
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO totp_secrets (email, encrypted_secret)
+            VALUES ($1, $2)
+            ON CONFLICT (email) DO UPDATE
+            SET encrypted_secret = EXCLUDED.encrypted_secret, last_used_time_step = NULL
+            WHERE totp_secrets.confirmed = FALSE
+            "#,
+            email.as_ref().expose_secret(),
+            encrypted_secret
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpSecretStoreError::SecretAlreadyConfirmed);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving TOTP secret from PostgreSQL", skip_all)]
+    async fn get_secret(&self, email: &Email) -> Result<TotpEnrollment, TotpSecretStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT encrypted_secret, confirmed
+            FROM totp_secrets
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?
+        .ok_or(TotpSecretStoreError::SecretNotFound)?;
+
+        let secret = decrypt_secret(&self.encryption_key, email, &row.encrypted_secret)
+            .map_err(TotpSecretStoreError::UnexpectedError)?;
+
+        Ok(TotpEnrollment {
+            secret,
+            confirmed: row.confirmed,
+        })
+    }
+
+    #[tracing::instrument(name = "Confirming TOTP secret in PostgreSQL", skip_all)]
+    async fn confirm_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_secrets
+            SET confirmed = TRUE
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpSecretStoreError::SecretNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing TOTP secret from PostgreSQL", skip_all)]
+    async fn remove_secret(&mut self, email: &Email) -> Result<(), TotpSecretStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM totp_secrets
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Recording TOTP time step in PostgreSQL", skip_all)]
+    async fn use_time_step(
+        &mut self,
+        email: &Email,
+        time_step: u64,
+    ) -> Result<(), TotpSecretStoreError> {
+        let time_step: i64 = time_step
+            .try_into()
+            .wrap_err("failed to cast TOTP time step to i64")
+            .map_err(TotpSecretStoreError::UnexpectedError)?;
+
+        // The update only applies when the step is newer, which makes the check atomic
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_secrets
+            SET last_used_time_step = $2
+            WHERE email = $1 AND (last_used_time_step IS NULL OR last_used_time_step < $2)
+            "#,
+            email.as_ref().expose_secret(),
+            time_step
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TotpSecretStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TotpSecretStoreError::TimeStepAlreadyUsed);
+        }
+
+        Ok(())
+    }
+}
+
+fn cipher(encryption_key: &Secret<String>) -> Result<Aes256Gcm> {
+    let key = STANDARD
+        .decode(encryption_key.expose_secret())
+        .wrap_err("failed to decode TOTP encryption key")?;
+
+    if key.len() != 32 {
+        return Err(eyre!("TOTP encryption key must be 32 bytes long"));
+    }
+
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+// The email is bound to the ciphertext so a secret can't be moved to another account
+#[tracing::instrument(name = "Encrypting TOTP secret", skip_all)]
+fn encrypt_secret(
+    encryption_key: &Secret<String>,
+    email: &Email,
+    secret: &TotpSecret,
+) -> Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let payload = Payload {
+        msg: secret.as_ref().expose_secret().as_bytes(),
+        aad: email.as_ref().expose_secret().as_bytes(),
+    };
+
+    let ciphertext = cipher(encryption_key)?
+        .encrypt(&nonce, payload)
+        .map_err(|_| eyre!("failed to encrypt TOTP secret"))?;
+
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+#[tracing::instrument(name = "Decrypting TOTP secret", skip_all)]
+fn decrypt_secret(
+    encryption_key: &Secret<String>,
+    email: &Email,
+    encrypted_secret: &[u8],
+) -> Result<TotpSecret> {
+    if encrypted_secret.len() < NONCE_LENGTH {
+        return Err(eyre!("encrypted TOTP secret is too short"));
+    }
+
+    let (nonce, ciphertext) = encrypted_secret.split_at(NONCE_LENGTH);
+    let payload = Payload {
+        msg: ciphertext,
+        aad: email.as_ref().expose_secret().as_bytes(),
+    };
+
+    let plaintext = cipher(encryption_key)?
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| eyre!("failed to decrypt TOTP secret"))?;
+
+    let secret = String::from_utf8(plaintext).wrap_err("decrypted TOTP secret is not UTF-8")?;
+    TotpSecret::parse(Secret::new(secret))
+}
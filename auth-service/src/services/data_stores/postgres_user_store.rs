@@ -2,11 +2,12 @@ use argon2::{
     password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
     PasswordVerifier, Version,
 };
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{eyre, Context, Result};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use crate::domain::{Email, Password, TwoFAMethod, User, UserPage, UserStore, UserStoreError};
 
 pub struct PostgresUserStore {
     pool: PgPool,
@@ -28,12 +29,14 @@ impl UserStore for PostgresUserStore {
 
         sqlx::query!(
             r#"
-            INSERT INTO users (email, password_hash, requires_2fa)
-            VALUES ($1, $2, $3)
+            INSERT INTO users (email, password_hash, requires_2fa, email_verified, two_fa_method)
+            VALUES ($1, $2, $3, $4, $5)
             "#,
             user.email.as_ref().expose_secret(),
             &password_hash.expose_secret(),
-            user.requires_2fa
+            user.requires_2fa,
+            user.email_verified,
+            user.two_fa_method.as_str()
         )
         .execute(&self.pool)
         .await
@@ -49,9 +52,23 @@ impl UserStore for PostgresUserStore {
 
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        sqlx::query!(
+        sqlx::query_as!(
+            UserRow,
             r#"
-            SELECT email, password_hash, requires_2fa
+            SELECT email, password_hash, requires_2fa, email_verified, two_fa_method, deleted_at,
+                token_generation, disabled, password_reset_required,
+                ARRAY(
+                    SELECT role FROM user_roles
+                    WHERE user_roles.email = users.email
+                    ORDER BY role
+                ) AS "roles!",
+                ARRAY(
+                    SELECT DISTINCT role_permissions.permission
+                    FROM user_roles
+                    JOIN role_permissions ON role_permissions.role = user_roles.role
+                    WHERE user_roles.email = users.email
+                    ORDER BY role_permissions.permission
+                ) AS "permissions!"
             FROM users
             WHERE email = $1
             "#,
@@ -60,16 +77,8 @@ impl UserStore for PostgresUserStore {
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
-        .map(|row| {
-            Ok(User {
-                email: Email::parse(Secret::new(row.email))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                password: Password::parse(Secret::new(row.password_hash))
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                requires_2fa: row.requires_2fa,
-            })
-        })
         .ok_or(UserStoreError::UserNotFound)?
+        .try_into()
     }
 
     #[tracing::instrument(name = "Validating user credentials in PostgreSQL", skip_all)]
@@ -89,6 +98,435 @@ impl UserStore for PostgresUserStore {
 
         Ok(())
     }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $1, password_reset_required = FALSE
+            WHERE email = $2
+            "#,
+            &password_hash.expose_secret(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking user email as verified in PostgreSQL", skip_all)]
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET email_verified = TRUE
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting user 2FA method in PostgreSQL", skip_all)]
+    async fn set_two_fa_method(
+        &mut self,
+        email: &Email,
+        method: TwoFAMethod,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET two_fa_method = $1, requires_2fa = TRUE
+            WHERE email = $2
+            "#,
+            method.as_str(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting 2FA requirement in PostgreSQL", skip_all)]
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET requires_2fa = $1
+            WHERE email = $2
+            "#,
+            requires_2fa,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Disabling 2FA in PostgreSQL", skip_all)]
+    async fn disable_2fa(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET requires_2fa = FALSE, two_fa_method = $1
+            WHERE email = $2
+            "#,
+            TwoFAMethod::Email.as_str(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Soft-deleting user in PostgreSQL", skip_all)]
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET deleted_at = NOW()
+            WHERE email = $1 AND deleted_at IS NULL
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Restoring user in PostgreSQL", skip_all)]
+    async fn restore_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET deleted_at = NULL
+            WHERE email = $1 AND deleted_at IS NOT NULL
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Incrementing user token generation in PostgreSQL", skip_all)]
+    async fn increment_token_generation(&mut self, email: &Email) -> Result<i64, UserStoreError> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET token_generation = token_generation + 1
+            WHERE email = $1
+            RETURNING token_generation
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .map(|row| row.token_generation)
+        .ok_or(UserStoreError::UserNotFound)
+    }
+
+    #[tracing::instrument(name = "Adding role to user in PostgreSQL", skip_all)]
+    async fn add_role(&mut self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+            WITH target AS (SELECT email FROM users WHERE email = $1),
+            granted AS (
+                INSERT INTO user_roles(email, role)
+                SELECT email, $2 FROM target
+                ON CONFLICT DO NOTHING
+            )
+            SELECT EXISTS(SELECT 1 FROM target) AS "user_exists!"
+            "#,
+            email.as_ref().expose_secret(),
+            role
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                UserStoreError::RoleNotFound
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        if !row.user_exists {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing role from user in PostgreSQL", skip_all)]
+    async fn remove_role(&mut self, email: &Email, role: &str) -> Result<(), UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+            WITH target AS (SELECT email FROM users WHERE email = $1),
+            revoked AS (
+                DELETE FROM user_roles
+                WHERE email IN (SELECT email FROM target) AND role = $2
+                RETURNING email
+            ),
+            bumped AS (
+                UPDATE users
+                SET token_generation = token_generation + 1
+                WHERE email IN (SELECT email FROM revoked)
+            )
+            SELECT EXISTS(SELECT 1 FROM target) AS "user_exists!"
+            "#,
+            email.as_ref().expose_secret(),
+            role
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if !row.user_exists {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing users from PostgreSQL", skip_all)]
+    async fn list_users(
+        &self,
+        search: Option<&str>,
+        offset: u64,
+        limit: u64,
+    ) -> Result<UserPage, UserStoreError> {
+        let pattern = search.map(|search| format!("%{}%", escape_like(search)));
+        let offset =
+            i64::try_from(offset).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let limit = i64::try_from(limit).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let total = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "total!"
+            FROM users
+            WHERE $1::TEXT IS NULL OR email ILIKE $1
+            "#,
+            pattern
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .total;
+
+        let users = sqlx::query_as!(
+            UserRow,
+            r#"
+            SELECT email, password_hash, requires_2fa, email_verified, two_fa_method, deleted_at,
+                token_generation, disabled, password_reset_required,
+                ARRAY(
+                    SELECT role FROM user_roles
+                    WHERE user_roles.email = users.email
+                    ORDER BY role
+                ) AS "roles!",
+                ARRAY(
+                    SELECT DISTINCT role_permissions.permission
+                    FROM user_roles
+                    JOIN role_permissions ON role_permissions.role = user_roles.role
+                    WHERE user_roles.email = users.email
+                    ORDER BY role_permissions.permission
+                ) AS "permissions!"
+            FROM users
+            WHERE $1::TEXT IS NULL OR email ILIKE $1
+            ORDER BY email
+            LIMIT $2 OFFSET $3
+            "#,
+            pattern,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(User::try_from)
+        .collect::<Result<_, _>>()?;
+
+        Ok(UserPage {
+            users,
+            total: total as u64,
+        })
+    }
+
+    #[tracing::instrument(name = "Setting user disabled in PostgreSQL", skip_all)]
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET disabled = $1
+            WHERE email = $2
+            "#,
+            disabled,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting password reset requirement in PostgreSQL", skip_all)]
+    async fn set_password_reset_required(
+        &mut self,
+        email: &Email,
+        required: bool,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_reset_required = $1
+            WHERE email = $2
+            "#,
+            required,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Purging deleted users from PostgreSQL", skip_all)]
+    async fn purge_deleted_users(
+        &mut self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<Vec<Email>, UserStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE deleted_at < $1
+            RETURNING email
+            "#,
+            deleted_before
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Email::parse(Secret::new(row.email))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+        })
+        .collect()
+    }
+}
+
+// Columns of `users` a `User` is read from, along with the user's roles and their permissions
+struct UserRow {
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    email_verified: bool,
+    two_fa_method: String,
+    deleted_at: Option<DateTime<Utc>>,
+    token_generation: i64,
+    disabled: bool,
+    password_reset_required: bool,
+    roles: Vec<String>,
+    permissions: Vec<String>,
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = UserStoreError;
+
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        Ok(User {
+            email: Email::parse(Secret::new(row.email))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            password: Password::parse(Secret::new(row.password_hash))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            requires_2fa: row.requires_2fa,
+            email_verified: row.email_verified,
+            two_fa_method: TwoFAMethod::parse(&row.two_fa_method)
+                .map_err(UserStoreError::UnexpectedError)?,
+            deleted_at: row.deleted_at,
+            token_generation: row.token_generation,
+            roles: row.roles,
+            permissions: row.permissions,
+            disabled: row.disabled,
+            password_reset_required: row.password_reset_required,
+        })
+    }
+}
+
+// Searches match literally, so `%` and `_` in them must not act as LIKE wildcards
+fn escape_like(search: &str) -> String {
+    search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
 
 #[tracing::instrument(name = "Verify password hash", skip_all)]
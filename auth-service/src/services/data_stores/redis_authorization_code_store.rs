@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError, AuthorizationGrant,
+    Email,
+};
+
+pub struct RedisAuthorizationCodeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisAuthorizationCodeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for RedisAuthorizationCodeStore {
+    #[tracing::instrument(name = "Adding authorization code to Redis", skip_all)]
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let key = get_key(&code);
+
+        let data = StoredGrant {
+            client_id: grant.client_id,
+            redirect_uri: grant.redirect_uri,
+            email: grant.email.as_ref().expose_secret().to_owned(),
+            code_challenge: grant.code_challenge,
+            scope: grant.scope,
+            nonce: grant.nonce,
+        };
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("failed to serialize authorization grant")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(&key, serialized_data, FIVE_MINUTES_IN_SECONDS)
+            .wrap_err("failed to set authorization code in Redis")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Using authorization code in Redis", skip_all)]
+    async fn use_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        let key = get_key(code);
+
+        // GETDEL makes sure two concurrent requests can't both exchange the code
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(&key)
+            .wrap_err("failed to get authorization code from Redis")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        let Some(value) = value else {
+            return Err(AuthorizationCodeStoreError::CodeNotFound);
+        };
+
+        let data: StoredGrant = serde_json::from_str(&value)
+            .wrap_err("failed to deserialize authorization grant")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        Ok(AuthorizationGrant {
+            client_id: data.client_id,
+            redirect_uri: data.redirect_uri,
+            email: Email::parse(Secret::new(data.email))
+                .map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            code_challenge: data.code_challenge,
+            scope: data.scope,
+            nonce: data.nonce,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredGrant {
+    client_id: String,
+    redirect_uri: String,
+    email: String,
+    code_challenge: String,
+    scope: Option<String>,
+    nonce: Option<String>,
+}
+
+const FIVE_MINUTES_IN_SECONDS: u64 = 300;
+const AUTHORIZATION_CODE_KEY_PREFIX: &str = "authorization_code:";
+
+fn get_key(code: &AuthorizationCode) -> String {
+    format!(
+        "{}{}",
+        AUTHORIZATION_CODE_KEY_PREFIX,
+        code.as_ref().expose_secret()
+    )
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        Email, EmailVerificationToken, EmailVerificationTokenStore,
+        EmailVerificationTokenStoreError,
+    },
+    utils::constants::EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS,
+};
+
+pub struct RedisEmailVerificationTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisEmailVerificationTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for RedisEmailVerificationTokenStore {
+    #[tracing::instrument(name = "Adding email verification token to Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let mut conn = self.conn.write().await;
+
+        // SET NX only succeeds if no token was issued for this email within the cooldown
+        let cooldown_started: Option<String> = redis::cmd("SET")
+            .arg(get_cooldown_key(&email))
+            .arg(true)
+            .arg("NX")
+            .arg("EX")
+            .arg(EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS)
+            .query(&mut *conn)
+            .wrap_err("failed to set email verification cooldown in Redis")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        if cooldown_started.is_none() {
+            return Err(EmailVerificationTokenStoreError::CooldownActive);
+        }
+
+        let _: () = conn
+            .set_ex(
+                get_token_key(&token),
+                email.as_ref().expose_secret(),
+                ONE_DAY_IN_SECONDS,
+            )
+            .wrap_err("failed to set email verification token in Redis")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Using email verification token in Redis", skip_all)]
+    async fn use_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError> {
+        let email: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(get_token_key(token))
+            .wrap_err("failed to get email verification token from Redis")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        match email {
+            Some(email) => Email::parse(Secret::new(email))
+                .map_err(EmailVerificationTokenStoreError::UnexpectedError),
+            None => Err(EmailVerificationTokenStoreError::TokenNotFound),
+        }
+    }
+}
+
+const ONE_DAY_IN_SECONDS: u64 = 60 * 60 * 24;
+const EMAIL_VERIFICATION_TOKEN_KEY_PREFIX: &str = "email_verification_token:";
+const EMAIL_VERIFICATION_COOLDOWN_KEY_PREFIX: &str = "email_verification_cooldown:";
+
+fn get_token_key(token: &EmailVerificationToken) -> String {
+    format!(
+        "{}{}",
+        EMAIL_VERIFICATION_TOKEN_KEY_PREFIX,
+        token.as_ref().expose_secret()
+    )
+}
+
+fn get_cooldown_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        EMAIL_VERIFICATION_COOLDOWN_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
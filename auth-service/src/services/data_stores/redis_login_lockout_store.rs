@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection};
+use secrecy::ExposeSecret;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{Email, LoginLockoutStore, LoginLockoutStoreError},
+    utils::lockout::FAILED_LOGIN_ATTEMPTS_TTL_SECONDS,
+};
+
+pub struct RedisLoginLockoutStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisLoginLockoutStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginLockoutStore for RedisLoginLockoutStore {
+    #[tracing::instrument(name = "Adding failed login attempt to Redis", skip_all)]
+    async fn add_failed_attempt(&mut self, email: &Email) -> Result<u32, LoginLockoutStoreError> {
+        let key = get_failed_attempts_key(email);
+
+        // Every failure extends the window, so the count only resets after a quiet day
+        let (failed_attempts,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire(&key, FAILED_LOGIN_ATTEMPTS_TTL_SECONDS)
+            .ignore()
+            .query(&mut *self.conn.write().await)
+            .wrap_err("failed to add failed login attempt to Redis")
+            .map_err(LoginLockoutStoreError::UnexpectedError)?;
+
+        Ok(failed_attempts)
+    }
+
+    #[tracing::instrument(name = "Locking account in Redis", skip_all)]
+    async fn lock(
+        &mut self,
+        email: &Email,
+        locked_until: DateTime<Utc>,
+    ) -> Result<(), LoginLockoutStoreError> {
+        let ttl: u64 = (locked_until - Utc::now())
+            .num_seconds()
+            .max(1)
+            .try_into()
+            .wrap_err("failed to cast lockout duration to u64")
+            .map_err(LoginLockoutStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_lockout_key(email), locked_until.timestamp(), ttl)
+            .wrap_err("failed to set lockout in Redis")
+            .map_err(LoginLockoutStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting lockout from Redis", skip_all)]
+    async fn get_lockout(
+        &self,
+        email: &Email,
+    ) -> Result<Option<DateTime<Utc>>, LoginLockoutStoreError> {
+        let locked_until: Option<i64> = self
+            .conn
+            .write()
+            .await
+            .get(get_lockout_key(email))
+            .wrap_err("failed to get lockout from Redis")
+            .map_err(LoginLockoutStoreError::UnexpectedError)?;
+
+        locked_until
+            .map(|timestamp| {
+                DateTime::from_timestamp(timestamp, 0)
+                    .ok_or(eyre!("invalid lockout timestamp {}", timestamp))
+                    .map_err(LoginLockoutStoreError::UnexpectedError)
+            })
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "Resetting lockout in Redis", skip_all)]
+    async fn reset(&mut self, email: &Email) -> Result<(), LoginLockoutStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(&[get_failed_attempts_key(email), get_lockout_key(email)])
+            .wrap_err("failed to reset lockout in Redis")
+            .map_err(LoginLockoutStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+const FAILED_LOGIN_ATTEMPTS_KEY_PREFIX: &str = "failed_login_attempts:";
+const LOGIN_LOCKOUT_KEY_PREFIX: &str = "login_lockout:";
+
+fn get_failed_attempts_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        FAILED_LOGIN_ATTEMPTS_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+fn get_lockout_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        LOGIN_LOCKOUT_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
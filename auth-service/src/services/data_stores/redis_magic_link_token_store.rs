@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use tokio::sync::RwLock;
+
+use crate::domain::{Email, MagicLinkToken, MagicLinkTokenStore, MagicLinkTokenStoreError};
+
+pub struct RedisMagicLinkTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisMagicLinkTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl MagicLinkTokenStore for RedisMagicLinkTokenStore {
+    #[tracing::instrument(name = "Adding magic link token to Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: MagicLinkToken,
+    ) -> Result<(), MagicLinkTokenStoreError> {
+        let key = get_key(&token);
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(&key, email.as_ref().expose_secret(), TEN_MINUTES_IN_SECONDS)
+            .wrap_err("failed to set magic link token in Redis")
+            .map_err(MagicLinkTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Using magic link token in Redis", skip_all)]
+    async fn use_token(
+        &mut self,
+        token: &MagicLinkToken,
+    ) -> Result<Email, MagicLinkTokenStoreError> {
+        let key = get_key(token);
+
+        // GETDEL makes sure two concurrent requests can't both consume the token
+        let email: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(&key)
+            .wrap_err("failed to get magic link token from Redis")
+            .map_err(MagicLinkTokenStoreError::UnexpectedError)?;
+
+        match email {
+            Some(email) => {
+                Email::parse(Secret::new(email)).map_err(MagicLinkTokenStoreError::UnexpectedError)
+            }
+            None => Err(MagicLinkTokenStoreError::TokenNotFound),
+        }
+    }
+}
+
+const TEN_MINUTES_IN_SECONDS: u64 = 600;
+const MAGIC_LINK_TOKEN_KEY_PREFIX: &str = "magic_link_token:";
+
+fn get_key(token: &MagicLinkToken) -> String {
+    format!(
+        "{}{}",
+        MAGIC_LINK_TOKEN_KEY_PREFIX,
+        token.as_ref().expose_secret()
+    )
+}
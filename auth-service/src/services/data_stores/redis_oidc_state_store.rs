@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::domain::{OidcLogin, OidcState, OidcStateStore, OidcStateStoreError};
+
+pub struct RedisOidcStateStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisOidcStateStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl OidcStateStore for RedisOidcStateStore {
+    #[tracing::instrument(name = "Adding OIDC state to Redis", skip_all)]
+    async fn add_state(
+        &mut self,
+        state: OidcState,
+        login: OidcLogin,
+    ) -> Result<(), OidcStateStoreError> {
+        let key = get_key(&state);
+
+        let data = StoredLogin {
+            provider: login.provider,
+            nonce: login.nonce,
+            code_verifier: login.code_verifier,
+            return_to: login.return_to,
+        };
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("failed to serialize OIDC login")
+            .map_err(OidcStateStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(&key, serialized_data, TEN_MINUTES_IN_SECONDS)
+            .wrap_err("failed to set OIDC state in Redis")
+            .map_err(OidcStateStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Using OIDC state in Redis", skip_all)]
+    async fn use_state(&mut self, state: &OidcState) -> Result<OidcLogin, OidcStateStoreError> {
+        let key = get_key(state);
+
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(&key)
+            .wrap_err("failed to get OIDC state from Redis")
+            .map_err(OidcStateStoreError::UnexpectedError)?;
+
+        let Some(value) = value else {
+            return Err(OidcStateStoreError::StateNotFound);
+        };
+
+        let data: StoredLogin = serde_json::from_str(&value)
+            .wrap_err("failed to deserialize OIDC login")
+            .map_err(OidcStateStoreError::UnexpectedError)?;
+
+        Ok(OidcLogin {
+            provider: data.provider,
+            nonce: data.nonce,
+            code_verifier: data.code_verifier,
+            return_to: data.return_to,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredLogin {
+    provider: String,
+    nonce: String,
+    code_verifier: String,
+    return_to: Option<String>,
+}
+
+// Users have this long to sign in with the provider
+const TEN_MINUTES_IN_SECONDS: u64 = 600;
+const OIDC_STATE_KEY_PREFIX: &str = "oidc_state:";
+
+fn get_key(state: &OidcState) -> String {
+    format!(
+        "{}{}",
+        OIDC_STATE_KEY_PREFIX,
+        state.as_ref().expose_secret()
+    )
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    Email, PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError,
+};
+
+pub struct RedisPasswordResetTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPasswordResetTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for RedisPasswordResetTokenStore {
+    #[tracing::instrument(name = "Adding password reset token to Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let key = get_key(&token);
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(
+                &key,
+                email.as_ref().expose_secret(),
+                FIFTEEN_MINUTES_IN_SECONDS,
+            )
+            .wrap_err("failed to set password reset token in Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Using password reset token in Redis", skip_all)]
+    async fn use_token(
+        &mut self,
+        token: &PasswordResetToken,
+    ) -> Result<Email, PasswordResetTokenStoreError> {
+        let key = get_key(token);
+
+        // GETDEL makes sure two concurrent requests can't both consume the token
+        let email: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(&key)
+            .wrap_err("failed to get password reset token from Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        match email {
+            Some(email) => Email::parse(Secret::new(email))
+                .map_err(PasswordResetTokenStoreError::UnexpectedError),
+            None => Err(PasswordResetTokenStoreError::TokenNotFound),
+        }
+    }
+}
+
+const FIFTEEN_MINUTES_IN_SECONDS: u64 = 900;
+const PASSWORD_RESET_TOKEN_KEY_PREFIX: &str = "password_reset_token:";
+
+fn get_key(token: &PasswordResetToken) -> String {
+    format!(
+        "{}{}",
+        PASSWORD_RESET_TOKEN_KEY_PREFIX,
+        token.as_ref().expose_secret()
+    )
+}
@@ -0,0 +1,75 @@
+use std::{sync::Arc, time::Duration};
+
+use color_eyre::eyre::Context;
+use redis::{Connection, Script};
+use tokio::sync::RwLock;
+
+use crate::domain::{RateLimit, RateLimitStore, RateLimitStoreError};
+
+pub struct RedisRateLimitStore {
+    conn: Arc<RwLock<Connection>>,
+    script: Script,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self {
+            conn,
+            script: Script::new(TAKE_TOKEN_SCRIPT),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    #[tracing::instrument(name = "Taking rate limit token from Redis", skip_all)]
+    async fn take_token(&mut self, key: &str, limit: RateLimit) -> Result<(), RateLimitStoreError> {
+        let retry_after_ms: u64 = self
+            .script
+            .key(get_bucket_key(key))
+            .arg(limit.capacity)
+            .arg(limit.refill_interval.as_millis() as u64)
+            .invoke(&mut *self.conn.write().await)
+            .wrap_err("failed to take rate limit token from Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        if retry_after_ms > 0 {
+            let retry_after = Duration::from_millis(retry_after_ms);
+            return Err(RateLimitStoreError::LimitExceeded(retry_after));
+        }
+
+        Ok(())
+    }
+}
+
+// Refilling and taking a token has to happen in one step, otherwise replicas racing on the same
+// bucket could both take its last token. Returns 0 if a token was taken, or else the number of
+// milliseconds until one is available. Uses the Redis clock so replicas agree on the time.
+const TAKE_TOKEN_SCRIPT: &str = r"
+local capacity = tonumber(ARGV[1])
+local refill_interval = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or capacity
+local updated_at = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + (now - updated_at) / refill_interval)
+
+local retry_after = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+else
+    retry_after = math.ceil((1 - tokens) * refill_interval)
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity * refill_interval))
+return retry_after
+";
+
+const RATE_LIMIT_KEY_PREFIX: &str = "rate_limit:";
+
+fn get_bucket_key(key: &str) -> String {
+    format!("{}{}", RATE_LIMIT_KEY_PREFIX, key)
+}
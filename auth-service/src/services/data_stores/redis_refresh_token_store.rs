@@ -0,0 +1,266 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{Email, RefreshToken, RefreshTokenFamily, RefreshTokenStore, RefreshTokenStoreError},
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    #[tracing::instrument(name = "Adding refresh token to Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        family: RefreshTokenFamily,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let families_key = get_user_families_key(&family.email);
+        let tokens_key = get_user_tokens_key(&family.email);
+        let ttl = ttl()?;
+
+        // Keep an index of every family and token issued to the user so they can all be
+        // revoked or removed at once
+        let _: () = redis::pipe()
+            .sadd(&families_key, &family.id)
+            .ignore()
+            .expire(&families_key, ttl as i64)
+            .ignore()
+            .sadd(
+                &tokens_key,
+                &[get_token_key(&token), get_used_token_key(&token)],
+            )
+            .ignore()
+            .expire(&tokens_key, ttl as i64)
+            .ignore()
+            .query(&mut *self.conn.write().await)
+            .wrap_err("failed to index refresh token family in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let record = RefreshTokenRecord {
+            family_id: family.id,
+            email: family.email.as_ref().expose_secret().to_owned(),
+        };
+
+        self.set_record(&token, &record).await
+    }
+
+    #[tracing::instrument(name = "Using refresh token in Redis", skip_all)]
+    async fn use_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamily, RefreshTokenStoreError> {
+        let key = get_token_key(token);
+
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get(&key)
+            .wrap_err("failed to get refresh token from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let Some(value) = value else {
+            return Err(RefreshTokenStoreError::TokenNotFound);
+        };
+
+        let record: RefreshTokenRecord = serde_json::from_str(&value)
+            .wrap_err("failed to deserialize refresh token record")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let is_revoked: bool = self
+            .conn
+            .write()
+            .await
+            .exists(get_revoked_family_key(&record.family_id))
+            .wrap_err("failed to check if refresh token family is revoked in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        if is_revoked {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        let family = RefreshTokenFamily {
+            id: record.family_id,
+            email: Email::parse(Secret::new(record.email))
+                .map_err(RefreshTokenStoreError::UnexpectedError)?,
+        };
+
+        // The token is marked as used with a single SET NX, so when replicas race on the same
+        // token exactly one of them claims it and the others see it as reused
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(get_used_token_key(token))
+            .arg(true)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl()?)
+            .query(&mut *self.conn.write().await)
+            .wrap_err("failed to mark refresh token as used in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        if claimed.is_none() {
+            return Err(RefreshTokenStoreError::TokenReused(family));
+        }
+
+        Ok(family)
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token family in Redis", skip_all)]
+    async fn revoke_family(
+        &mut self,
+        family: &RefreshTokenFamily,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_revoked_family_key(&family.id), true, ttl()?)
+            .wrap_err("failed to revoke refresh token family in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking all refresh token families in Redis", skip_all)]
+    async fn revoke_all_families(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let family_ids: Vec<String> = conn
+            .smembers(get_user_families_key(email))
+            .wrap_err("failed to get refresh token families from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let ttl = ttl()?;
+        let mut pipe = redis::pipe();
+        for family_id in &family_ids {
+            pipe.set_ex(get_revoked_family_key(family_id), true, ttl)
+                .ignore();
+        }
+
+        let _: () = pipe
+            .query(&mut *conn)
+            .wrap_err("failed to revoke refresh token families in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing all refresh tokens from Redis", skip_all)]
+    async fn remove_all_tokens(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let mut conn = self.conn.write().await;
+        let families_key = get_user_families_key(email);
+        let tokens_key = get_user_tokens_key(email);
+
+        let family_ids: Vec<String> = conn
+            .smembers(&families_key)
+            .wrap_err("failed to get refresh token families from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let mut keys: Vec<String> = conn
+            .smembers(&tokens_key)
+            .wrap_err("failed to get refresh tokens from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        keys.extend(family_ids.iter().map(|id| get_revoked_family_key(id)));
+        keys.push(families_key);
+        keys.push(tokens_key);
+
+        let _: () = conn
+            .del(keys)
+            .wrap_err("failed to remove refresh tokens from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+impl RedisRefreshTokenStore {
+    async fn set_record(
+        &mut self,
+        token: &RefreshToken,
+        record: &RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let serialized_record = serde_json::to_string(record)
+            .wrap_err("failed to serialize refresh token record")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_token_key(token), serialized_record, ttl()?)
+            .wrap_err("failed to set refresh token in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+fn ttl() -> Result<u64, RefreshTokenStoreError> {
+    REFRESH_TOKEN_TTL_SECONDS
+        .try_into()
+        .wrap_err("failed to cast REFRESH_TOKEN_TTL_SECONDS to u64")
+        .map_err(RefreshTokenStoreError::UnexpectedError)
+}
+
+#[derive(Serialize, Deserialize)]
+struct RefreshTokenRecord {
+    family_id: String,
+    email: String,
+}
+
+const REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token:";
+const USED_TOKEN_KEY_PREFIX: &str = "used_refresh_token:";
+const REVOKED_FAMILY_KEY_PREFIX: &str = "revoked_refresh_token_family:";
+const USER_FAMILIES_KEY_PREFIX: &str = "refresh_token_families:";
+const USER_TOKENS_KEY_PREFIX: &str = "refresh_tokens:";
+
+fn get_token_key(token: &RefreshToken) -> String {
+    format!(
+        "{}{}",
+        REFRESH_TOKEN_KEY_PREFIX,
+        token.as_ref().expose_secret()
+    )
+}
+
+fn get_used_token_key(token: &RefreshToken) -> String {
+    format!(
+        "{}{}",
+        USED_TOKEN_KEY_PREFIX,
+        token.as_ref().expose_secret()
+    )
+}
+
+fn get_revoked_family_key(family_id: &str) -> String {
+    format!("{}{}", REVOKED_FAMILY_KEY_PREFIX, family_id)
+}
+
+fn get_user_families_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        USER_FAMILIES_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+fn get_user_tokens_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        USER_TOKENS_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
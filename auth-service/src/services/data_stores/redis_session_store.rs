@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{Email, Session, SessionStore, SessionStoreError},
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisSessionStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisSessionStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    #[tracing::instrument(name = "Adding session to Redis", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        let sessions_key = get_user_sessions_key(&session.email);
+        let ttl = ttl()?;
+
+        // Keep an index of the user's sessions so they can be listed and revoked at once
+        let _: () = redis::pipe()
+            .sadd(&sessions_key, &session.id)
+            .ignore()
+            .expire(&sessions_key, ttl as i64)
+            .ignore()
+            .query(&mut *self.conn.write().await)
+            .wrap_err("failed to index session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        self.set_session(&session).await
+    }
+
+    #[tracing::instrument(name = "Getting session from Redis", skip_all)]
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get(get_session_key(id))
+            .wrap_err("failed to get session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let Some(value) = value else {
+            return Err(SessionStoreError::SessionNotFound);
+        };
+
+        to_session(id, &value)
+    }
+
+    #[tracing::instrument(name = "Getting sessions from Redis", skip_all)]
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let mut conn = self.conn.write().await;
+        let sessions_key = get_user_sessions_key(email);
+
+        let ids: Vec<String> = conn
+            .smembers(&sessions_key)
+            .wrap_err("failed to get sessions from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut sessions = Vec::with_capacity(ids.len());
+        let mut expired_ids = Vec::new();
+
+        for id in ids {
+            let value: Option<String> = conn
+                .get(get_session_key(&id))
+                .wrap_err("failed to get session from Redis")
+                .map_err(SessionStoreError::UnexpectedError)?;
+
+            match value {
+                Some(value) => sessions.push(to_session(&id, &value)?),
+                None => expired_ids.push(id),
+            }
+        }
+
+        // Sessions expire on their own, so the index can point at ones that are gone
+        if !expired_ids.is_empty() {
+            let _: () = conn
+                .srem(&sessions_key, expired_ids)
+                .wrap_err("failed to remove expired sessions from Redis")
+                .map_err(SessionStoreError::UnexpectedError)?;
+        }
+
+        Ok(sessions)
+    }
+
+    #[tracing::instrument(name = "Touching session in Redis", skip_all)]
+    async fn touch_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        let mut session = self.get_session(id).await?;
+        session.last_seen = Utc::now();
+
+        let sessions_key = get_user_sessions_key(&session.email);
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .expire(&sessions_key, ttl()? as i64)
+            .wrap_err("failed to extend session index in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        self.set_session(&session).await
+    }
+
+    #[tracing::instrument(name = "Revoking session in Redis", skip_all)]
+    async fn revoke_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        let session = self.get_session(id).await?;
+
+        let _: () = redis::pipe()
+            .del(get_session_key(id))
+            .ignore()
+            .srem(get_user_sessions_key(&session.email), id)
+            .ignore()
+            .query(&mut *self.conn.write().await)
+            .wrap_err("failed to revoke session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking all sessions in Redis", skip_all)]
+    async fn revoke_all_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        let mut conn = self.conn.write().await;
+        let sessions_key = get_user_sessions_key(email);
+
+        let ids: Vec<String> = conn
+            .smembers(&sessions_key)
+            .wrap_err("failed to get sessions from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut keys: Vec<String> = ids.iter().map(|id| get_session_key(id)).collect();
+        keys.push(sessions_key);
+
+        let _: () = conn
+            .del(keys)
+            .wrap_err("failed to revoke sessions in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+impl RedisSessionStore {
+    async fn set_session(&mut self, session: &Session) -> Result<(), SessionStoreError> {
+        let data = StoredSession {
+            email: session.email.as_ref().expose_secret().to_owned(),
+            created_at: session.created_at.timestamp(),
+            last_seen: session.last_seen.timestamp(),
+            ip: session.ip.clone(),
+            user_agent: session.user_agent.clone(),
+        };
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("failed to serialize session")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        // A session lives as long as its refresh token, every refresh extends it
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_session_key(&session.id), serialized_data, ttl()?)
+            .wrap_err("failed to set session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+fn to_session(id: &str, value: &str) -> Result<Session, SessionStoreError> {
+    let data: StoredSession = serde_json::from_str(value)
+        .wrap_err("failed to deserialize session")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+    Ok(Session {
+        id: id.to_owned(),
+        email: Email::parse(Secret::new(data.email)).map_err(SessionStoreError::UnexpectedError)?,
+        created_at: to_date_time(data.created_at)?,
+        last_seen: to_date_time(data.last_seen)?,
+        ip: data.ip,
+        user_agent: data.user_agent,
+    })
+}
+
+fn to_date_time(timestamp: i64) -> Result<DateTime<Utc>, SessionStoreError> {
+    DateTime::from_timestamp(timestamp, 0)
+        .ok_or(eyre!("invalid session timestamp {}", timestamp))
+        .map_err(SessionStoreError::UnexpectedError)
+}
+
+fn ttl() -> Result<u64, SessionStoreError> {
+    REFRESH_TOKEN_TTL_SECONDS
+        .try_into()
+        .wrap_err("failed to cast REFRESH_TOKEN_TTL_SECONDS to u64")
+        .map_err(SessionStoreError::UnexpectedError)
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    email: String,
+    created_at: i64,
+    last_seen: i64,
+    ip: Option<String>,
+    user_agent: Option<String>,
+}
+
+const SESSION_KEY_PREFIX: &str = "session:";
+const USER_SESSIONS_KEY_PREFIX: &str = "sessions:";
+
+fn get_session_key(id: &str) -> String {
+    format!("{}{}", SESSION_KEY_PREFIX, id)
+}
+
+fn get_user_sessions_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        USER_SESSIONS_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
@@ -1,20 +1,30 @@
 use std::sync::Arc;
 
+use chrono::Utc;
 use color_eyre::eyre::Context;
 use redis::{Commands, Connection};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use crate::domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError};
+use crate::{
+    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    utils::constants::{MAX_PENDING_TWO_FA_LOGINS, TWO_FA_RESEND_COOLDOWN_SECONDS},
+};
 
 pub struct RedisTwoFACodeStore {
     conn: Arc<RwLock<Connection>>,
+    // Prepended to every key, so stores for different kinds of challenges can share a Redis
+    namespace: &'static str,
 }
 
 impl RedisTwoFACodeStore {
     pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+        Self::with_namespace(conn, "")
+    }
+
+    pub fn with_namespace(conn: Arc<RwLock<Connection>>, namespace: &'static str) -> Self {
+        Self { conn, namespace }
     }
 }
 
@@ -27,48 +37,92 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(&email);
+        let key = get_key(self.namespace, &login_attempt_id);
+        let pending_key = get_pending_key(self.namespace, &email);
 
         let data = TwoFATuple(
-            login_attempt_id.as_ref().expose_secret().to_owned(),
+            email.as_ref().expose_secret().to_owned(),
             code.as_ref().expose_secret().to_owned(),
         );
         let serialized_data = serde_json::to_string(&data)
             .wrap_err("failed to serialize 2FA tuple")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        let _: () = self
-            .conn
-            .write()
-            .await
+        let mut conn = self.conn.write().await;
+
+        // The user's pending login attempts are scored by when their code was issued, so the
+        // ones whose code has expired can be pruned and the oldest found. Milliseconds keep logins
+        // made within the same second apart.
+        let now = Utc::now().timestamp_millis();
+        let (pending,): (usize,) = redis::pipe()
+            .atomic()
             .set_ex(&key, serialized_data, TEN_MINUTES_IN_SECONDS)
+            .ignore()
+            .zadd(&pending_key, login_attempt_id.as_ref().expose_secret(), now)
+            .ignore()
+            .zrembyscore(&pending_key, "-inf", now - TEN_MINUTES_IN_SECONDS as i64 * 1000)
+            .ignore()
+            .expire(&pending_key, TEN_MINUTES_IN_SECONDS as i64)
+            .ignore()
+            .zcard(&pending_key)
+            .query(&mut *conn)
             .wrap_err("failed to set 2FA code in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        Ok(())
+        if pending <= MAX_PENDING_TWO_FA_LOGINS {
+            return Ok(());
+        }
+
+        let dropped: Vec<String> = conn
+            .zrange(
+                &pending_key,
+                0,
+                (pending - MAX_PENDING_TWO_FA_LOGINS - 1) as isize,
+            )
+            .wrap_err("failed to get pending 2FA logins from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        remove_pending(&mut conn, self.namespace, &pending_key, &dropped)
     }
 
     #[tracing::instrument(name = "Removing 2FA code from Redis", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(email);
-
-        let _: () = self
-            .conn
-            .write()
-            .await
-            .del(&key)
-            .wrap_err("failed to delete 2FA code from Redis")
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let email = match self.get_code(login_attempt_id).await {
+            Ok((email, _)) => email,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        remove_pending(
+            &mut *self.conn.write().await,
+            self.namespace,
+            &get_pending_key(self.namespace, &email),
+            &[login_attempt_id.as_ref().expose_secret().to_owned()],
+        )
+    }
+
+    #[tracing::instrument(name = "Removing all 2FA codes of a user from Redis", skip_all)]
+    async fn remove_all_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let pending_key = get_pending_key(self.namespace, email);
+        let mut conn = self.conn.write().await;
+
+        let login_attempt_ids: Vec<String> = conn
+            .zrange(&pending_key, 0, -1)
+            .wrap_err("failed to get pending 2FA logins from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        Ok(())
+        remove_pending(&mut conn, self.namespace, &pending_key, &login_attempt_ids)
     }
 
     #[tracing::instrument(name = "Getting 2FA code from Redis", skip_all)]
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        let key = get_key(email);
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode), TwoFACodeStoreError> {
+        let key = get_key(self.namespace, login_attempt_id);
 
         match self.conn.write().await.get::<_, String>(&key) {
             Ok(value) => {
@@ -76,25 +130,156 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
                     .wrap_err("failed to deserialize 2FA tuple")
                     .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-                let login_attempt_id =
-                    LoginAttemptId::parse(Secret::new(data.0)).map_err(TwoFACodeStoreError::UnexpectedError)?;
+                let email = Email::parse(Secret::new(data.0))
+                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-                let email_code =
-                    TwoFACode::parse(Secret::new(data.1)).map_err(TwoFACodeStoreError::UnexpectedError)?;
+                let email_code = TwoFACode::parse(Secret::new(data.1))
+                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-                Ok((login_attempt_id, email_code))
+                Ok((email, email_code))
             }
             Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    #[tracing::instrument(name = "Recording 2FA attempt in Redis", skip_all)]
+    async fn record_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let key = get_attempts_key(self.namespace, login_attempt_id);
+
+        // The count only has to outlive the code it belongs to
+        let (attempts,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire(&key, TEN_MINUTES_IN_SECONDS as i64)
+            .ignore()
+            .query(&mut *self.conn.write().await)
+            .wrap_err("failed to record 2FA attempt in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(attempts)
+    }
+
+    #[tracing::instrument(name = "Recording 2FA code resend in Redis", skip_all)]
+    async fn record_resend(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+
+        // SET NX only succeeds if the code wasn't resent within the cooldown
+        let cooldown_started: Option<String> = redis::cmd("SET")
+            .arg(get_resend_cooldown_key(self.namespace, login_attempt_id))
+            .arg(true)
+            .arg("NX")
+            .arg("EX")
+            .arg(TWO_FA_RESEND_COOLDOWN_SECONDS)
+            .query(&mut *conn)
+            .wrap_err("failed to set 2FA resend cooldown in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        if cooldown_started.is_none() {
+            return Err(TwoFACodeStoreError::ResendCooldownActive);
+        }
+
+        let key = get_resends_key(self.namespace, login_attempt_id);
+        let (resends,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire(&key, TEN_MINUTES_IN_SECONDS as i64)
+            .ignore()
+            .query(&mut *conn)
+            .wrap_err("failed to record 2FA code resend in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(resends)
+    }
+}
+
+// Deletes the codes of the given login attempts and drops them from the user's pending set
+fn remove_pending(
+    conn: &mut Connection,
+    namespace: &str,
+    pending_key: &str,
+    login_attempt_ids: &[String],
+) -> Result<(), TwoFACodeStoreError> {
+    if login_attempt_ids.is_empty() {
+        return Ok(());
+    }
+
+    let keys: Vec<String> = login_attempt_ids
+        .iter()
+        .map(|id| format!("{}{}{}", namespace, TWO_FA_CODE_PREFIX, id))
+        .collect();
+
+    redis::pipe()
+        .atomic()
+        .del(keys)
+        .ignore()
+        .zrem(pending_key, login_attempt_ids)
+        .ignore()
+        .query::<()>(conn)
+        .wrap_err("failed to delete 2FA codes from Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)
 }
 
+// The email and the code of a login attempt
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
+// Namespace of the store holding the challenges started to turn 2FA off
+pub const DISABLE_2FA_NAMESPACE: &str = "disable_2fa:";
+
 const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+const TWO_FA_PENDING_PREFIX: &str = "two_fa_pending:";
+const TWO_FA_ATTEMPTS_PREFIX: &str = "two_fa_attempts:";
+const TWO_FA_RESENDS_PREFIX: &str = "two_fa_resends:";
+const TWO_FA_RESEND_COOLDOWN_PREFIX: &str = "two_fa_resend_cooldown:";
+
+fn get_key(namespace: &str, login_attempt_id: &LoginAttemptId) -> String {
+    format!(
+        "{}{}{}",
+        namespace,
+        TWO_FA_CODE_PREFIX,
+        login_attempt_id.as_ref().expose_secret()
+    )
+}
+
+fn get_pending_key(namespace: &str, email: &Email) -> String {
+    format!(
+        "{}{}{}",
+        namespace,
+        TWO_FA_PENDING_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+fn get_attempts_key(namespace: &str, login_attempt_id: &LoginAttemptId) -> String {
+    format!(
+        "{}{}{}",
+        namespace,
+        TWO_FA_ATTEMPTS_PREFIX,
+        login_attempt_id.as_ref().expose_secret()
+    )
+}
+
+fn get_resends_key(namespace: &str, login_attempt_id: &LoginAttemptId) -> String {
+    format!(
+        "{}{}{}",
+        namespace,
+        TWO_FA_RESENDS_PREFIX,
+        login_attempt_id.as_ref().expose_secret()
+    )
+}
 
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+fn get_resend_cooldown_key(namespace: &str, login_attempt_id: &LoginAttemptId) -> String {
+    format!(
+        "{}{}{}",
+        namespace,
+        TWO_FA_RESEND_COOLDOWN_PREFIX,
+        login_attempt_id.as_ref().expose_secret()
+    )
 }
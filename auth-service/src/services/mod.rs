@@ -1,3 +1,6 @@
+pub mod account_purge;
 pub mod data_stores;
 pub mod mock_email_client;
+pub mod oidc_client;
 pub mod postmark_email_client;
+pub mod signing_key_reload;
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use color_eyre::eyre::{eyre, Context, Result};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::utils::pkce::{compute_code_challenge, PKCE_METHOD_S256};
+
+// Providers are configured as a JSON array in the OIDC_PROVIDERS environment variable, e.g.
+// `[{"name": "google", "issuer": "https://accounts.google.com", "clientId": "...",
+// "clientSecret": "..."}]`
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OidcProviderConfig {
+    // Used in the login URL, `/login/oidc/{name}`
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Secret<String>,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+}
+
+fn default_scopes() -> Vec<String> {
+    vec!["openid".to_owned(), "email".to_owned()]
+}
+
+// Claims this service needs from a provider's ID token
+#[derive(Debug, Deserialize)]
+pub struct ExternalIdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub nonce: Option<String>,
+}
+
+// Relying party side of OpenID Connect, used to sign users in with external providers
+pub struct OidcClient {
+    http_client: Client,
+    providers: HashMap<String, OidcProviderConfig>,
+    // Discovery documents rarely change, so they are only fetched once per provider
+    metadata: RwLock<HashMap<String, ProviderMetadata>>,
+}
+
+impl OidcClient {
+    pub fn new(providers: Vec<OidcProviderConfig>, http_client: Client) -> Self {
+        Self {
+            http_client,
+            providers: providers
+                .into_iter()
+                .map(|provider| (provider.name.clone(), provider))
+                .collect(),
+            metadata: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_provider(&self, name: &str) -> Option<&OidcProviderConfig> {
+        self.providers.get(name)
+    }
+
+    pub fn provider_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.providers.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    #[tracing::instrument(name = "Build OIDC authorization URL", skip_all)]
+    pub async fn authorization_url(
+        &self,
+        provider: &OidcProviderConfig,
+        redirect_uri: &str,
+        state: &str,
+        nonce: &str,
+        code_verifier: &str,
+    ) -> Result<Url> {
+        let metadata = self.get_metadata(provider).await?;
+
+        Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", provider.client_id.as_str()),
+                ("redirect_uri", redirect_uri),
+                ("scope", provider.scopes.join(" ").as_str()),
+                ("state", state),
+                ("nonce", nonce),
+                (
+                    "code_challenge",
+                    compute_code_challenge(code_verifier).as_str(),
+                ),
+                ("code_challenge_method", PKCE_METHOD_S256),
+            ],
+        )
+        .wrap_err("invalid authorization endpoint")
+    }
+
+    // Exchanges the authorization code and returns the claims of the validated ID token.
+    // Checking the nonce is left to the caller.
+    #[tracing::instrument(name = "Exchange OIDC authorization code", skip_all)]
+    pub async fn exchange_code(
+        &self,
+        provider: &OidcProviderConfig,
+        redirect_uri: &str,
+        code: &Secret<String>,
+        code_verifier: &str,
+    ) -> Result<ExternalIdTokenClaims> {
+        let metadata = self.get_metadata(provider).await?;
+
+        let response: TokenResponse = self
+            .http_client
+            .post(&metadata.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code.expose_secret()),
+                ("redirect_uri", redirect_uri),
+                ("client_id", &provider.client_id),
+                ("client_secret", provider.client_secret.expose_secret()),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await?
+            .error_for_status()
+            .wrap_err("token request was rejected")?
+            .json()
+            .await
+            .wrap_err("invalid token response")?;
+
+        // Keys are fetched for every login, so rotations at the provider are picked up
+        let jwks: JwkSet = self
+            .http_client
+            .get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .wrap_err("invalid JWKS")?;
+
+        validate_id_token(&response.id_token, &jwks, provider)
+    }
+
+    async fn get_metadata(&self, provider: &OidcProviderConfig) -> Result<ProviderMetadata> {
+        if let Some(metadata) = self.metadata.read().await.get(&provider.name) {
+            return Ok(metadata.clone());
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            provider.issuer.trim_end_matches('/')
+        );
+
+        let metadata: ProviderMetadata = self
+            .http_client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .wrap_err("invalid discovery document")?;
+
+        if metadata.issuer != provider.issuer {
+            return Err(eyre!(
+                "discovery document is for issuer {}, expected {}",
+                metadata.issuer,
+                provider.issuer
+            ));
+        }
+
+        self.metadata
+            .write()
+            .await
+            .insert(provider.name.clone(), metadata.clone());
+
+        Ok(metadata)
+    }
+}
+
+fn validate_id_token(
+    id_token: &str,
+    jwks: &JwkSet,
+    provider: &OidcProviderConfig,
+) -> Result<ExternalIdTokenClaims> {
+    let header = decode_header(id_token).wrap_err("invalid ID token header")?;
+
+    // Symmetric algorithms would let anyone who knows the client secret forge tokens
+    if !ASYMMETRIC_ALGORITHMS.contains(&header.alg) {
+        return Err(eyre!(
+            "ID token uses unsupported algorithm {:?}",
+            header.alg
+        ));
+    }
+
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }
+    .ok_or(eyre!("ID token was signed with an unknown key"))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&provider.issuer]);
+    validation.set_audience(&[&provider.client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+    decode::<ExternalIdTokenClaims>(
+        id_token,
+        &DecodingKey::from_jwk(jwk).wrap_err("invalid JWK")?,
+        &validation,
+    )
+    .map(|data| data.claims)
+    .wrap_err("invalid ID token")
+}
+
+const ASYMMETRIC_ALGORITHMS: [Algorithm; 9] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+#[derive(Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::signing_key::{SigningKey, SIGNING_ALGORITHM};
+    use jsonwebtoken::{encode, Header};
+    use serde_json::json;
+
+    // PKCS#8 encodings of the all-zero and all-one Ed25519 seeds
+    const TEST_KEY: &str = "MC4CAQAwBQYDK2VwBCIEIAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+    const OTHER_TEST_KEY: &str = "MC4CAQAwBQYDK2VwBCIEIAEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEB";
+
+    fn provider() -> OidcProviderConfig {
+        OidcProviderConfig {
+            name: "test".to_owned(),
+            issuer: "https://idp.example.com".to_owned(),
+            client_id: "auth-service".to_owned(),
+            client_secret: Secret::new("secret".to_owned()),
+            scopes: default_scopes(),
+        }
+    }
+
+    fn sign(key: &SigningKey, claims: serde_json::Value) -> String {
+        let header = Header {
+            kid: Some(key.kid.clone()),
+            ..Header::new(SIGNING_ALGORITHM)
+        };
+        encode(&header, &claims, &key.encoding_key).unwrap()
+    }
+
+    fn claims() -> serde_json::Value {
+        json!({
+            "iss": "https://idp.example.com",
+            "aud": "auth-service",
+            "sub": "12345",
+            "exp": 4_102_444_800u64,
+            "email": "test@example.com",
+            "email_verified": true,
+            "nonce": "abc"
+        })
+    }
+
+    #[test]
+    fn test_valid_id_token_is_accepted() {
+        let key = SigningKey::from_pkcs8(TEST_KEY).unwrap();
+        let jwks = JwkSet {
+            keys: vec![key.jwk.clone()],
+        };
+
+        let claims = validate_id_token(&sign(&key, claims()), &jwks, &provider()).unwrap();
+        assert_eq!(claims.sub, "12345");
+        assert_eq!(claims.email.as_deref(), Some("test@example.com"));
+        assert_eq!(claims.email_verified, Some(true));
+        assert_eq!(claims.nonce.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_id_token_from_unknown_key_is_rejected() {
+        let key = SigningKey::from_pkcs8(TEST_KEY).unwrap();
+        let other_key = SigningKey::from_pkcs8(OTHER_TEST_KEY).unwrap();
+        let jwks = JwkSet {
+            keys: vec![key.jwk.clone()],
+        };
+
+        assert!(validate_id_token(&sign(&other_key, claims()), &jwks, &provider()).is_err());
+    }
+
+    #[test]
+    fn test_id_token_for_other_audience_or_issuer_is_rejected() {
+        let key = SigningKey::from_pkcs8(TEST_KEY).unwrap();
+        let jwks = JwkSet {
+            keys: vec![key.jwk.clone()],
+        };
+
+        let mut other_audience = claims();
+        other_audience["aud"] = json!("other-client");
+        assert!(validate_id_token(&sign(&key, other_audience), &jwks, &provider()).is_err());
+
+        let mut other_issuer = claims();
+        other_issuer["iss"] = json!("https://evil.example.com");
+        assert!(validate_id_token(&sign(&key, other_issuer), &jwks, &provider()).is_err());
+    }
+
+    #[test]
+    fn test_symmetric_id_token_is_rejected() {
+        let key = SigningKey::from_pkcs8(TEST_KEY).unwrap();
+        let jwks = JwkSet {
+            keys: vec![key.jwk.clone()],
+        };
+
+        let token = encode(
+            &Header::default(),
+            &claims(),
+            &jsonwebtoken::EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+
+        assert!(validate_id_token(&token, &jwks, &provider()).is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::utils::signing_key::KEY_RING;
+
+// Periodically applies changes to the signing keys directory, so keys can be rotated without
+// restarting the service
+pub async fn run_signing_key_reload(dir: PathBuf, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+
+    loop {
+        interval.tick().await;
+
+        let result = match KEY_RING.write() {
+            Ok(mut key_ring) => key_ring.reload_from_dir(&dir),
+            Err(_) => {
+                tracing::error!("signing key ring lock is poisoned");
+                return;
+            }
+        };
+
+        if let Err(e) = result {
+            tracing::error!("failed to reload signing keys: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{
+        authenticated_user::AuthenticatedUser,
+        constants::{roles, ADMIN_API_KEY},
+    },
+};
+
+// Header that carries the static admin key, see `constants::ADMIN_API_KEY`
+pub const ADMIN_KEY_HEADER_NAME: &str = "x-admin-key";
+
+// Who a request to the admin API was made by. Rejects requests with a wrong admin key, and
+// requests from users without the admin role.
+#[derive(Debug)]
+pub enum Admin {
+    // Holder of the static admin key, which grants every permission
+    ApiKey,
+    User(AuthenticatedUser),
+}
+
+impl Admin {
+    pub fn require(&self, permission: &str) -> Result<(), AuthAPIError> {
+        match self {
+            Self::ApiKey => Ok(()),
+            Self::User(user) if user.claims.permissions.iter().any(|p| p == permission) => Ok(()),
+            Self::User(_) => Err(AuthAPIError::InsufficientPermissions),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Admin {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(key) = parts.headers.get(ADMIN_KEY_HEADER_NAME) {
+            return match is_admin_key(ADMIN_API_KEY.as_ref(), key.as_bytes()) {
+                true => Ok(Self::ApiKey),
+                false => Err(AuthAPIError::InvalidToken),
+            };
+        }
+
+        let user = AuthenticatedUser::from_request_parts(parts, state).await?;
+        user.require_full_access()?;
+        if !user.claims.roles.iter().any(|role| role == roles::ADMIN) {
+            return Err(AuthAPIError::InsufficientPermissions);
+        }
+
+        Ok(Self::User(user))
+    }
+}
+
+// Digests are compared so the time taken doesn't tell how much of the key was right
+fn is_admin_key(admin_key: Option<&Secret<String>>, candidate: &[u8]) -> bool {
+    admin_key.is_some_and(|admin_key| {
+        Sha256::digest(admin_key.expose_secret().as_bytes()) == Sha256::digest(candidate)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_admin_key() {
+        let admin_key = Secret::new("a".repeat(32));
+
+        assert!(is_admin_key(Some(&admin_key), "a".repeat(32).as_bytes()));
+        assert!(!is_admin_key(Some(&admin_key), "a".repeat(31).as_bytes()));
+        assert!(!is_admin_key(Some(&admin_key), b""));
+        // Without a configured key, no key is accepted
+        assert!(!is_admin_key(None, b""));
+    }
+}
@@ -1,38 +1,165 @@
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::cookie::Cookie;
 use chrono::Utc;
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, Email};
+use crate::domain::{
+    BannedTokenStore, Email, RefreshToken, RefreshTokenFamily, RefreshTokenStore, Session,
+    SessionStore, SessionStoreError, User, UserStore,
+};
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::{
+    client_info::ClientInfo,
+    constants::{AUTH_COOKIE_POLICY, AUTH_SERVICE_URL},
+    signing_key::{read_key_ring, SIGNING_ALGORITHM},
+};
 
 type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
+type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
+
+// Starts a new session for the user and returns its auth and refresh cookies
+#[tracing::instrument(name = "Start session", skip_all)]
+pub async fn start_session(
+    user: &User,
+    client: ClientInfo,
+    session_store: SessionStoreType,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<(Cookie<'static>, Cookie<'static>)> {
+    let session = add_session(user, client, session_store).await?;
+
+    let auth_cookie = generate_auth_cookie(user, &session.id)?;
+    let refresh_cookie =
+        generate_refresh_cookie(session.refresh_token_family(), refresh_token_store).await?;
+
+    Ok((auth_cookie, refresh_cookie))
+}
+
+// Starts a new session for a client that sends its auth token in the `Authorization` header
+// instead of a cookie and returns the token. No refresh token is issued, the client logs in
+// again once the token expires.
+#[tracing::instrument(name = "Start bearer session", skip_all)]
+pub async fn start_bearer_session(
+    user: &User,
+    client: ClientInfo,
+    session_store: SessionStoreType,
+) -> Result<String> {
+    let session = add_session(user, client, session_store).await?;
+    generate_auth_token(user, Some(session.id))
+}
+
+async fn add_session(
+    user: &User,
+    client: ClientInfo,
+    session_store: SessionStoreType,
+) -> Result<Session> {
+    let session = Session::new(user.email.clone(), client.ip, client.user_agent);
+
+    session_store
+        .write()
+        .await
+        .add_session(session.clone())
+        .await
+        .wrap_err("failed to store session")?;
+
+    Ok(session)
+}
 
 #[tracing::instrument(name = "Generate auth cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(user: &User, session_id: &str) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(user, Some(session_id.to_owned()))?;
     Ok(create_auth_cookie(token))
 }
 
 fn create_auth_cookie(token: String) -> Cookie<'static> {
-    let cookie = Cookie::build((JWT_COOKIE_NAME, token))
-        .path("/")
-        .http_only(true)
-        .same_site(SameSite::Lax)
-        .build();
+    AUTH_COOKIE_POLICY.auth_cookie(token)
+}
+
+// Issues a new refresh token in the given family and returns it wrapped in a cookie
+#[tracing::instrument(name = "Generate refresh cookie", skip_all)]
+pub async fn generate_refresh_cookie(
+    family: RefreshTokenFamily,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<Cookie<'static>> {
+    let token = RefreshToken::default();
+
+    refresh_token_store
+        .write()
+        .await
+        .add_token(token.clone(), family)
+        .await
+        .wrap_err("failed to store refresh token")?;
+
+    Ok(create_refresh_cookie(&token))
+}
 
-    cookie
+fn create_refresh_cookie(token: &RefreshToken) -> Cookie<'static> {
+    AUTH_COOKIE_POLICY.refresh_cookie(token.as_ref().expose_secret().to_owned())
 }
 
 pub const TOKEN_TTL_SECONDS: i64 = 600;
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24 * 14; // 14 days
 
 #[tracing::instrument(name = "Generate auth token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
+pub fn generate_auth_token(user: &User, sid: Option<String>) -> Result<String> {
+    create_token(&user_claims(user, sid, None)?)
+}
+
+// Tokens handed to OAuth clients don't belong to a browser session and have no session ID.
+// They carry the granted scope, which keeps them away from the account management routes.
+#[tracing::instrument(name = "Generate OAuth access token", skip_all)]
+pub fn generate_oauth_access_token(user: &User, scope: String) -> Result<String> {
+    create_token(&user_claims(user, None, Some(scope))?)
+}
+
+fn user_claims(user: &User, sid: Option<String>, scope: Option<String>) -> Result<Claims> {
+    let exp = get_expiry_time()?;
+
+    let sub = user.email.as_ref().expose_secret().to_owned();
+
+    Ok(Claims {
+        sub,
+        exp,
+        sid,
+        scope,
+        generation: user.token_generation,
+        roles: user.roles.clone(),
+        permissions: user.permissions.clone(),
+    })
+}
+
+// ID tokens tell an OpenID Connect client who signed in. They are not accepted as access tokens,
+// `validate_token` rejects them because of the audience.
+#[tracing::instrument(name = "Generate ID token", skip_all)]
+pub fn generate_id_token(user: &User, client_id: &str, nonce: Option<String>) -> Result<String> {
+    let exp = get_expiry_time()?;
+    let iat = Utc::now()
+        .timestamp()
+        .try_into()
+        .wrap_err("failed to cast iat time to usize")?;
+
+    let email = user.email.as_ref().expose_secret().to_owned();
+
+    let claims = IdTokenClaims {
+        iss: AUTH_SERVICE_URL.to_owned(),
+        sub: email.clone(),
+        aud: client_id.to_owned(),
+        exp,
+        iat,
+        nonce,
+        email,
+        email_verified: user.email_verified,
+    };
+
+    create_token(&claims)
+}
+
+fn get_expiry_time() -> Result<usize> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .wrap_err("failed to create 10 minute time delta")?;
 
@@ -41,22 +168,18 @@ fn generate_auth_token(email: &Email) -> Result<String> {
         .ok_or(eyre!("failed to add 10 minutes to current time"))?
         .timestamp();
 
-    let exp: usize = exp.try_into().wrap_err(format!(
+    exp.try_into().wrap_err(format!(
         "failed to cast exp time to usize. exp time: {}",
         exp
-    ))?;
-
-    let sub = email.as_ref().expose_secret().to_owned();
-
-    let claims = Claims { sub, exp };
-
-    create_token(&claims)
+    ))
 }
 
 #[tracing::instrument(name = "Validate token", skip_all)]
 pub async fn validate_token(
     token: &str,
     banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
+    user_store: UserStoreType,
 ) -> Result<Claims> {
     match banned_token_store.read().await.contains_token(&Secret::new(token.to_string())).await {
         Ok(value) => {
@@ -67,40 +190,139 @@ pub async fn validate_token(
         Err(e) => return Err(e.into()),
     }
 
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+    // Tokens name the key that signed them, which may no longer be the active one
+    let kid = decode_header(token)
+        .wrap_err("failed to decode token header")?
+        .kid
+        .ok_or(eyre!("token has no key ID"))?;
+
+    // The key ring guard must not be held across the session lookup below
+    let claims = {
+        let key_ring = read_key_ring()?;
+        let key = key_ring
+            .get_key(&kid)
+            .ok_or(eyre!("token was signed with unknown key {}", kid))?;
+
+        decode::<Claims>(
+            token,
+            &key.decoding_key,
+            &Validation::new(SIGNING_ALGORITHM),
+        )
+        .map(|data| data.claims)
+        .wrap_err("failed to decode token")?
+    };
+
+    // Signing the user out of a session takes effect before its tokens expire
+    if let Some(sid) = &claims.sid {
+        match session_store.read().await.get_session(sid).await {
+            Ok(_) => {}
+            Err(SessionStoreError::SessionNotFound) => return Err(eyre!("session was revoked")),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    // Logging out everywhere and changing the password move the user to a new generation
+    let email = Email::parse(Secret::new(claims.sub.clone()))?;
+    let user = user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .wrap_err("failed to get token owner")?;
+
+    if claims.generation < user.token_generation {
+        return Err(eyre!("token generation is stale"));
+    }
+
+    if user.disabled {
+        return Err(eyre!("token owner is disabled"));
+    }
+
+    if user.deleted_at.is_some() {
+        return Err(eyre!("token owner is deleted"));
+    }
+
+    Ok(claims)
 }
 
 #[tracing::instrument(name = "Create token", skip_all)]
-fn create_token(claims: &Claims) -> Result<String> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-    )
-    .wrap_err("failed to create token")
+fn create_token<T: Serialize>(claims: &T) -> Result<String> {
+    let key_ring = read_key_ring()?;
+    let key = key_ring.active_key();
+
+    let header = Header {
+        kid: Some(key.kid.clone()),
+        ..Header::new(SIGNING_ALGORITHM)
+    };
+
+    encode(&header, &claims, &key.encoding_key).wrap_err("failed to create token")
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    // ID of the session the token was issued to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    // Scope granted to the OAuth client the token was issued to, only the user's own session
+    // tokens (no scope) may manage the account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // Token generation of the user when the token was issued
+    #[serde(rename = "gen", default)]
+    pub generation: i64,
+    // Roles and permissions of the user when the token was issued
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<String>,
+}
+
+// Standard OpenID Connect claims, see OpenID Connect Core section 2
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    pub email: String,
+    pub email_verified: bool,
 }
 
 #[cfg(test)]
 mod tests {
+    use axum_extra::extract::cookie::SameSite;
+
     use super::*;
-    use crate::services::data_stores::hashset_banned_token_store::HashsetBannedTokenStore;
+    use crate::{
+        domain::Password,
+        services::data_stores::{
+            hashmap_refresh_token_store::HashmapRefreshTokenStore,
+            hashmap_session_store::HashmapSessionStore, hashmap_user_store::HashmapUserStore,
+            hashset_banned_token_store::HashsetBannedTokenStore,
+        },
+        utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    };
+
+    fn test_user() -> User {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password123".to_owned())).unwrap();
+        User::new(email, password, false)
+    }
+
+    async fn user_store_with(user: &User) -> UserStoreType {
+        let mut user_store = HashmapUserStore::default();
+        user_store.add_user(user.clone()).await.unwrap();
+        Arc::new(RwLock::new(user_store))
+    }
 
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(&test_user(), "session-id").unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -120,18 +342,49 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_generate_auth_token() {
+    async fn test_create_refresh_cookie() {
+        let token = RefreshToken::default();
+        let cookie = create_refresh_cookie(&token);
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.value(), token.as_ref().expose_secret());
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    }
+
+    #[tokio::test]
+    async fn test_generate_refresh_cookie() {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let family = RefreshTokenFamily::new(email);
+        let refresh_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+
+        let cookie = generate_refresh_cookie(family.clone(), refresh_store.clone())
+            .await
+            .unwrap();
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+
+        // The issued token must be usable exactly once
+        let token = RefreshToken::parse(Secret::new(cookie.value().to_owned())).unwrap();
+        let result = refresh_store.write().await.use_token(&token).await.unwrap();
+        assert_eq!(result, family);
+    }
+
+    #[tokio::test]
+    async fn test_generate_auth_token() {
+        let result = generate_auth_token(&test_user(), None).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let user = test_user();
+        let token = generate_auth_token(&user, None).unwrap();
         let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_store).await.unwrap();
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = user_store_with(&user).await;
+        let result = validate_token(&token, banned_store, session_store, user_store)
+            .await
+            .unwrap();
         assert_eq!(result.sub, "test@example.com");
 
         let exp = Utc::now()
@@ -140,29 +393,181 @@ mod tests {
             .timestamp();
 
         assert!(result.exp > exp as usize);
+        assert_eq!(result.scope, None);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_oauth_access_token() {
+        let user = test_user();
+        let token = generate_oauth_access_token(&user, "openid".to_owned()).unwrap();
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = user_store_with(&user).await;
+        let result = validate_token(&token, banned_store, session_store, user_store)
+            .await
+            .unwrap();
+        assert_eq!(result.sid, None);
+        assert_eq!(result.scope.as_deref(), Some("openid"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_id_token() {
+        let user = test_user();
+        let token = generate_id_token(&user, "mobile-app", None).unwrap();
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = user_store_with(&user).await;
+        let result = validate_token(&token, banned_store, session_store, user_store).await;
+        assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
         let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_store).await;
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = user_store_with(&test_user()).await;
+        let result = validate_token(&token, banned_store, session_store, user_store).await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
-        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let user = test_user();
+        let token = generate_auth_token(&user, None).unwrap();
         let mut banned_store_impl = HashsetBannedTokenStore::default();
 
         // Add token to banned store
-        banned_store_impl.add_token(Secret::new(token.clone())).await.unwrap();
+        banned_store_impl
+            .add_token(Secret::new(token.clone()))
+            .await
+            .unwrap();
 
         let banned_store = Arc::new(RwLock::new(banned_store_impl));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = user_store_with(&user).await;
 
         // Validation should fail for banned token
-        let result = validate_token(&token, banned_store).await;
+        let result = validate_token(&token, banned_store, session_store, user_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_start_session() {
+        let user = test_user();
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let refresh_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let user_store = user_store_with(&user).await;
+        let client = ClientInfo {
+            ip: Some("127.0.0.1".to_owned()),
+            user_agent: Some("Firefox".to_owned()),
+        };
+
+        let (auth_cookie, refresh_cookie) =
+            start_session(&user, client, session_store.clone(), refresh_store.clone())
+                .await
+                .unwrap();
+
+        let claims = validate_token(
+            auth_cookie.value(),
+            banned_store,
+            session_store.clone(),
+            user_store,
+        )
+        .await
+        .unwrap();
+        let sid = claims.sid.unwrap();
+
+        let session = session_store.read().await.get_session(&sid).await.unwrap();
+        assert_eq!(session.email, user.email);
+        assert_eq!(session.ip.as_deref(), Some("127.0.0.1"));
+        assert_eq!(session.user_agent.as_deref(), Some("Firefox"));
+
+        // The refresh token continues the same session
+        let token = RefreshToken::parse(Secret::new(refresh_cookie.value().to_owned())).unwrap();
+        let family = refresh_store.write().await.use_token(&token).await.unwrap();
+        assert_eq!(family.id, sid);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_revoked_session() {
+        let user = test_user();
+        let session = Session::new(user.email.clone(), None, None);
+        let mut session_store_impl = HashmapSessionStore::default();
+        session_store_impl
+            .add_session(session.clone())
+            .await
+            .unwrap();
+
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session_store = Arc::new(RwLock::new(session_store_impl));
+        let user_store = user_store_with(&user).await;
+        let token = generate_auth_token(&user, Some(session.id.clone())).unwrap();
+
+        let result = validate_token(
+            &token,
+            banned_store.clone(),
+            session_store.clone(),
+            user_store.clone(),
+        )
+        .await;
+        assert_eq!(result.unwrap().sid, Some(session.id.clone()));
+
+        session_store
+            .write()
+            .await
+            .revoke_session(&session.id)
+            .await
+            .unwrap();
+
+        let result = validate_token(&token, banned_store, session_store, user_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_stale_generation() {
+        let user = test_user();
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = user_store_with(&user).await;
+        let token = generate_auth_token(&user, None).unwrap();
+
+        let generation = user_store
+            .write()
+            .await
+            .increment_token_generation(&user.email)
+            .await
+            .unwrap();
+
+        let result = validate_token(
+            &token,
+            banned_store.clone(),
+            session_store.clone(),
+            user_store.clone(),
+        )
+        .await;
+        assert!(result.is_err());
+
+        // Tokens issued after the increment are accepted
+        let user = User {
+            token_generation: generation,
+            ..user
+        };
+        let token = generate_auth_token(&user, None).unwrap();
+        let result = validate_token(&token, banned_store, session_store, user_store)
+            .await
+            .unwrap();
+        assert_eq!(result.generation, generation);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_of_unknown_user() {
+        let token = generate_auth_token(&test_user(), None).unwrap();
+        let banned_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+        let user_store = Arc::new(RwLock::new(HashmapUserStore::default()));
+        let result = validate_token(&token, banned_store, session_store, user_store).await;
         assert!(result.is_err());
     }
 }
@@ -0,0 +1,113 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email},
+    utils::{
+        auth::{validate_token, Claims},
+        constants::AUTH_COOKIE_POLICY,
+    },
+};
+
+// Where the auth token of a request was found
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenSource {
+    BearerHeader,
+    Cookie,
+}
+
+// The raw auth token of a request, taken from the `Authorization: Bearer` header or else the auth
+// cookie. Nothing is validated, routes that need a signed-in user use `AuthenticatedUser`.
+#[derive(Clone, Debug)]
+pub struct AuthToken {
+    pub value: String,
+    pub source: TokenSource,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthToken
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let bearer_token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|token| !token.is_empty());
+
+        if let Some(token) = bearer_token {
+            return Ok(Self {
+                value: token.to_owned(),
+                source: TokenSource::BearerHeader,
+            });
+        }
+
+        CookieJar::from_headers(&parts.headers)
+            .get(AUTH_COOKIE_POLICY.auth_cookie_name())
+            .map(|cookie| Self {
+                value: cookie.value().to_owned(),
+                source: TokenSource::Cookie,
+            })
+            .ok_or(AuthAPIError::MissingToken)
+    }
+}
+
+// The signed-in user a request was made by. Rejects the request unless its auth token passes
+// `validate_token`.
+#[derive(Debug)]
+pub struct AuthenticatedUser {
+    pub email: Email,
+    pub claims: Claims,
+    pub token: AuthToken,
+}
+
+impl AuthenticatedUser {
+    // Tokens issued to OAuth clients carry a scope and may not manage the user's account
+    pub fn require_full_access(&self) -> Result<(), AuthAPIError> {
+        match self.claims.scope {
+            Some(_) => Err(AuthAPIError::InsufficientScope),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = AuthToken::from_request_parts(parts, state).await?;
+
+        let claims = validate_token(
+            &token.value,
+            state.banned_token_store.clone(),
+            state.session_store.clone(),
+            state.user_store.clone(),
+        )
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        let email = Email::parse(Secret::new(claims.sub.clone()))
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        Ok(Self {
+            email,
+            claims,
+            token,
+        })
+    }
+}
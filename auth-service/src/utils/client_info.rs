@@ -0,0 +1,88 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts},
+};
+
+use crate::utils::constants::CLIENT_IP_HEADER;
+
+// Where a request came from, recorded on the session it starts so users can recognise their
+// devices and used to rate limit requests by IP. The IP is the one of the connection, unless
+// `CLIENT_IP_HEADER` names a header set by the reverse proxy. The user agent is sent by the
+// client and is informational only.
+#[derive(Clone, Debug, Default)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let forwarded_ip = CLIENT_IP_HEADER
+            .as_ref()
+            .and_then(|name| parts.headers.get(name))
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_forwarded_ip);
+
+        let ip = forwarded_ip.or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| address.ip().to_string())
+        });
+
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+        Ok(Self { ip, user_agent })
+    }
+}
+
+const MAX_USER_AGENT_LENGTH: usize = 256;
+
+// Proxies append the address they were connected from to X-Forwarded-For, so the last entry is
+// the only one that wasn't sent by the client
+fn parse_forwarded_ip(value: &str) -> Option<String> {
+    value
+        .rsplit(',')
+        .next()
+        .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+        .map(|ip| ip.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forwarded_ip() {
+        assert_eq!(
+            parse_forwarded_ip("203.0.113.7"),
+            Some("203.0.113.7".to_owned())
+        );
+        assert_eq!(
+            parse_forwarded_ip("10.0.0.1, 203.0.113.7"),
+            Some("203.0.113.7".to_owned())
+        );
+        assert_eq!(
+            parse_forwarded_ip(" 2001:db8::1 "),
+            Some("2001:db8::1".to_owned())
+        );
+        assert_eq!(parse_forwarded_ip("203.0.113.7, unknown"), None);
+        assert_eq!(parse_forwarded_ip(""), None);
+    }
+}
@@ -1,23 +1,50 @@
+use axum::http::HeaderName;
+use axum_extra::extract::cookie::SameSite;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Duration;
 use dotenvy::dotenv;
 use lazy_static::lazy_static;
 use secrecy::Secret;
-use std::env as std_env;
+use std::{env as std_env, path::PathBuf};
+
+use super::cookie_policy::{parse_same_site, CookiePolicy};
 
 // Define a lazily evaluated static. lazy_static is needed because std_env::var is not a const function.
 lazy_static! {
-    pub static ref JWT_SECRET: Secret<String> = set_token();
+    pub static ref JWT_SIGNING_KEY: Option<Secret<String>> = set_jwt_signing_key();
+    pub static ref JWT_SIGNING_KEYS_DIR: Option<PathBuf> = set_jwt_signing_keys_dir();
     pub static ref DATABASE_URL: Secret<String> = set_database_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
     pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();
+    pub static ref TOTP_ENCRYPTION_KEY: Secret<String> = set_totp_encryption_key();
+    pub static ref ACCOUNT_DELETION_GRACE_PERIOD: Duration = set_account_deletion_grace_period();
+    pub static ref AUTH_SERVICE_URL: String = set_auth_service_url();
+    pub static ref OIDC_PROVIDERS: Secret<String> = set_oidc_providers();
+    pub static ref LOGIN_LOCKOUT_THRESHOLD: u32 = set_login_lockout_threshold();
+    pub static ref AUTH_COOKIE_POLICY: CookiePolicy = set_auth_cookie_policy();
+    pub static ref ADMIN_API_KEY: Option<Secret<String>> = set_admin_api_key();
+    pub static ref CLIENT_IP_HEADER: Option<HeaderName> = set_client_ip_header();
 }
 
-fn set_token() -> Secret<String> {
+// Only used when JWT_SIGNING_KEYS_DIR is not set
+fn set_jwt_signing_key() -> Option<Secret<String>> {
     dotenv().ok(); // Load environment variables
-    let secret = std_env::var(env::JWT_SECRET_ENV_VAR).expect("JWT_SECRET must be set.");
-    if secret.is_empty() {
-        panic!("JWT_SECRET must not be empty.");
+    let key = std_env::var(env::JWT_SIGNING_KEY_ENV_VAR)
+        .ok()
+        .filter(|key| !key.is_empty())?;
+    if STANDARD.decode(&key).is_err() {
+        panic!("JWT_SIGNING_KEY must be a base64 encoded PKCS#8 Ed25519 private key.");
     }
-    Secret::new(secret)
+    Some(Secret::new(key))
+}
+
+// Directory of rotatable signing keys, see `utils::signing_key::KeyRing::load_from_dir`
+fn set_jwt_signing_keys_dir() -> Option<PathBuf> {
+    dotenv().ok();
+    std_env::var(env::JWT_SIGNING_KEYS_DIR_ENV_VAR)
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
 }
 
 fn set_database_url() -> Secret<String> {
@@ -41,18 +68,263 @@ fn set_postmark_auth_token() -> Secret<String> {
     )
 }
 
+fn set_totp_encryption_key() -> Secret<String> {
+    dotenv().ok();
+    let key =
+        std_env::var(env::TOTP_ENCRYPTION_KEY_ENV_VAR).expect("TOTP_ENCRYPTION_KEY must be set.");
+    let is_valid = STANDARD
+        .decode(&key)
+        .is_ok_and(|decoded| decoded.len() == 32);
+    if !is_valid {
+        panic!("TOTP_ENCRYPTION_KEY must be a base64 encoded 32 byte key.");
+    }
+    Secret::new(key)
+}
+
+fn set_account_deletion_grace_period() -> Duration {
+    dotenv().ok();
+    let days = match std_env::var(env::ACCOUNT_DELETION_GRACE_PERIOD_DAYS_ENV_VAR) {
+        Ok(days) => days
+            .parse()
+            .expect("ACCOUNT_DELETION_GRACE_PERIOD_DAYS must be a number of days."),
+        Err(_) => DEFAULT_ACCOUNT_DELETION_GRACE_PERIOD_DAYS,
+    };
+    Duration::try_days(days).expect("ACCOUNT_DELETION_GRACE_PERIOD_DAYS is out of range.")
+}
+
+fn set_auth_service_url() -> String {
+    dotenv().ok();
+    std_env::var(env::AUTH_SERVICE_URL_ENV_VAR)
+        .ok()
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_AUTH_SERVICE_URL.to_owned())
+}
+
+// JSON array of external OpenID Connect providers, see `services::oidc_client::OidcProviderConfig`
+fn set_oidc_providers() -> Secret<String> {
+    dotenv().ok();
+    Secret::new(
+        std_env::var(env::OIDC_PROVIDERS_ENV_VAR)
+            .ok()
+            .filter(|providers| !providers.is_empty())
+            .unwrap_or("[]".to_owned()),
+    )
+}
+
+// Failed logins in a row before an account gets locked, see `utils::lockout`
+fn set_login_lockout_threshold() -> u32 {
+    dotenv().ok();
+    let threshold = match std_env::var(env::LOGIN_LOCKOUT_THRESHOLD_ENV_VAR) {
+        Ok(threshold) => threshold
+            .parse()
+            .expect("LOGIN_LOCKOUT_THRESHOLD must be a number of attempts."),
+        Err(_) => DEFAULT_LOGIN_LOCKOUT_THRESHOLD,
+    };
+    if threshold == 0 {
+        panic!("LOGIN_LOCKOUT_THRESHOLD must be at least 1.");
+    }
+    threshold
+}
+
+// Attributes of the auth and refresh token cookies. The defaults suit a single host served over
+// plain HTTP, deployments behind HTTPS should at least set AUTH_COOKIE_SECURE.
+fn set_auth_cookie_policy() -> CookiePolicy {
+    dotenv().ok();
+    let name = std_env::var(env::AUTH_COOKIE_NAME_ENV_VAR)
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(JWT_COOKIE_NAME.to_owned());
+    let domain = std_env::var(env::AUTH_COOKIE_DOMAIN_ENV_VAR)
+        .ok()
+        .filter(|domain| !domain.is_empty());
+    let secure = match std_env::var(env::AUTH_COOKIE_SECURE_ENV_VAR) {
+        Ok(secure) => secure
+            .parse()
+            .expect("AUTH_COOKIE_SECURE must be true or false."),
+        Err(_) => false,
+    };
+    let same_site = match std_env::var(env::AUTH_COOKIE_SAME_SITE_ENV_VAR) {
+        Ok(same_site) => {
+            parse_same_site(&same_site).expect("AUTH_COOKIE_SAME_SITE must be Strict, Lax or None.")
+        }
+        Err(_) => DEFAULT_AUTH_COOKIE_SAME_SITE,
+    };
+    CookiePolicy::new(&name, domain, secure, same_site).expect("Invalid auth cookie settings")
+}
+
+// Static key for the admin API, see `utils::admin`. Without it, only users with the admin role
+// can use the admin API.
+fn set_admin_api_key() -> Option<Secret<String>> {
+    dotenv().ok();
+    let key = std_env::var(env::ADMIN_API_KEY_ENV_VAR)
+        .ok()
+        .filter(|key| !key.is_empty())?;
+    if key.len() < MIN_ADMIN_API_KEY_LENGTH {
+        panic!("ADMIN_API_KEY must be at least {MIN_ADMIN_API_KEY_LENGTH} characters long.");
+    }
+    Some(Secret::new(key))
+}
+
+// Header the reverse proxy puts the client IP in, e.g. X-Forwarded-For or X-Real-IP. Without it
+// the IP of the connection is used, which behind a proxy is the proxy's for every client. Only set
+// this if every request passes through the proxy, otherwise clients can send any IP they like.
+fn set_client_ip_header() -> Option<HeaderName> {
+    dotenv().ok();
+    let header = std_env::var(env::CLIENT_IP_HEADER_ENV_VAR)
+        .ok()
+        .filter(|header| !header.is_empty())?;
+    Some(
+        header
+            .parse()
+            .expect("CLIENT_IP_HEADER must be a valid header name."),
+    )
+}
+
 pub mod env {
-    pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_SIGNING_KEY_ENV_VAR: &str = "JWT_SIGNING_KEY";
+    pub const JWT_SIGNING_KEYS_DIR_ENV_VAR: &str = "JWT_SIGNING_KEYS_DIR";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const TOTP_ENCRYPTION_KEY_ENV_VAR: &str = "TOTP_ENCRYPTION_KEY";
+    pub const ACCOUNT_DELETION_GRACE_PERIOD_DAYS_ENV_VAR: &str =
+        "ACCOUNT_DELETION_GRACE_PERIOD_DAYS";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
+    pub const OIDC_PROVIDERS_ENV_VAR: &str = "OIDC_PROVIDERS";
+    pub const LOGIN_LOCKOUT_THRESHOLD_ENV_VAR: &str = "LOGIN_LOCKOUT_THRESHOLD";
+    pub const AUTH_COOKIE_NAME_ENV_VAR: &str = "AUTH_COOKIE_NAME";
+    pub const AUTH_COOKIE_DOMAIN_ENV_VAR: &str = "AUTH_COOKIE_DOMAIN";
+    pub const AUTH_COOKIE_SECURE_ENV_VAR: &str = "AUTH_COOKIE_SECURE";
+    pub const AUTH_COOKIE_SAME_SITE_ENV_VAR: &str = "AUTH_COOKIE_SAME_SITE";
+    pub const ADMIN_API_KEY_ENV_VAR: &str = "ADMIN_API_KEY";
+    pub const CLIENT_IP_HEADER_ENV_VAR: &str = "CLIENT_IP_HEADER";
 }
 
+// Default name of the auth cookie, see `AUTH_COOKIE_POLICY`
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+pub const CSRF_COOKIE_NAME: &str = "csrf_token";
+pub const OIDC_STATE_COOKIE_NAME: &str = "oidc_state";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS: i64 = 60;
+// Codes that can be submitted for one login attempt before the user has to log in again
+pub const MAX_TWO_FA_ATTEMPTS: u32 = 5;
+pub const TWO_FA_RESEND_COOLDOWN_SECONDS: i64 = 30;
+pub const MAX_TWO_FA_RESENDS: u32 = 3;
+// Login attempts awaiting 2FA per user, e.g. one per device the user is signing in on
+pub const MAX_PENDING_TWO_FA_LOGINS: usize = 5;
+pub const DEFAULT_ACCOUNT_DELETION_GRACE_PERIOD_DAYS: i64 = 30;
+pub const DEFAULT_AUTH_SERVICE_URL: &str = "http://localhost:3000";
+pub const DEFAULT_LOGIN_LOCKOUT_THRESHOLD: u32 = 5;
+pub const DEFAULT_AUTH_COOKIE_SAME_SITE: SameSite = SameSite::Lax;
+pub const MIN_ADMIN_API_KEY_LENGTH: usize = 32;
+// Users per page of the admin user listing, unless the request asks for fewer or more
+pub const DEFAULT_ADMIN_USERS_PAGE_SIZE: u64 = 20;
+pub const MAX_ADMIN_USERS_PAGE_SIZE: u64 = 100;
+
+// Built-in roles and the permissions they grant, also seeded by the roles migration
+pub mod roles {
+    pub const ADMIN: &str = "admin";
+    pub const ADMIN_PERMISSIONS: [&str; 3] = [
+        super::permissions::OAUTH_CLIENTS_WRITE,
+        super::permissions::USERS_READ,
+        super::permissions::USERS_WRITE,
+    ];
+}
+
+pub mod permissions {
+    pub const USERS_READ: &str = "users:read";
+    pub const USERS_WRITE: &str = "users:write";
+    pub const OAUTH_CLIENTS_WRITE: &str = "oauth_clients:write";
+}
+
+// Per-route rate limits, see `utils::rate_limit`
+pub mod rate_limits {
+    use std::time::Duration;
+
+    use crate::{domain::RateLimit, utils::rate_limit::RouteRateLimits};
+
+    pub const SIGNUP: RouteRateLimits = RouteRateLimits {
+        per_ip: Some(RateLimit {
+            capacity: 10,
+            refill_interval: Duration::from_secs(60),
+        }),
+        per_email: Some(RateLimit {
+            capacity: 3,
+            refill_interval: Duration::from_secs(60),
+        }),
+    };
+    // Password guessing is also bounded by account lockout, see `utils::lockout`
+    pub const LOGIN: RouteRateLimits = RouteRateLimits {
+        per_ip: Some(RateLimit {
+            capacity: 20,
+            refill_interval: Duration::from_secs(3),
+        }),
+        per_email: Some(RateLimit {
+            capacity: 10,
+            refill_interval: Duration::from_secs(30),
+        }),
+    };
+    pub const VERIFY_2FA: RouteRateLimits = RouteRateLimits {
+        per_ip: Some(RateLimit {
+            capacity: 20,
+            refill_interval: Duration::from_secs(3),
+        }),
+        per_email: Some(RateLimit {
+            capacity: 5,
+            refill_interval: Duration::from_secs(30),
+        }),
+    };
+    // Resends per login attempt are also limited, see `MAX_TWO_FA_RESENDS`
+    pub const RESEND_2FA: RouteRateLimits = RouteRateLimits {
+        per_ip: Some(RateLimit {
+            capacity: 10,
+            refill_interval: Duration::from_secs(6),
+        }),
+        per_email: Some(RateLimit {
+            capacity: 5,
+            refill_interval: Duration::from_secs(60),
+        }),
+    };
+    // Routes that send email to the address in the request are limited per email, so they can't be
+    // used to flood someone's inbox
+    pub const FORGOT_PASSWORD: RouteRateLimits = RouteRateLimits {
+        per_ip: Some(RateLimit {
+            capacity: 10,
+            refill_interval: Duration::from_secs(6),
+        }),
+        per_email: Some(RateLimit {
+            capacity: 3,
+            refill_interval: Duration::from_secs(300),
+        }),
+    };
+    pub const MAGIC_LINK: RouteRateLimits = RouteRateLimits {
+        per_ip: Some(RateLimit {
+            capacity: 10,
+            refill_interval: Duration::from_secs(6),
+        }),
+        per_email: Some(RateLimit {
+            capacity: 3,
+            refill_interval: Duration::from_secs(300),
+        }),
+    };
+    // Resends are also limited by `EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS`
+    pub const RESEND_VERIFICATION_EMAIL: RouteRateLimits = RouteRateLimits {
+        per_ip: Some(RateLimit {
+            capacity: 10,
+            refill_interval: Duration::from_secs(6),
+        }),
+        per_email: Some(RateLimit {
+            capacity: 3,
+            refill_interval: Duration::from_secs(300),
+        }),
+    };
+}
 
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
+    pub const ACCOUNT_PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+    pub const SIGNING_KEY_RELOAD_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
     pub mod email_client {
         use std::time::Duration;
 
@@ -60,6 +332,11 @@ pub mod prod {
         pub const SENDER: &str = "bogdan@codeiron.io";
         pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
     }
+    pub mod oidc_client {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+    }
 }
 
 pub mod test {
@@ -70,4 +347,9 @@ pub mod test {
         pub const SENDER: &str = "test@email.com";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
+    pub mod oidc_client {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
+    }
 }
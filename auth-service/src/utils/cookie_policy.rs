@@ -0,0 +1,295 @@
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use color_eyre::eyre::{eyre, Result};
+use time::Duration;
+
+use super::{
+    auth::{REFRESH_TOKEN_TTL_SECONDS, TOKEN_TTL_SECONDS},
+    constants::{CSRF_COOKIE_NAME, OIDC_STATE_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
+
+// Browsers only accept `__Host-` cookies that are `Secure`, have no `Domain` and use path `/`,
+// which keeps subdomains from setting or overriding them
+const HOST_PREFIX: &str = "__Host-";
+// Browsers only accept `__Secure-` cookies that are `Secure`
+const SECURE_PREFIX: &str = "__Secure-";
+
+// Attributes shared by the auth, refresh token, CSRF and OIDC state cookies, see
+// `constants::AUTH_COOKIE_POLICY`. The other cookies carry the same name prefix as the auth cookie.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CookiePolicy {
+    auth_cookie_name: String,
+    refresh_cookie_name: String,
+    csrf_cookie_name: String,
+    oidc_state_cookie_name: String,
+    domain: Option<String>,
+    secure: bool,
+    same_site: SameSite,
+}
+
+impl CookiePolicy {
+    pub fn new(
+        auth_cookie_name: &str,
+        domain: Option<String>,
+        secure: bool,
+        same_site: SameSite,
+    ) -> Result<Self> {
+        let prefix = [HOST_PREFIX, SECURE_PREFIX]
+            .into_iter()
+            .find(|prefix| auth_cookie_name.starts_with(prefix))
+            .unwrap_or_default();
+
+        if auth_cookie_name.len() == prefix.len() {
+            return Err(eyre!("cookie name must not be empty"));
+        }
+        if !prefix.is_empty() && !secure {
+            return Err(eyre!("{prefix} cookies must be secure"));
+        }
+        if prefix == HOST_PREFIX && domain.is_some() {
+            return Err(eyre!("{HOST_PREFIX} cookies must not have a domain"));
+        }
+        // Browsers reject cross-site cookies that aren't secure
+        if same_site == SameSite::None && !secure {
+            return Err(eyre!("cookies with SameSite=None must be secure"));
+        }
+
+        Ok(Self {
+            auth_cookie_name: auth_cookie_name.to_owned(),
+            refresh_cookie_name: format!("{prefix}{REFRESH_TOKEN_COOKIE_NAME}"),
+            csrf_cookie_name: format!("{prefix}{CSRF_COOKIE_NAME}"),
+            // Not sent to every path, which `__Host-` cookies have to be
+            oidc_state_cookie_name: match prefix {
+                "" => OIDC_STATE_COOKIE_NAME.to_owned(),
+                _ => format!("{SECURE_PREFIX}{OIDC_STATE_COOKIE_NAME}"),
+            },
+            domain,
+            secure,
+            same_site,
+        })
+    }
+
+    pub fn auth_cookie_name(&self) -> &str {
+        &self.auth_cookie_name
+    }
+
+    pub fn refresh_cookie_name(&self) -> &str {
+        &self.refresh_cookie_name
+    }
+
+    pub fn csrf_cookie_name(&self) -> &str {
+        &self.csrf_cookie_name
+    }
+
+    pub fn oidc_state_cookie_name(&self) -> &str {
+        &self.oidc_state_cookie_name
+    }
+
+    // Expires together with the token, so browsers don't keep sending a dead token
+    pub fn auth_cookie(&self, token: String) -> Cookie<'static> {
+        self.build(self.auth_cookie_name.clone(), token, TOKEN_TTL_SECONDS)
+    }
+
+    pub fn refresh_cookie(&self, token: String) -> Cookie<'static> {
+        self.build(
+            self.refresh_cookie_name.clone(),
+            token,
+            REFRESH_TOKEN_TTL_SECONDS,
+        )
+    }
+
+    // Lasts as long as a login can be refreshed, so pages don't have to fetch a new token
+    pub fn csrf_cookie(&self, token: String) -> Cookie<'static> {
+        self.build(
+            self.csrf_cookie_name.clone(),
+            token,
+            REFRESH_TOKEN_TTL_SECONDS,
+        )
+    }
+
+    // Only sent to `path` and lasts until the browser is closed. The provider sends the user back
+    // with a cross-site navigation, which Strict cookies aren't sent with, so those are sent as Lax.
+    pub fn oidc_state_cookie(&self, state: String, path: &'static str) -> Cookie<'static> {
+        let mut cookie = self.removal_cookie(self.oidc_state_cookie_name.clone());
+        cookie.set_path(path);
+        cookie.set_value(state);
+        cookie.set_http_only(true);
+        cookie.set_secure(self.secure);
+        cookie.set_same_site(match self.same_site {
+            SameSite::Strict => SameSite::Lax,
+            same_site => same_site,
+        });
+
+        cookie
+    }
+
+    pub fn remove_oidc_state_cookie(&self, jar: CookieJar, path: &'static str) -> CookieJar {
+        let mut cookie = self.removal_cookie(self.oidc_state_cookie_name.clone());
+        cookie.set_path(path);
+
+        jar.remove(cookie)
+    }
+
+    // Browsers only drop a cookie when the removal cookie has the same path and domain
+    pub fn remove_cookies(&self, jar: CookieJar) -> CookieJar {
+        jar.remove(self.removal_cookie(self.auth_cookie_name.clone()))
+            .remove(self.removal_cookie(self.refresh_cookie_name.clone()))
+    }
+
+    fn build(&self, name: String, value: String, max_age_seconds: i64) -> Cookie<'static> {
+        let mut cookie = self.removal_cookie(name);
+        cookie.set_value(value);
+        cookie.set_http_only(true);
+        cookie.set_secure(self.secure);
+        cookie.set_same_site(self.same_site);
+        cookie.set_max_age(Duration::seconds(max_age_seconds));
+
+        cookie
+    }
+
+    fn removal_cookie(&self, name: String) -> Cookie<'static> {
+        let mut cookie = Cookie::build(name).path("/").build();
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        cookie
+    }
+}
+
+pub fn parse_same_site(value: &str) -> Result<SameSite> {
+    match value.to_lowercase().as_str() {
+        "strict" => Ok(SameSite::Strict),
+        "lax" => Ok(SameSite::Lax),
+        "none" => Ok(SameSite::None),
+        _ => Err(eyre!("unknown SameSite value {value}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        http::{header, HeaderMap, HeaderValue},
+        response::IntoResponse,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_auth_cookie_attributes() {
+        let policy = CookiePolicy::new(
+            "jwt",
+            Some("example.com".to_owned()),
+            true,
+            SameSite::Strict,
+        )
+        .unwrap();
+
+        let cookie = policy.auth_cookie("token".to_owned());
+        assert_eq!(cookie.name(), "jwt");
+        assert_eq!(cookie.value(), "token");
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(cookie.max_age(), Some(Duration::seconds(TOKEN_TTL_SECONDS)));
+
+        let cookie = policy.refresh_cookie("token".to_owned());
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(
+            cookie.max_age(),
+            Some(Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        );
+    }
+
+    #[test]
+    fn test_oidc_state_cookie_attributes() {
+        let policy = CookiePolicy::new(
+            "jwt",
+            Some("example.com".to_owned()),
+            true,
+            SameSite::Strict,
+        )
+        .unwrap();
+
+        let cookie = policy.oidc_state_cookie("state".to_owned(), "/login/oidc");
+        assert_eq!(cookie.name(), OIDC_STATE_COOKIE_NAME);
+        assert_eq!(cookie.path(), Some("/login/oidc"));
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(true));
+        // Strict cookies wouldn't come back with the provider's redirect
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.max_age(), None);
+    }
+
+    #[test]
+    fn test_prefix_applies_to_refresh_cookie() {
+        let policy = CookiePolicy::new("__Host-jwt", None, true, SameSite::Lax).unwrap();
+        assert_eq!(policy.auth_cookie_name(), "__Host-jwt");
+        assert_eq!(policy.refresh_cookie_name(), "__Host-refresh_token");
+        assert_eq!(policy.csrf_cookie_name(), "__Host-csrf_token");
+
+        // Cookies with other paths can't use `__Host-`
+        assert_eq!(policy.oidc_state_cookie_name(), "__Secure-oidc_state");
+
+        let policy = CookiePolicy::new("__Secure-jwt", None, true, SameSite::Lax).unwrap();
+        assert_eq!(policy.refresh_cookie_name(), "__Secure-refresh_token");
+        assert_eq!(policy.oidc_state_cookie_name(), "__Secure-oidc_state");
+    }
+
+    #[test]
+    fn test_rejects_invalid_policies() {
+        assert!(CookiePolicy::new("", None, false, SameSite::Lax).is_err());
+        assert!(CookiePolicy::new("__Host-", None, true, SameSite::Lax).is_err());
+        assert!(CookiePolicy::new("__Host-jwt", None, false, SameSite::Lax).is_err());
+        assert!(CookiePolicy::new(
+            "__Host-jwt",
+            Some("example.com".to_owned()),
+            true,
+            SameSite::Lax
+        )
+        .is_err());
+        assert!(CookiePolicy::new("__Secure-jwt", None, false, SameSite::Lax).is_err());
+        assert!(CookiePolicy::new("jwt", None, false, SameSite::None).is_err());
+    }
+
+    #[test]
+    fn test_remove_cookies_matches_domain() {
+        let policy =
+            CookiePolicy::new("jwt", Some("example.com".to_owned()), false, SameSite::Lax).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("jwt=token; refresh_token=token"),
+        );
+        let response = policy
+            .remove_cookies(CookieJar::from_headers(&headers))
+            .into_response();
+
+        let removed = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|value| Cookie::parse(value.to_str().unwrap().to_owned()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(removed.len(), 2);
+        for cookie in removed {
+            assert_eq!(cookie.value(), "");
+            assert_eq!(cookie.path(), Some("/"));
+            assert_eq!(cookie.domain(), Some("example.com"));
+            assert_eq!(cookie.max_age(), Some(Duration::ZERO));
+        }
+    }
+
+    #[test]
+    fn test_parse_same_site() {
+        assert_eq!(parse_same_site("Strict").unwrap(), SameSite::Strict);
+        assert_eq!(parse_same_site("lax").unwrap(), SameSite::Lax);
+        assert_eq!(parse_same_site("none").unwrap(), SameSite::None);
+        assert!(parse_same_site("sometimes").is_err());
+    }
+}
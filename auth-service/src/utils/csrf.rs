@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::CookieJar;
+use reqwest::Url;
+use secrecy::Secret;
+
+use crate::{
+    domain::{AuthAPIError, CsrfToken},
+    utils::{
+        authenticated_user::{AuthToken, TokenSource},
+        constants::AUTH_COOKIE_POLICY,
+    },
+};
+
+// Header that carries the token handed out by `GET /csrf-token`
+pub const CSRF_HEADER_NAME: &str = "x-csrf-token";
+
+// State of the `csrf_protect` middleware
+#[derive(Clone)]
+pub struct CsrfGuard {
+    // Other origins allowed to make requests, the service's own origin always is
+    allowed_origins: Arc<Vec<HeaderValue>>,
+    // Routes only called from servers, e.g. with a token in the body rather than a cookie
+    exempt_paths: &'static [&'static str],
+}
+
+impl CsrfGuard {
+    pub fn new(allowed_origins: Vec<HeaderValue>, exempt_paths: &'static [&'static str]) -> Self {
+        Self {
+            allowed_origins: Arc::new(allowed_origins),
+            exempt_paths,
+        }
+    }
+
+    // Browsers send Origin with every cross-origin request, and some leave it out on same-origin
+    // ones, in which case Referer tells where the request came from. Requests from outside a
+    // browser carry neither and are left to the token check.
+    fn is_allowed_origin(&self, headers: &HeaderMap) -> bool {
+        let origin = match (headers.get(header::ORIGIN), headers.get(header::REFERER)) {
+            (Some(origin), _) => origin.to_str().ok().map(str::to_owned),
+            (None, Some(referer)) => referer
+                .to_str()
+                .ok()
+                .and_then(|referer| Url::parse(referer).ok())
+                .map(|url| url.origin().ascii_serialization()),
+            (None, None) => return true,
+        };
+
+        let Some(origin) = origin else {
+            return false;
+        };
+
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed == origin.as_str())
+            || is_same_origin(&origin, headers)
+    }
+}
+
+// Checks requests that change state. Their origin has to be allowed, and requests that carry
+// cookies have to double-submit the token from `GET /csrf-token`: in its cookie and in the
+// `X-CSRF-Token` header, which a page on another origin can't set. Requests authenticated with
+// an `Authorization` header carry no ambient credentials and are let through.
+pub async fn csrf_protect(
+    State(guard): State<CsrfGuard>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) || guard.exempt_paths.contains(&request.uri().path())
+    {
+        return next.run(request).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+
+    if let Ok(AuthToken {
+        source: TokenSource::BearerHeader,
+        ..
+    }) = AuthToken::from_request_parts(&mut parts, &()).await
+    {
+        return next.run(Request::from_parts(parts, body)).await;
+    }
+
+    let has_cookies = parts.headers.contains_key(header::COOKIE);
+    if !guard.is_allowed_origin(&parts.headers)
+        || (has_cookies && !has_matching_token(&parts.headers))
+    {
+        return AuthAPIError::CsrfCheckFailed.into_response();
+    }
+
+    next.run(Request::from_parts(parts, body)).await
+}
+
+fn has_matching_token(headers: &HeaderMap) -> bool {
+    let header_token = headers
+        .get(CSRF_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| CsrfToken::parse(Secret::new(value.to_owned())).ok());
+    let cookie_token = CookieJar::from_headers(headers)
+        .get(AUTH_COOKIE_POLICY.csrf_cookie_name())
+        .and_then(|cookie| CsrfToken::parse(Secret::new(cookie.value().to_owned())).ok());
+
+    match (header_token, cookie_token) {
+        (Some(header_token), Some(cookie_token)) => header_token == cookie_token,
+        _ => false,
+    }
+}
+
+fn is_same_origin(origin: &str, headers: &HeaderMap) -> bool {
+    let (Ok(origin), Some(host)) = (
+        Url::parse(origin),
+        headers
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok()),
+    ) else {
+        return false;
+    };
+
+    let authority = match (origin.host_str(), origin.port()) {
+        (Some(origin_host), Some(port)) => format!("{origin_host}:{port}"),
+        (Some(origin_host), None) => origin_host.to_owned(),
+        (None, _) => return false,
+    };
+
+    authority.eq_ignore_ascii_case(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::ExposeSecret;
+
+    use super::*;
+
+    fn headers(pairs: &[(header::HeaderName, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    fn guard() -> CsrfGuard {
+        CsrfGuard::new(vec![HeaderValue::from_static("http://localhost:8000")], &[])
+    }
+
+    #[test]
+    fn test_allows_same_and_listed_origins() {
+        let guard = guard();
+
+        assert!(guard.is_allowed_origin(&headers(&[
+            (header::HOST, "localhost:3000"),
+            (header::ORIGIN, "http://localhost:3000"),
+        ])));
+        assert!(guard.is_allowed_origin(&headers(&[
+            (header::HOST, "localhost:3000"),
+            (header::ORIGIN, "http://localhost:8000"),
+        ])));
+        assert!(guard.is_allowed_origin(&headers(&[
+            (header::HOST, "localhost:3000"),
+            (header::REFERER, "http://localhost:3000/?return_to=x"),
+        ])));
+        assert!(guard.is_allowed_origin(&headers(&[(header::HOST, "localhost:3000")])));
+    }
+
+    #[test]
+    fn test_rejects_other_origins() {
+        let guard = guard();
+
+        assert!(!guard.is_allowed_origin(&headers(&[
+            (header::HOST, "localhost:3000"),
+            (header::ORIGIN, "http://evil.example"),
+        ])));
+        assert!(!guard.is_allowed_origin(&headers(&[
+            (header::HOST, "localhost:3000"),
+            (header::ORIGIN, "null"),
+        ])));
+        assert!(!guard.is_allowed_origin(&headers(&[
+            (header::HOST, "localhost:3000"),
+            (header::REFERER, "http://evil.example/page"),
+        ])));
+    }
+
+    #[test]
+    fn test_token_has_to_match_cookie() {
+        let token = CsrfToken::default();
+        let value = token.as_ref().expose_secret().clone();
+        let cookie = format!("{}={}", AUTH_COOKIE_POLICY.csrf_cookie_name(), value);
+
+        let mut matching = HeaderMap::new();
+        matching.insert(header::COOKIE, cookie.parse().unwrap());
+        matching.insert(CSRF_HEADER_NAME, value.parse().unwrap());
+        assert!(has_matching_token(&matching));
+
+        let mut other = matching.clone();
+        let other_token = CsrfToken::default();
+        other.insert(
+            CSRF_HEADER_NAME,
+            other_token.as_ref().expose_secret().parse().unwrap(),
+        );
+        assert!(!has_matching_token(&other));
+
+        let mut missing_cookie = matching.clone();
+        missing_cookie.remove(header::COOKIE);
+        assert!(!has_matching_token(&missing_cookie));
+    }
+}
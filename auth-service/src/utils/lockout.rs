@@ -0,0 +1,46 @@
+use chrono::Duration;
+
+// The first lockout lasts a minute and every further failure doubles it, up to an hour. The cap
+// keeps an attacker from locking someone out of their account for long.
+const BASE_LOCKOUT_SECONDS: i64 = 60;
+const MAX_LOCKOUT_SECONDS: i64 = 60 * 60;
+// Failures are forgotten once none have been recorded for this long
+pub const FAILED_LOGIN_ATTEMPTS_TTL_SECONDS: i64 = 60 * 60 * 24;
+
+// How long to lock an account after the given number of failed logins in a row, if at all
+pub fn lockout_duration(failed_attempts: u32, threshold: u32) -> Option<Duration> {
+    if failed_attempts < threshold {
+        return None;
+    }
+
+    let doublings = (failed_attempts - threshold).min(MAX_LOCKOUT_SECONDS.ilog2());
+    let seconds = (BASE_LOCKOUT_SECONDS << doublings).min(MAX_LOCKOUT_SECONDS);
+    Some(Duration::seconds(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_lockout_below_threshold() {
+        assert_eq!(lockout_duration(0, 5), None);
+        assert_eq!(lockout_duration(4, 5), None);
+    }
+
+    #[test]
+    fn test_lockout_doubles_after_threshold() {
+        assert_eq!(lockout_duration(5, 5), Some(Duration::seconds(60)));
+        assert_eq!(lockout_duration(6, 5), Some(Duration::seconds(120)));
+        assert_eq!(lockout_duration(7, 5), Some(Duration::seconds(240)));
+    }
+
+    #[test]
+    fn test_lockout_is_capped() {
+        assert_eq!(lockout_duration(11, 5), Some(Duration::seconds(60 * 60)));
+        assert_eq!(
+            lockout_duration(u32::MAX, 5),
+            Some(Duration::seconds(60 * 60))
+        );
+    }
+}
@@ -1,3 +1,13 @@
+pub mod admin;
 pub mod auth;
+pub mod authenticated_user;
+pub mod client_info;
 pub mod constants;
+pub mod cookie_policy;
+pub mod csrf;
+pub mod lockout;
+pub mod pkce;
+pub mod rate_limit;
+pub mod signing_key;
+pub mod totp;
 pub mod tracing;
@@ -0,0 +1,64 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+
+// Only the S256 method is supported, `plain` offers no protection if the challenge leaks
+pub const PKCE_METHOD_S256: &str = "S256";
+const CODE_VERIFIER_MIN_LENGTH: usize = 43;
+const CODE_VERIFIER_MAX_LENGTH: usize = 128;
+// A base64url encoded SHA-256 digest without padding
+const CODE_CHALLENGE_LENGTH: usize = 43;
+
+// Verifiers use the unreserved URI characters, see RFC 7636 section 4.1
+pub fn is_valid_code_verifier(verifier: &str) -> bool {
+    (CODE_VERIFIER_MIN_LENGTH..=CODE_VERIFIER_MAX_LENGTH).contains(&verifier.len())
+        && verifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'))
+}
+
+pub fn is_valid_code_challenge(challenge: &str) -> bool {
+    challenge.len() == CODE_CHALLENGE_LENGTH
+        && challenge
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+pub fn compute_code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+pub fn verify_code_challenge(verifier: &str, challenge: &str) -> bool {
+    is_valid_code_verifier(verifier) && compute_code_challenge(verifier) == challenge
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example from RFC 7636 appendix B
+    const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    #[test]
+    fn test_compute_code_challenge_matches_rfc_example() {
+        assert_eq!(compute_code_challenge(VERIFIER), CHALLENGE);
+        assert!(is_valid_code_challenge(CHALLENGE));
+    }
+
+    #[test]
+    fn test_verify_code_challenge() {
+        assert!(verify_code_challenge(VERIFIER, CHALLENGE));
+        assert!(!verify_code_challenge(
+            "aBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk",
+            CHALLENGE
+        ));
+    }
+
+    #[test]
+    fn test_code_verifier_format() {
+        assert!(!is_valid_code_verifier("too-short"));
+        assert!(!is_valid_code_verifier(&"a".repeat(129)));
+        assert!(!is_valid_code_verifier(&format!("{}!", &VERIFIER[..43])));
+        assert!(is_valid_code_verifier(&"a".repeat(128)));
+    }
+}
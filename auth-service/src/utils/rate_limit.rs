@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::{
+    app_state::RateLimitStoreType,
+    domain::{AuthAPIError, RateLimit, RateLimitStoreError},
+    utils::client_info::ClientInfo,
+};
+
+// The limits of a single route. Either can be left out, e.g. for routes without an email.
+#[derive(Debug, Clone, Copy)]
+pub struct RouteRateLimits {
+    pub per_ip: Option<RateLimit>,
+    pub per_email: Option<RateLimit>,
+}
+
+// State of the `rate_limit` middleware for one route, each route has its own buckets
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: RateLimitStoreType,
+    route: &'static str,
+    limits: RouteRateLimits,
+}
+
+impl RateLimiter {
+    pub fn new(store: RateLimitStoreType, route: &'static str, limits: RouteRateLimits) -> Self {
+        Self {
+            store,
+            route,
+            limits,
+        }
+    }
+}
+
+// Rejects requests over the route's limits with 429 and a Retry-After header. Requests are
+// limited by client IP (see `ClientInfo` for deployments behind a reverse proxy), and by the
+// `email` field of JSON bodies so a single account can't be hammered from many addresses either.
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    client: ClientInfo,
+    request: Request,
+    next: Next,
+) -> Response {
+    // The body has to be read to find the email, and is handed on to the route afterwards
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_SIZE).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    };
+
+    let mut buckets = Vec::new();
+
+    if let (Some(limit), Some(ip)) = (limiter.limits.per_ip, client.ip) {
+        buckets.push((format!("{}:ip:{}", limiter.route, ip), limit));
+    }
+
+    let email = serde_json::from_slice::<EmailBody>(&bytes)
+        .ok()
+        .map(|body| body.email.trim().to_lowercase());
+    if let (Some(limit), Some(email)) = (limiter.limits.per_email, email) {
+        buckets.push((format!("{}:email:{}", limiter.route, email), limit));
+    }
+
+    for (key, limit) in buckets {
+        match limiter.store.write().await.take_token(&key, limit).await {
+            Ok(()) => {}
+            Err(RateLimitStoreError::LimitExceeded(retry_after)) => {
+                return too_many_requests(retry_after)
+            }
+            Err(e) => return AuthAPIError::UnexpectedError(e.into()).into_response(),
+        }
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    // Retry-After is in whole seconds, rounding down would invite a retry that fails again
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+    let mut response = AuthAPIError::TooManyRequests.into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(seconds.max(1)));
+    response
+}
+
+#[derive(Deserialize)]
+struct EmailBody {
+    email: String,
+}
+
+// Same as the default limit of axum's body extractors
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
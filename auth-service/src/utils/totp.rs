@@ -0,0 +1,127 @@
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::ExposeSecret;
+use totp_rs::{Algorithm, TOTP};
+
+use crate::domain::{Email, TotpSecret, TwoFACode};
+
+pub const TOTP_ISSUER: &str = "Auth Service";
+pub const TOTP_STEP_SECONDS: u64 = 30;
+// Codes from the previous and next time step are accepted to allow for clock drift
+pub const TOTP_SKEW_STEPS: u64 = 1;
+const TOTP_DIGITS: usize = 6;
+
+fn totp(secret: &TotpSecret, email: &Email) -> Result<TOTP> {
+    let secret = totp_rs::Secret::Encoded(secret.as_ref().expose_secret().to_owned())
+        .to_bytes()
+        .map_err(|e| eyre!("failed to decode TOTP secret: {:?}", e))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        TOTP_SKEW_STEPS as u8,
+        TOTP_STEP_SECONDS,
+        secret,
+        Some(TOTP_ISSUER.to_owned()),
+        email.as_ref().expose_secret().to_owned(),
+    )
+    .wrap_err("failed to create TOTP")
+}
+
+// Returns the `otpauth://` URI authenticator apps use to register the secret
+pub fn generate_totp_uri(secret: &TotpSecret, email: &Email) -> Result<String> {
+    Ok(totp(secret, email)?.get_url())
+}
+
+// Returns the time step the code belongs to if it is valid at `time` (in seconds since the epoch)
+#[tracing::instrument(name = "Verify TOTP code", skip_all)]
+pub fn verify_totp_code(
+    secret: &TotpSecret,
+    email: &Email,
+    code: &TwoFACode,
+    time: u64,
+) -> Result<Option<u64>> {
+    let totp = totp(secret, email)?;
+    let current_step = time / TOTP_STEP_SECONDS;
+    let code = code.as_ref().expose_secret().as_bytes();
+
+    // Every candidate is checked so the comparison takes the same time whichever step matches
+    let mut matched_step = None;
+    for step in current_step.saturating_sub(TOTP_SKEW_STEPS)..=current_step + TOTP_SKEW_STEPS {
+        let candidate = totp.generate(step * TOTP_STEP_SECONDS);
+        if constant_time_eq(candidate.as_bytes(), code) {
+            matched_step = Some(step);
+        }
+    }
+
+    Ok(matched_step)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_owned())).unwrap()
+    }
+
+    fn code_at(secret: &TotpSecret, time: u64) -> TwoFACode {
+        let code = totp(secret, &email()).unwrap().generate(time);
+        TwoFACode::parse(Secret::new(code)).unwrap()
+    }
+
+    #[test]
+    fn test_generate_totp_uri() {
+        let secret = TotpSecret::default();
+        let uri = generate_totp_uri(&secret, &email()).unwrap();
+
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains(&format!("secret={}", secret.as_ref().expose_secret())));
+        assert!(uri.contains("issuer=Auth%20Service"));
+    }
+
+    #[test]
+    fn test_verify_totp_code_matches_rfc_6238_vector() {
+        // Test vector from RFC 6238 appendix B, truncated to 6 digits
+        let secret =
+            TotpSecret::parse(Secret::new("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_owned())).unwrap();
+        let code = TwoFACode::parse(Secret::new("287082".to_owned())).unwrap();
+
+        assert_eq!(
+            verify_totp_code(&secret, &email(), &code, 59).unwrap(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_verify_totp_code_accepts_one_step_of_skew() {
+        let secret = TotpSecret::default();
+        let time = 1_000_000 * TOTP_STEP_SECONDS;
+
+        for offset in [-1i64, 0, 1] {
+            let code_time = (time as i64 + offset * TOTP_STEP_SECONDS as i64) as u64;
+            let code = code_at(&secret, code_time);
+            assert_eq!(
+                verify_totp_code(&secret, &email(), &code, time).unwrap(),
+                Some(code_time / TOTP_STEP_SECONDS)
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_totp_code_rejects_codes_outside_skew() {
+        let secret =
+            TotpSecret::parse(Secret::new("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_owned())).unwrap();
+        let code = TwoFACode::parse(Secret::new("287082".to_owned())).unwrap();
+
+        // The code belongs to step 1, which is more than one step behind step 3
+        assert_eq!(
+            verify_totp_code(&secret, &email(), &code, 3 * TOTP_STEP_SECONDS).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,391 @@
+use auth_service::{
+    domain::{Email, OAuthClientSecret},
+    routes::{
+        AdminUserResponse, BearerTokenResponse, ListUsersResponse, RegisterOAuthClientResponse,
+        TwoFactorAuthResponse,
+    },
+    utils::{admin::ADMIN_KEY_HEADER_NAME, constants::roles},
+    ErrorResponse,
+};
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    email
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    let login_body = json!({
+        "email": email,
+        "password": password,
+        "tokenDelivery": "body"
+    });
+
+    app.post_login(&login_body).await
+}
+
+async fn login_for_token(app: &TestApp, email: &str) -> String {
+    let response = login(app, email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<BearerTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to BearerTokenResponse")
+        .access_token
+}
+
+async fn signup_admin(app: &TestApp) -> String {
+    let email = signup(app).await;
+    app.user_store
+        .write()
+        .await
+        .add_role(
+            &Email::parse(Secret::new(email.clone())).unwrap(),
+            roles::ADMIN,
+        )
+        .await
+        .unwrap();
+
+    login_for_token(app, &email).await
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_only_let_admins_in() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    let token = login_for_token(&app, &email).await;
+
+    let response = app.get_admin_users(&token, &()).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Insufficient permissions");
+
+    let response = app.post_admin_user_action(&token, &email, "disable").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // No admin key is configured for tests, so none is accepted
+    let response = app
+        .http_client
+        .get(format!("{}/admin/users", &app.address))
+        .header(ADMIN_KEY_HEADER_NAME, "a".repeat(32))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_and_search_users() {
+    let mut app = TestApp::new().await;
+
+    let admin_token = signup_admin(&app).await;
+    let mut emails = [signup(&app).await, signup(&app).await];
+    emails.sort();
+
+    let response = app.get_admin_users(&admin_token, &()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let list = response.json::<ListUsersResponse>().await.unwrap();
+    assert_eq!(list.total, 3);
+    assert_eq!(list.page, 1);
+    assert_eq!(list.users.len(), 3);
+
+    let query = [("search", emails[1].to_uppercase())];
+    let response = app.get_admin_users(&admin_token, &query).await;
+    let list = response.json::<ListUsersResponse>().await.unwrap();
+    assert_eq!(list.total, 1);
+    assert_eq!(list.users[0].email, emails[1]);
+
+    // Users are ordered by email, and the total covers every page
+    let query = [("perPage", "1"), ("page", "2")];
+    let response = app.get_admin_users(&admin_token, &query).await;
+    let list = response.json::<ListUsersResponse>().await.unwrap();
+    assert_eq!(list.total, 3);
+    assert_eq!(list.per_page, 1);
+    assert_eq!(list.users.len(), 1);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_user() {
+    let mut app = TestApp::new().await;
+
+    let admin_token = signup_admin(&app).await;
+    let email = signup(&app).await;
+
+    let response = app.get_admin_user(&admin_token, &email).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let user = response.json::<AdminUserResponse>().await.unwrap();
+    assert_eq!(user.email, email);
+    assert!(user.email_verified);
+    assert!(!user.disabled);
+    assert!(user.roles.is_empty());
+
+    let response = app.get_admin_user(&admin_token, &get_random_email()).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_disable_and_enable_user() {
+    let mut app = TestApp::new().await;
+
+    let admin_token = signup_admin(&app).await;
+    let email = signup(&app).await;
+    let token = login_for_token(&app, &email).await;
+
+    let response = app
+        .post_admin_user_action(&admin_token, &email, "disable")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let user = response.json::<AdminUserResponse>().await.unwrap();
+    assert!(user.disabled);
+
+    // The user is signed out and can't sign back in
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Account disabled");
+
+    let response = app
+        .post_admin_user_action(&admin_token, &email, "enable")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_force_2fa() {
+    let mut app = TestApp::new().await;
+
+    let admin_token = signup_admin(&app).await;
+    let email = signup(&app).await;
+    let token = login_for_token(&app, &email).await;
+
+    // One email about 2FA being turned on and one with the login code
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_admin_user_action(&admin_token, &email, "force-2fa")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let user = response.json::<AdminUserResponse>().await.unwrap();
+    assert!(user.requires_2fa);
+
+    // Sessions signed in without a second factor are ended
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 206);
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_force_password_reset() {
+    let mut app = TestApp::new().await;
+
+    let admin_token = signup_admin(&app).await;
+    let email = signup(&app).await;
+    let token = login_for_token(&app, &email).await;
+
+    let response = app
+        .post_admin_user_action(&admin_token, &email, "force-password-reset")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let user = response.json::<AdminUserResponse>().await.unwrap();
+    assert!(user.password_reset_required);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Password reset required");
+
+    // The emailed reset token lifts the requirement
+    let reset_token = app.get_emailed_token(&email, "Password reset").await;
+    let reset_body = json!({
+        "token": reset_token,
+        "newPassword": "newpassword123"
+    });
+    let response = app.post_reset_password(&reset_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &email, "newpassword123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_tokens() {
+    let mut app = TestApp::new().await;
+
+    let admin_token = signup_admin(&app).await;
+    let email = signup(&app).await;
+    let token = login_for_token(&app, &email).await;
+
+    let response = app
+        .post_admin_user_action(&admin_token, &email, "revoke-tokens")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Signing in again still works
+    login_for_token(&app, &email).await;
+
+    let response = app
+        .post_admin_user_action(&admin_token, &get_random_email(), "revoke-tokens")
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_register_oauth_client() {
+    let mut app = TestApp::new().await;
+
+    let admin_token = signup_admin(&app).await;
+    let body = json!({
+        "clientId": "billing-dashboard",
+        "redirectUris": ["https://billing.example.com/callback"],
+        "confidential": true
+    });
+
+    let response = app.post_admin_oauth_client(&admin_token, &body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let client = response
+        .json::<RegisterOAuthClientResponse>()
+        .await
+        .expect("Could not deserialize response body to RegisterOAuthClientResponse");
+    assert_eq!(client.client_id, "billing-dashboard");
+    assert!(client.confidential);
+
+    // The returned secret authenticates the client
+    let client_secret = client
+        .client_secret
+        .expect("confidential clients get a secret");
+    let client_secret = OAuthClientSecret::parse(Secret::new(client_secret)).unwrap();
+    app.oauth_client_store
+        .read()
+        .await
+        .validate_client_secret("billing-dashboard", &client_secret)
+        .await
+        .unwrap();
+
+    let response = app.post_admin_oauth_client(&admin_token, &body).await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(error_message(response).await, "OAuth client already exists");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_register_public_oauth_client_without_secret() {
+    let mut app = TestApp::new().await;
+
+    let admin_token = signup_admin(&app).await;
+    let body = json!({
+        "clientId": "mobile-app",
+        "redirectUris": ["com.example.app:/callback"],
+        "confidential": false
+    });
+
+    let response = app.post_admin_oauth_client(&admin_token, &body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let client = response
+        .json::<RegisterOAuthClientResponse>()
+        .await
+        .expect("Could not deserialize response body to RegisterOAuthClientResponse");
+    assert_eq!(client.client_secret, None);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_invalid_oauth_client() {
+    let mut app = TestApp::new().await;
+
+    let admin_token = signup_admin(&app).await;
+    let bodies = [
+        json!({ "clientId": "", "redirectUris": ["https://app.test/cb"], "confidential": true }),
+        json!({ "clientId": "web", "redirectUris": [], "confidential": true }),
+        json!({ "clientId": "web", "redirectUris": ["/relative"], "confidential": true }),
+        json!({ "clientId": "web", "redirectUris": ["https://app.test/#x"], "confidential": true }),
+    ];
+
+    for body in bodies {
+        let response = app.post_admin_oauth_client(&admin_token, &body).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            body
+        );
+    }
+
+    // Registering clients takes the admin role
+    let email = signup(&app).await;
+    let token = login_for_token(&app, &email).await;
+    let body = json!({
+        "clientId": "web",
+        "redirectUris": ["https://example.com/cb"],
+        "confidential": true
+    });
+    let response = app.post_admin_oauth_client(&token, &body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,175 @@
+use auth_service::{
+    routes::{BearerTokenResponse, SessionsResponse, TwoFactorAuthResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, requires_2fa: bool) -> String {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    email
+}
+
+async fn login_for_token(app: &TestApp, email: &str) -> String {
+    let login_body = json!({
+        "email": email,
+        "password": "password123",
+        "tokenDelivery": "body"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The token is only handed out in the body
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let json = response
+        .json::<BearerTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to BearerTokenResponse");
+    assert_eq!(json.token_type, "Bearer");
+
+    json.access_token
+}
+
+async fn post_with_bearer(app: &TestApp, route: &str, token: &str) -> reqwest::Response {
+    app.http_client
+        .post(format!("{}{}", &app.address, route))
+        .bearer_auth(token)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn should_accept_bearer_token_on_authenticated_routes() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app, false).await;
+    let token = login_for_token(&app, &email).await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/sessions", &app.address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = response
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+        .sessions;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    // Without a body, verify-token checks the token the request was made with
+    let response = post_with_bearer(&app, "/verify-token", &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = post_with_bearer(&app, "/logout", &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&json!({ "token": &token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = post_with_bearer(&app, "/logout", &token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_bearer_token() {
+    let mut app = TestApp::new().await;
+
+    for route in ["/logout", "/verify-token"] {
+        let response = post_with_bearer(&app, route, "invalid").await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Missing auth token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_token_in_body_after_2fa() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let code = app.get_emailed_token(&email, "2FA Code").await;
+
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code,
+        "tokenDelivery": "body"
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let token = response
+        .json::<BearerTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to BearerTokenResponse")
+        .access_token;
+
+    let response = post_with_bearer(&app, "/verify-token", &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,256 @@
+use auth_service::{
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+struct Session {
+    email: String,
+    jwt: String,
+    refresh_token: String,
+}
+
+async fn signup_and_login(app: &TestApp) -> Session {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let jwt = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh token cookie found")
+        .value()
+        .to_owned();
+
+    Session {
+        email,
+        jwt,
+        refresh_token,
+    }
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let test_cases = vec![
+        json!({ "currentPassword": "password123" }),
+        json!({ "newPassword": "password456" }),
+        json!({}),
+    ];
+
+    for malformed_body in test_cases {
+        let response = app.post_change_password(&malformed_body).await;
+        assert_eq!(response.status().as_u16(), 422);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let body = json!({
+        "currentPassword": "password123",
+        "newPassword": "password456"
+    });
+
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Missing auth token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let body = json!({
+        "currentPassword": "password123",
+        "newPassword": "password456"
+    });
+
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Invalid auth token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_current_password_is_incorrect() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let body = json!({
+        "currentPassword": "wrongpassword",
+        "newPassword": "password456"
+    });
+
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Incorrect credentials");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_new_password_is_invalid() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let body = json!({
+        "currentPassword": "password123",
+        "newPassword": "short"
+    });
+
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Invalid credentials");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_revoke_other_sessions() {
+    let mut app = TestApp::new().await;
+
+    let session = signup_and_login(&app).await;
+
+    let body = json!({
+        "currentPassword": "password123",
+        "newPassword": "password456"
+    });
+
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The caller gets a fresh auth cookie
+    let new_jwt = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    assert_ne!(new_jwt, session.jwt);
+
+    let response = app.post_verify_token(&json!({ "token": new_jwt })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The token used for the request is no longer accepted
+    let response = app.post_verify_token(&json!({ "token": session.jwt })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Refresh tokens issued before the change can't be used to resume a session
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, session.refresh_token
+        ),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Only the new password is accepted from now on
+    let response = app
+        .post_login(&json!({ "email": &session.email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&json!({ "email": &session.email, "password": "password456" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_invalidate_access_tokens_of_other_sessions() {
+    let mut app = TestApp::new().await;
+
+    let other_session = signup_and_login(&app).await;
+
+    // Log in a second time so the password is changed from a different session
+    let response = app
+        .post_login(&json!({ "email": &other_session.email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = json!({
+        "currentPassword": "password123",
+        "newPassword": "password456"
+    });
+
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&json!({ "token": other_session.jwt }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
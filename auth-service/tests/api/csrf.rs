@@ -0,0 +1,144 @@
+use auth_service::{
+    routes::{BearerTokenResponse, CsrfTokenResponse},
+    ErrorResponse,
+};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp, token_delivery: &str) -> reqwest::Response {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123",
+        "tokenDelivery": token_delivery
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+}
+
+// Shares the test app's cookies but, like a page on another site, can't send the CSRF token
+fn client_without_csrf_token(app: &TestApp) -> reqwest::Client {
+    reqwest::Client::builder()
+        .cookie_provider(app.cookie_jar.clone())
+        .build()
+        .unwrap()
+}
+
+async fn assert_csrf_check_failed(response: reqwest::Response) {
+    assert_eq!(response.status().as_u16(), 403);
+
+    let json = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "CSRF check failed");
+}
+
+#[tokio::test]
+async fn should_return_403_if_csrf_token_missing() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, "cookie").await;
+
+    let response = client_without_csrf_token(&app)
+        .post(format!("{}/logout", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_csrf_check_failed(response).await;
+
+    // The session is still there
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_other_origin() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, "cookie").await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/logout", &app.address))
+        .header("Origin", "http://evil.example")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_csrf_check_failed(response).await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/logout", &app.address))
+        .header("Origin", &app.address)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_require_csrf_token_for_bearer_requests() {
+    let mut app = TestApp::new().await;
+
+    let token = signup_and_login(&app, "body")
+        .await
+        .json::<BearerTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to BearerTokenResponse")
+        .access_token;
+
+    let response = client_without_csrf_token(&app)
+        .post(format!("{}/logout", &app.address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_existing_csrf_token() {
+    let mut app = TestApp::new().await;
+
+    let mut tokens = Vec::new();
+    for _ in 0..2 {
+        let response = app
+            .http_client
+            .get(format!("{}/csrf-token", &app.address))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status().as_u16(), 200);
+
+        let json = response
+            .json::<CsrfTokenResponse>()
+            .await
+            .expect("Could not deserialize response body to CsrfTokenResponse");
+        tokens.push(json.csrf_token);
+    }
+
+    assert_eq!(tokens[0], tokens[1]);
+
+    app.clean_up().await;
+}
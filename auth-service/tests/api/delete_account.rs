@@ -0,0 +1,238 @@
+use auth_service::{
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+struct Session {
+    email: String,
+    jwt: String,
+    refresh_token: String,
+}
+
+async fn signup_and_login(app: &TestApp) -> Session {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let jwt = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh token cookie found")
+        .value()
+        .to_owned();
+
+    Session {
+        email,
+        jwt,
+        refresh_token,
+    }
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let response = app.post_delete_account(&json!({})).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let test_cases = vec![
+        json!({ "email": get_random_email() }),
+        json!({ "password": "password123" }),
+        json!({}),
+    ];
+
+    for malformed_body in test_cases {
+        let response = app.post_restore_account(&malformed_body).await;
+        assert_eq!(response.status().as_u16(), 422);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_delete_account(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Missing auth token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app
+        .post_delete_account(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Invalid auth token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_is_incorrect() {
+    let mut app = TestApp::new().await;
+
+    let session = signup_and_login(&app).await;
+
+    let response = app
+        .post_delete_account(&json!({ "password": "wrongpassword" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Incorrect credentials");
+
+    // The account is untouched
+    let response = app
+        .post_verify_token(&json!({ "token": session.jwt }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_block_login_after_deletion() {
+    let mut app = TestApp::new().await;
+
+    let session = signup_and_login(&app).await;
+
+    let response = app
+        .post_delete_account(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+
+    // Every token issued to the user is revoked
+    let response = app
+        .post_verify_token(&json!({ "token": session.jwt }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, session.refresh_token
+        ),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let login_body = json!({
+        "email": &session.email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Account pending deletion");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_restore_account_within_grace_period() {
+    let mut app = TestApp::new().await;
+
+    let session = signup_and_login(&app).await;
+
+    let response = app
+        .post_delete_account(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_restore_account(&json!({
+            "email": &session.email,
+            "password": "wrongpassword"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_restore_account(&json!({
+            "email": &session.email,
+            "password": "password123"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_body = json!({
+        "email": &session.email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,119 @@
+use auth_service::{routes::ForgotPasswordResponse, ErrorResponse};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = vec![
+        json!({
+            "mail": "test@example.com"
+        }),
+        json!({}),
+        json!("not an object"),
+    ];
+
+    for malformed_body in test_cases {
+        let response = app.post_forgot_password(&malformed_body).await;
+        assert_eq!(response.status().as_u16(), 422);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_email() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_forgot_password(&json!({ "email": "not-an-email" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let error_response = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Failed to deserialize error response");
+    assert_eq!(error_response.error, "Invalid credentials");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_respond_identically_whether_or_not_user_exists() {
+    let mut app = TestApp::new().await;
+
+    let existing_email = get_random_email();
+    let signup_body = json!({
+        "email": &existing_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Only the existing user should receive an email
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let existing_response = app
+        .post_forgot_password(&json!({ "email": &existing_email }))
+        .await;
+    let missing_response = app
+        .post_forgot_password(&json!({ "email": get_random_email() }))
+        .await;
+
+    assert_eq!(existing_response.status().as_u16(), 200);
+    assert_eq!(missing_response.status().as_u16(), 200);
+
+    let existing_body = existing_response
+        .json::<ForgotPasswordResponse>()
+        .await
+        .expect("Could not deserialize response body to ForgotPasswordResponse");
+    let missing_body = missing_response
+        .json::<ForgotPasswordResponse>()
+        .await
+        .expect("Could not deserialize response body to ForgotPasswordResponse");
+
+    assert_eq!(existing_body, missing_body);
+
+    // The email is sent in the background after the response
+    app.wait_for_email(&existing_email, "Password reset").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_even_if_email_fails_to_send() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_forgot_password(&json!({ "email": &email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.wait_for_email(&email, "Password reset").await;
+
+    app.clean_up().await;
+}
@@ -1,19 +1,39 @@
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType},
+    app_state::{
+        AppState, BannedTokenStoreType, OAuthClientStoreType, PasswordResetTokenStoreType,
+        TwoFACodeStoreType, UserStoreType,
+    },
     domain::Email,
     get_postgres_pool, get_redis_client,
     services::{
         data_stores::{
+            hashmap_rate_limit_store::HashmapRateLimitStore,
+            postgres_oauth_client_store::PostgresOAuthClientStore,
+            postgres_recovery_code_store::PostgresRecoveryCodeStore,
+            postgres_totp_secret_store::PostgresTotpSecretStore,
             postgres_user_store::PostgresUserStore,
+            redis_authorization_code_store::RedisAuthorizationCodeStore,
             redis_banned_token_store::RedisBannedTokenStore,
-            redis_two_fa_code_store::RedisTwoFACodeStore,
+            redis_email_verification_token_store::RedisEmailVerificationTokenStore,
+            redis_login_lockout_store::RedisLoginLockoutStore,
+            redis_magic_link_token_store::RedisMagicLinkTokenStore,
+            redis_oidc_state_store::RedisOidcStateStore,
+            redis_password_reset_token_store::RedisPasswordResetTokenStore,
+            redis_refresh_token_store::RedisRefreshTokenStore,
+            redis_session_store::RedisSessionStore,
+            redis_two_fa_code_store::{RedisTwoFACodeStore, DISABLE_2FA_NAMESPACE},
         },
+        oidc_client::{OidcClient, OidcProviderConfig},
         postmark_email_client::PostmarkEmailClient,
     },
-    utils::constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME},
+    routes::CsrfTokenResponse,
+    utils::{
+        constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME, TOTP_ENCRYPTION_KEY},
+        csrf::CSRF_HEADER_NAME,
+    },
     Application,
 };
-use reqwest::cookie::Jar;
+use reqwest::{cookie::Jar, header::HeaderMap};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{
     postgres::{PgConnectOptions, PgConnection, PgPoolOptions},
@@ -28,10 +48,15 @@ use wiremock::MockServer;
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
+    pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub oauth_client_store: OAuthClientStoreType,
     pub http_client: reqwest::Client,
     pub email_server: MockServer,
+    // Stands in for an external OpenID Connect provider named `OIDC_PROVIDER`
+    pub oidc_server: MockServer,
     pub db_name: String,
     pub clean_up_called: bool,
 }
@@ -45,15 +70,61 @@ impl TestApp {
         let base_url = email_server.uri();
         let email_client = Arc::new(configure_postmark_email_client(base_url));
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+        let oidc_server = MockServer::start().await;
+        let oidc_client = Arc::new(configure_oidc_client(oidc_server.uri()));
+
+        let totp_secret_store = Arc::new(RwLock::new(PostgresTotpSecretStore::new(
+            pg_pool.clone(),
+            TOTP_ENCRYPTION_KEY.clone(),
+        )));
+        let recovery_code_store =
+            Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+        let oauth_client_store =
+            Arc::new(RwLock::new(PostgresOAuthClientStore::new(pg_pool.clone())));
+        let user_store: UserStoreType = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
         let banned_token_store =
             Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+        let two_fa_code_store =
+            Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
+        let disable_2fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::with_namespace(
+            redis_conn.clone(),
+            DISABLE_2FA_NAMESPACE,
+        )));
+        let refresh_token_store =
+            Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_conn.clone())));
+        let password_reset_token_store =
+            Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(redis_conn.clone())));
+        let email_verification_token_store =
+            Arc::new(RwLock::new(RedisEmailVerificationTokenStore::new(redis_conn.clone())));
+        let magic_link_token_store =
+            Arc::new(RwLock::new(RedisMagicLinkTokenStore::new(redis_conn.clone())));
+        let authorization_code_store =
+            Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(redis_conn.clone())));
+        let oidc_state_store =
+            Arc::new(RwLock::new(RedisOidcStateStore::new(redis_conn.clone())));
+        let session_store = Arc::new(RwLock::new(RedisSessionStore::new(redis_conn.clone())));
+        let login_lockout_store = Arc::new(RwLock::new(RedisLoginLockoutStore::new(redis_conn)));
+        // Every test app gets its own buckets, so tests can't exhaust each other's limits
+        let rate_limit_store = Arc::new(RwLock::new(HashmapRateLimitStore::default()));
         let app_state = AppState::new(
-            user_store,
+            user_store.clone(),
             banned_token_store.clone(),
             two_fa_code_store.clone(),
+            disable_2fa_code_store,
             email_client,
+            refresh_token_store,
+            password_reset_token_store.clone(),
+            email_verification_token_store,
+            totp_secret_store,
+            recovery_code_store,
+            magic_link_token_store,
+            oauth_client_store.clone(),
+            authorization_code_store,
+            oidc_state_store,
+            oidc_client,
+            session_store,
+            login_lockout_store,
+            rate_limit_store,
         );
 
         let app = Application::build(app_state, test::APP_ADDRESS)
@@ -64,12 +135,21 @@ impl TestApp {
 
         // Run the auth service in a separate async task
         // to avoid blocking the main test thread.
-        #[allow(clippy::let_undescore_future)]
+        #[allow(clippy::let_underscore_future)]
         let _ = tokio::spawn(app.run());
 
         let cookie_jar = Arc::new(Jar::default());
+        let csrf_token = get_csrf_token(&address, cookie_jar.clone()).await;
+
+        // Like a page of the app, every request double-submits the CSRF token
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(CSRF_HEADER_NAME, csrf_token.parse().unwrap());
+
+        // Redirects are not followed, so tests can inspect them
         let http_client = reqwest::Client::builder()
             .cookie_provider(cookie_jar.clone())
+            .default_headers(default_headers)
+            .redirect(reqwest::redirect::Policy::none())
             .build()
             .unwrap();
 
@@ -77,10 +157,14 @@ impl TestApp {
         Self {
             address,
             cookie_jar,
+            user_store,
             banned_token_store,
             two_fa_code_store,
+            password_reset_token_store,
+            oauth_client_store,
             http_client,
             email_server,
+            oidc_server,
             db_name,
             clean_up_called: false,
         }
@@ -108,7 +192,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -120,7 +204,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .json(body)
             .send()
             .await
@@ -135,6 +219,38 @@ impl TestApp {
             .expect("Failed to execute request")
     }
 
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout-all", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_magic_link<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login/magic-link", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_consume_magic_link<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login/magic-link/consume", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -147,6 +263,18 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_resend_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_verify_token<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -159,6 +287,373 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_forgot_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/forgot-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/reset-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_delete_account<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/delete-account", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_restore_account<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/restore-account", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_enroll(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/totp/enroll", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_regenerate_recovery_codes<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/recovery-codes/regenerate", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_enable_2fa(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/settings/2fa/enable", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_disable_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/settings/2fa/disable", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_confirm_disable_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/settings/2fa/disable/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_oauth_authorize<Query>(&self, query: &Query) -> reqwest::Response
+    where
+        Query: serde::Serialize,
+    {
+        self.http_client
+            .get(format!("{}/oauth/authorize", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oauth_token<Form>(&self, form: &Form) -> reqwest::Response
+    where
+        Form: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/oauth/token", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_openid_configuration(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/openid-configuration", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_oidc_providers(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/login/oidc", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_oidc_login<Query>(&self, provider: &str, query: &Query) -> reqwest::Response
+    where
+        Query: serde::Serialize,
+    {
+        self.http_client
+            .get(format!("{}/login/oidc/{}", &self.address, provider))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_oidc_callback<Query>(&self, provider: &str, query: &Query) -> reqwest::Response
+    where
+        Query: serde::Serialize,
+    {
+        self.http_client
+            .get(format!("{}/login/oidc/{}/callback", &self.address, provider))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_userinfo(&self, access_token: Option<&str>) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .get(format!("{}/userinfo", &self.address));
+
+        if let Some(access_token) = access_token {
+            request = request.bearer_auth(access_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_users<Query>(&self, access_token: &str, query: &Query) -> reqwest::Response
+    where
+        Query: serde::Serialize,
+    {
+        self.http_client
+            .get(format!("{}/admin/users", &self.address))
+            .bearer_auth(access_token)
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_user(&self, access_token: &str, email: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users/{}", &self.address, email))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Sends a POST request to `/admin/users/{email}/{action}`, e.g. `disable`.
+    pub async fn post_admin_user_action(
+        &self,
+        access_token: &str,
+        email: &str,
+        action: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin/users/{}/{}", &self.address, email, action))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_oauth_client<Body>(
+        &self,
+        access_token: &str,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/oauth-clients", &self.address))
+            .bearer_auth(access_token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_verification_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-verification-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Returns the most recent email with the given subject sent to `recipient`.
+    ///
+    /// Some emails are sent in the background after the response, so this waits for a while
+    /// before giving up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no such email was sent.
+    pub async fn wait_for_email(&self, recipient: &str, subject: &str) -> serde_json::Value {
+        for _ in 0..50 {
+            let requests = self
+                .email_server
+                .received_requests()
+                .await
+                .expect("Request recording is disabled");
+
+            let email = requests
+                .iter()
+                .rev()
+                .filter_map(|request| {
+                    serde_json::from_slice::<serde_json::Value>(&request.body).ok()
+                })
+                .find(|body| body["To"] == recipient && body["Subject"] == subject);
+
+            if let Some(email) = email {
+                return email;
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+
+        panic!("No matching email was sent");
+    }
+
+    /// Returns the token from the most recent email with the given subject sent to `recipient`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no such email was sent.
+    pub async fn get_emailed_token(&self, recipient: &str, subject: &str) -> String {
+        self.wait_for_email(recipient, subject).await["TextBody"]
+            .as_str()
+            .and_then(|text| text.split_whitespace().last())
+            .map(str::to_owned)
+            .expect("Email has no token")
+    }
+
+    /// Verifies the email address of a freshly signed up user using the emailed token.
+    pub async fn verify_email(&self, email: &str) {
+        let token = self.get_emailed_token(email, "Verify your email").await;
+
+        let response = self
+            .post_verify_email(&serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
     pub async fn clean_up(&mut self) {
         delete_database(&self.db_name).await;
         self.clean_up_called = true;
@@ -257,6 +752,22 @@ async fn delete_database(db_name: &str) {
         .expect("Failed to drop the database.");
 }
 
+// Fetches a CSRF token, which also stores its cookie in the jar
+async fn get_csrf_token(address: &str, cookie_jar: Arc<Jar>) -> String {
+    reqwest::Client::builder()
+        .cookie_provider(cookie_jar)
+        .build()
+        .unwrap()
+        .get(format!("{}/csrf-token", address))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json::<CsrfTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to CsrfTokenResponse")
+        .csrf_token
+}
+
 fn configure_redis() -> redis::Connection {
     get_redis_client(DEFAULT_REDIS_HOSTNAME.to_owned())
         .expect("Failed to get Redis client")
@@ -276,3 +787,23 @@ fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
 
     PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
 }
+
+pub const OIDC_PROVIDER: &str = "mock";
+pub const OIDC_CLIENT_ID: &str = "auth-service";
+
+fn configure_oidc_client(issuer: String) -> OidcClient {
+    let provider = OidcProviderConfig {
+        name: OIDC_PROVIDER.to_owned(),
+        issuer,
+        client_id: OIDC_CLIENT_ID.to_owned(),
+        client_secret: Secret::new("client_secret".to_owned()),
+        scopes: vec!["openid".to_owned(), "email".to_owned()],
+    };
+
+    let http_client = reqwest::Client::builder()
+        .timeout(test::oidc_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    OidcClient::new(vec![provider], http_client)
+}
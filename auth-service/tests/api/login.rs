@@ -1,7 +1,10 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_service::{
     routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    utils::{
+        auth::TOKEN_TTL_SECONDS,
+        constants::{DEFAULT_LOGIN_LOCKOUT_THRESHOLD, JWT_COOKIE_NAME},
+    },
     ErrorResponse,
 };
 use serde_json::json;
@@ -99,9 +102,9 @@ async fn should_return_401_if_incorrect_credentials() {
             "email": &valid_email,
             "password": "wrong_password"
         }),
-        // Non-existent user
+        // Non-existent user, a fresh email so failed logins don't pile up across test runs
         json!({
-            "email": "non_existent@example.com",
+            "email": get_random_email(),
             "password": "password123"
         }),
     ];
@@ -135,6 +138,7 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     let login_body = serde_json::json!({
         "email": random_email,
@@ -151,6 +155,11 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
         .expect("No auth cookie found");
 
     assert!(!auth_cookie.value().is_empty());
+    // The cookie expires together with the token
+    assert_eq!(
+        auth_cookie.max_age(),
+        Some(std::time::Duration::from_secs(TOKEN_TTL_SECONDS as u64))
+    );
 
     app.clean_up().await;
 }
@@ -170,6 +179,7 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     Mock::given(path("/email"))
         .and(method("POST"))
@@ -198,3 +208,101 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_return_423_after_repeated_failed_logins() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    // The user is told about the lockout once
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let wrong_body = json!({ "email": &email, "password": "wrong_password" });
+    for _ in 1..DEFAULT_LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_login(&wrong_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_login(&wrong_body).await;
+    assert_eq!(response.status().as_u16(), 423);
+
+    // Even the correct password is rejected while the account is locked
+    let response = app
+        .post_login(&json!({ "email": &email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 423);
+
+    let error_response = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Failed to deserialize error response");
+    assert_eq!(error_response.error, "Account temporarily locked");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_unknown_emails_like_existing_ones() {
+    let mut app = TestApp::new().await;
+
+    let wrong_body = json!({ "email": get_random_email(), "password": "password123" });
+    for _ in 1..DEFAULT_LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_login(&wrong_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_login(&wrong_body).await;
+    assert_eq!(response.status().as_u16(), 423);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_failed_logins_after_successful_login() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    let wrong_body = json!({ "email": &email, "password": "wrong_password" });
+    let valid_body = json!({ "email": &email, "password": "password123" });
+
+    for _ in 1..DEFAULT_LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_login(&wrong_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_login(&valid_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The count starts over, so another run of failures stays below the threshold
+    for _ in 1..DEFAULT_LOGIN_LOCKOUT_THRESHOLD {
+        let response = app.post_login(&wrong_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    app.clean_up().await;
+}
@@ -2,7 +2,6 @@ use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
 use reqwest::cookie::CookieStore;
 use reqwest::Url;
 use secrecy::Secret;
-use serde_json;
 
 use crate::helpers::{get_random_email, TestApp};
 
@@ -67,6 +66,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     let signup_response = app.post_signup(&signup_body).await;
     assert_eq!(signup_response.status().as_u16(), 201);
+    app.verify_email(&email).await;
 
     let login_body = serde_json::json!({
         "email": email,
@@ -117,6 +117,7 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
 
     let signup_response = app.post_signup(&signup_body).await;
     assert_eq!(signup_response.status().as_u16(), 201);
+    app.verify_email(&email).await;
 
     let login_body = serde_json::json!({
         "email": email,
@@ -0,0 +1,151 @@
+use auth_service::{
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+struct LoginTokens {
+    auth_token: String,
+    refresh_token: String,
+}
+
+async fn signup(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    email
+}
+
+async fn login(app: &TestApp, email: &str) -> LoginTokens {
+    let login_body = json!({
+        "email": email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let get_cookie = |name: &str| {
+        response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .expect("No cookie found")
+            .value()
+            .to_owned()
+    };
+
+    LoginTokens {
+        auth_token: get_cookie(JWT_COOKIE_NAME),
+        refresh_token: get_cookie(REFRESH_TOKEN_COOKIE_NAME),
+    }
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Missing auth token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_invalidate_every_token_of_the_user() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    let first_tokens = login(&app, &email).await;
+    let current_tokens = login(&app, &email).await;
+
+    let other_email = signup(&app).await;
+    let other_tokens = login(&app, &other_email).await;
+
+    // Sign back in as the first user
+    let url = Url::parse(&app.address).expect("Failed to parse URL");
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            JWT_COOKIE_NAME, current_tokens.auth_token
+        ),
+        &url,
+    );
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+
+    for tokens in [&first_tokens, &current_tokens] {
+        let response = app
+            .post_verify_token(&json!({ "token": &tokens.auth_token }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+
+        app.cookie_jar.add_cookie_str(
+            &format!(
+                "{}={}; HttpOnly; SameSite=Lax; Path=/",
+                REFRESH_TOKEN_COOKIE_NAME, tokens.refresh_token
+            ),
+            &url,
+        );
+
+        let response = app.post_refresh().await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Other users stay signed in
+    let response = app
+        .post_verify_token(&json!({ "token": &other_tokens.auth_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Signing in again issues tokens of the new generation
+    let tokens = login(&app, &email).await;
+    let response = app
+        .post_verify_token(&json!({ "token": &tokens.auth_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
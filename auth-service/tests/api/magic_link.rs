@@ -0,0 +1,240 @@
+use auth_service::{
+    domain::Email,
+    routes::{MagicLinkResponse, TwoFactorAuthResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, requires_2fa: bool) -> String {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    email
+}
+
+async fn mount_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+// The emailed link ends with the token as its last query parameter
+async fn get_magic_link_token(app: &TestApp, email: &str) -> String {
+    let link = app.get_emailed_token(email, "Sign-in link").await;
+
+    link.rsplit_once("magic_link_token=")
+        .map(|(_, token)| token.to_owned())
+        .expect("No token in magic link")
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_magic_link(&json!({})).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = app.post_consume_magic_link(&json!({})).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_email_is_invalid() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_magic_link(&json!({ "email": "not-an-email" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_reveal_unknown_or_unverified_accounts() {
+    let mut app = TestApp::new().await;
+
+    let unverified_email = get_random_email();
+    let signup_body = json!({
+        "email": &unverified_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    for email in [get_random_email(), unverified_email] {
+        let response = app.post_magic_link(&json!({ "email": email })).await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        let response = response
+            .json::<MagicLinkResponse>()
+            .await
+            .expect("Could not deserialize response body to MagicLinkResponse");
+        assert_eq!(
+            response.message,
+            "If an account exists for this email, a sign-in link has been sent."
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_login_with_magic_link_only_once() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app, false).await;
+    mount_email_server(&app).await;
+
+    let response = app.post_magic_link(&json!({ "email": &email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = get_magic_link_token(&app, &email).await;
+
+    let response = app
+        .post_consume_magic_link(&json!({ "token": &token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    let response = app
+        .post_consume_magic_link(&json!({ "token": &token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Invalid auth token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_continue_into_2fa_if_required() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app, true).await;
+    mount_email_server(&app).await;
+
+    let response = app.post_magic_link(&json!({ "email": &email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = get_magic_link_token(&app, &email).await;
+
+    let response = app
+        .post_consume_magic_link(&json!({ "token": &token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let two_fa_code = app.get_emailed_token(&email, "2FA Code").await;
+
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": two_fa_code
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_login_while_password_reset_is_required() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app, false).await;
+    mount_email_server(&app).await;
+
+    let response = app.post_magic_link(&json!({ "email": &email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = get_magic_link_token(&app, &email).await;
+
+    // An admin forces a reset after the link was sent
+    app.user_store
+        .write()
+        .await
+        .set_password_reset_required(&Email::parse(Secret::new(email.clone())).unwrap(), true)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_consume_magic_link(&json!({ "token": &token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Password reset required");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_token_is_invalid() {
+    let mut app = TestApp::new().await;
+
+    // A malformed token and a well-formed one that was never issued
+    let test_cases = ["not-a-token".to_owned(), "a".repeat(64)];
+
+    for token in test_cases {
+        let response = app
+            .post_consume_magic_link(&json!({ "token": token }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    app.clean_up().await;
+}
@@ -1,7 +1,28 @@
+mod admin;
+mod bearer_token;
+mod change_password;
+mod csrf;
+mod delete_account;
+mod forgot_password;
 mod helpers;
 mod login;
 mod logout;
+mod logout_all;
+mod magic_link;
+mod oauth;
+mod oidc;
+mod oidc_login;
+mod rate_limit;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
+mod reset_password;
+mod roles;
 mod root;
+mod sessions;
 mod signup;
+mod totp;
+mod two_fa_settings;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
@@ -0,0 +1,419 @@
+use auth_service::{
+    domain::{OAuthClient, OAuthClientSecret},
+    routes::TokenResponse,
+    utils::{auth::IdTokenClaims, pkce::compute_code_challenge},
+    ErrorResponse,
+};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, DecodingKey, Validation};
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const CLIENT_ID: &str = "mobile-app";
+const REDIRECT_URI: &str = "https://app.example.com/callback";
+const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+async fn register_client(app: &TestApp, client_secret: Option<OAuthClientSecret>) {
+    let client = OAuthClient {
+        client_id: CLIENT_ID.to_owned(),
+        redirect_uris: vec![REDIRECT_URI.to_owned()],
+        confidential: client_secret.is_some(),
+    };
+
+    app.oauth_client_store
+        .write()
+        .await
+        .add_client(client, client_secret)
+        .await
+        .unwrap();
+}
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+fn authorize_query() -> serde_json::Value {
+    json!({
+        "response_type": "code",
+        "client_id": CLIENT_ID,
+        "redirect_uri": REDIRECT_URI,
+        "code_challenge": compute_code_challenge(CODE_VERIFIER),
+        "code_challenge_method": "S256",
+        "state": "xyz"
+    })
+}
+
+fn redirect_location(response: &reqwest::Response) -> Url {
+    assert_eq!(response.status().as_u16(), 303);
+
+    let location = response
+        .headers()
+        .get("location")
+        .expect("No location header")
+        .to_str()
+        .unwrap();
+
+    Url::parse(location).expect("Location is not a valid URL")
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+async fn authorize(app: &TestApp) -> String {
+    let response = app.get_oauth_authorize(&authorize_query()).await;
+    let location = redirect_location(&response);
+
+    assert!(location.as_str().starts_with(REDIRECT_URI));
+    assert_eq!(query_param(&location, "state").as_deref(), Some("xyz"));
+
+    query_param(&location, "code").expect("No code in redirect")
+}
+
+fn token_form(code: &str) -> serde_json::Value {
+    json!({
+        "grant_type": "authorization_code",
+        "code": code,
+        "redirect_uri": REDIRECT_URI,
+        "client_id": CLIENT_ID,
+        "code_verifier": CODE_VERIFIER
+    })
+}
+
+async fn assert_oauth_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, error);
+}
+
+#[tokio::test]
+async fn should_redirect_to_login_if_not_authenticated() {
+    let mut app = TestApp::new().await;
+
+    register_client(&app, None).await;
+
+    let response = app.get_oauth_authorize(&authorize_query()).await;
+    let location = redirect_location(&response);
+
+    let return_to = query_param(&location, "return_to").expect("No return_to in redirect");
+    assert!(return_to.starts_with("/oauth/authorize?"));
+    assert!(return_to.contains("client_id=mobile-app"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_exchange_code_for_access_token() {
+    let mut app = TestApp::new().await;
+
+    register_client(&app, None).await;
+    signup_and_login(&app).await;
+
+    let code = authorize(&app).await;
+
+    let response = app.post_oauth_token(&token_form(&code)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+
+    let token_response = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_eq!(token_response.token_type, "Bearer");
+
+    let response = app
+        .post_verify_token(&json!({ "token": token_response.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Codes can only be exchanged once
+    let response = app.post_oauth_token(&token_form(&code)).await;
+    assert_oauth_error(response, 400, "invalid_grant").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_access_token_after_account_deletion() {
+    let mut app = TestApp::new().await;
+
+    register_client(&app, None).await;
+    signup_and_login(&app).await;
+
+    let code = authorize(&app).await;
+    let response = app.post_oauth_token(&token_form(&code)).await;
+    let token_response = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+
+    let response = app
+        .post_delete_account(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Access tokens issued to clients aren't tied to a session, so they are revoked separately
+    let response = app
+        .post_verify_token(&json!({ "token": token_response.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_access_token_away_from_account_management() {
+    let mut app = TestApp::new().await;
+
+    register_client(&app, None).await;
+    signup_and_login(&app).await;
+
+    let code = authorize(&app).await;
+    let response = app.post_oauth_token(&token_form(&code)).await;
+    let access_token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .access_token;
+
+    let routes = [
+        "/logout-all",
+        "/totp/enroll",
+        "/settings/2fa/enable",
+        "/delete-account",
+    ];
+
+    for route in routes {
+        let response = app
+            .http_client
+            .post(format!("{}{}", &app.address, route))
+            .bearer_auth(&access_token)
+            .json(&json!({ "password": "password123" }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_oauth_error(response, 403, "Insufficient scope").await;
+    }
+
+    // The token still identifies the user
+    let response = app
+        .post_verify_token(&json!({ "token": access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_issue_id_token_for_openid_scope() {
+    let mut app = TestApp::new().await;
+
+    register_client(&app, None).await;
+    let email = signup_and_login(&app).await;
+
+    let mut query = authorize_query();
+    query["scope"] = json!("openid email");
+    query["nonce"] = json!("n-0S6_WzA2Mj");
+
+    let response = app.get_oauth_authorize(&query).await;
+    let location = redirect_location(&response);
+    let code = query_param(&location, "code").expect("No code in redirect");
+
+    let response = app.post_oauth_token(&token_form(&code)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token_response = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    let id_token = token_response.id_token.expect("No ID token issued");
+
+    let jwks = app
+        .get_jwks()
+        .await
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+
+    let header = decode_header(&id_token).unwrap();
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[CLIENT_ID]);
+
+    let claims = decode::<IdTokenClaims>(
+        &id_token,
+        &DecodingKey::from_jwk(&jwks.keys[0]).unwrap(),
+        &validation,
+    )
+    .expect("ID token does not verify against the JWKS")
+    .claims;
+
+    assert_eq!(claims.sub, email);
+    assert_eq!(claims.email, email);
+    assert!(claims.email_verified);
+    assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+
+    // ID tokens can't be used as access tokens
+    let response = app.post_verify_token(&json!({ "token": id_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_issue_id_token_without_openid_scope() {
+    let mut app = TestApp::new().await;
+
+    register_client(&app, None).await;
+    signup_and_login(&app).await;
+
+    let code = authorize(&app).await;
+
+    let response = app.post_oauth_token(&token_form(&code)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token_response = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert!(token_response.id_token.is_none());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_wrong_code_verifier() {
+    let mut app = TestApp::new().await;
+
+    register_client(&app, None).await;
+    signup_and_login(&app).await;
+
+    let code = authorize(&app).await;
+
+    let mut form = token_form(&code);
+    form["code_verifier"] = json!("a".repeat(43));
+
+    let response = app.post_oauth_token(&form).await;
+    assert_oauth_error(response, 400, "invalid_grant").await;
+
+    // The failed attempt burned the code
+    let response = app.post_oauth_token(&token_form(&code)).await;
+    assert_oauth_error(response, 400, "invalid_grant").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_redirect_to_unregistered_client_or_uri() {
+    let mut app = TestApp::new().await;
+
+    register_client(&app, None).await;
+
+    let mut query = authorize_query();
+    query["client_id"] = json!("unknown-client");
+    let response = app.get_oauth_authorize(&query).await;
+    assert_oauth_error(response, 401, "invalid_client").await;
+
+    let mut query = authorize_query();
+    query["redirect_uri"] = json!("https://evil.example.com/callback");
+    let response = app.get_oauth_authorize(&query).await;
+    assert_oauth_error(response, 400, "invalid_request").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_redirect_with_error_if_pkce_is_missing() {
+    let mut app = TestApp::new().await;
+
+    register_client(&app, None).await;
+    signup_and_login(&app).await;
+
+    let test_cases = [
+        ("code_challenge_method", json!("plain"), "invalid_request"),
+        ("code_challenge", json!("too-short"), "invalid_request"),
+        ("response_type", json!("token"), "unsupported_response_type"),
+    ];
+
+    for (field, value, error) in test_cases {
+        let mut query = authorize_query();
+        query[field] = value;
+
+        let response = app.get_oauth_authorize(&query).await;
+        let location = redirect_location(&response);
+
+        assert!(location.as_str().starts_with(REDIRECT_URI));
+        assert_eq!(query_param(&location, "error").as_deref(), Some(error));
+        assert_eq!(query_param(&location, "code"), None);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_secret_for_confidential_clients() {
+    let mut app = TestApp::new().await;
+
+    let client_secret = OAuthClientSecret::default();
+    register_client(&app, Some(client_secret.clone())).await;
+    signup_and_login(&app).await;
+
+    let code = authorize(&app).await;
+
+    let response = app.post_oauth_token(&token_form(&code)).await;
+    assert_oauth_error(response, 401, "invalid_client").await;
+
+    let mut form = token_form(&code);
+    form["client_secret"] = json!(OAuthClientSecret::default().as_ref().expose_secret());
+    let response = app.post_oauth_token(&form).await;
+    assert_oauth_error(response, 401, "invalid_client").await;
+
+    // A failed client authentication does not burn the code
+    form["client_secret"] = json!(client_secret.as_ref().expose_secret());
+    let response = app.post_oauth_token(&form).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_unsupported_grant_type() {
+    let mut app = TestApp::new().await;
+
+    register_client(&app, None).await;
+
+    let mut form = token_form("code");
+    form["grant_type"] = json!("password");
+
+    let response = app.post_oauth_token(&form).await;
+    assert_oauth_error(response, 400, "unsupported_grant_type").await;
+
+    app.clean_up().await;
+}
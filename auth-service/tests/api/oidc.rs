@@ -0,0 +1,159 @@
+use auth_service::{
+    routes::{OpenIdConfiguration, UserInfoResponse},
+    utils::{auth::Claims, constants::JWT_COOKIE_NAME},
+    ErrorResponse,
+};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, DecodingKey, Validation};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let jwt = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (email, jwt)
+}
+
+#[tokio::test]
+async fn should_return_openid_configuration() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_openid_configuration().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let configuration = response
+        .json::<OpenIdConfiguration>()
+        .await
+        .expect("Could not deserialize response body to OpenIdConfiguration");
+
+    let issuer = &configuration.issuer;
+    assert_eq!(
+        configuration.authorization_endpoint,
+        format!("{}/oauth/authorize", issuer)
+    );
+    assert_eq!(
+        configuration.token_endpoint,
+        format!("{}/oauth/token", issuer)
+    );
+    assert_eq!(
+        configuration.userinfo_endpoint,
+        format!("{}/userinfo", issuer)
+    );
+    assert_eq!(
+        configuration.jwks_uri,
+        format!("{}/.well-known/jwks.json", issuer)
+    );
+    assert_eq!(
+        configuration.id_token_signing_alg_values_supported,
+        vec!["EdDSA"]
+    );
+    assert_eq!(configuration.code_challenge_methods_supported, vec!["S256"]);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_verify_access_token_with_jwks() {
+    let mut app = TestApp::new().await;
+
+    let (email, jwt) = signup_and_login(&app).await;
+
+    let response = app.get_jwks().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let jwks = response
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+    assert_eq!(jwks.keys.len(), 1);
+
+    // Only public key material is published
+    let jwk = serde_json::to_value(&jwks.keys[0]).unwrap();
+    assert!(jwk.get("d").is_none());
+
+    // Tokens name the key that signed them
+    let header = decode_header(&jwt).unwrap();
+    let jwk = header
+        .kid
+        .as_ref()
+        .and_then(|kid| jwks.find(kid))
+        .expect("Token was not signed with a published key");
+
+    let claims = decode::<Claims>(
+        &jwt,
+        &DecodingKey::from_jwk(jwk).unwrap(),
+        &Validation::new(header.alg),
+    )
+    .expect("Access token does not verify against the JWKS")
+    .claims;
+
+    assert_eq!(claims.sub, email);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_userinfo_for_bearer_token() {
+    let mut app = TestApp::new().await;
+
+    let (email, jwt) = signup_and_login(&app).await;
+
+    let response = app.get_userinfo(Some(&jwt)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let userinfo = response
+        .json::<UserInfoResponse>()
+        .await
+        .expect("Could not deserialize response body to UserInfoResponse");
+    assert_eq!(userinfo.sub, email);
+    assert_eq!(userinfo.email, email);
+    assert!(userinfo.email_verified);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_userinfo_without_valid_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_userinfo(None).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_userinfo(Some("invalid")).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token"
+    );
+
+    app.clean_up().await;
+}
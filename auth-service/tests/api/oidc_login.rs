@@ -0,0 +1,388 @@
+use auth_service::{
+    domain::Email,
+    routes::OidcProvidersResponse,
+    utils::{
+        constants::JWT_COOKIE_NAME,
+        signing_key::{SigningKey, SIGNING_ALGORITHM},
+    },
+    ErrorResponse,
+};
+use jsonwebtoken::{encode, Header};
+use reqwest::Url;
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp, OIDC_CLIENT_ID, OIDC_PROVIDER};
+
+// PKCS#8 encoding of the all-one Ed25519 seed, the mock provider signs ID tokens with it
+const PROVIDER_KEY: &str = "MC4CAQAwBQYDK2VwBCIEIAEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEB";
+const PROVIDER_CODE: &str = "provider-code";
+
+struct LoginStart {
+    state: String,
+    nonce: String,
+}
+
+async fn mount_provider(app: &TestApp) {
+    let issuer = app.oidc_server.uri();
+    let key = SigningKey::from_pkcs8(PROVIDER_KEY).unwrap();
+
+    Mock::given(path("/.well-known/openid-configuration"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "jwks_uri": format!("{}/jwks", issuer),
+        })))
+        .mount(&app.oidc_server)
+        .await;
+
+    Mock::given(path("/jwks"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "keys": [key.jwk] })))
+        .mount(&app.oidc_server)
+        .await;
+}
+
+// Lets the mock provider exchange `PROVIDER_CODE` once for an ID token with the given claims
+async fn mount_token_endpoint(app: &TestApp, claims: serde_json::Value) {
+    let key = SigningKey::from_pkcs8(PROVIDER_KEY).unwrap();
+    let header = Header {
+        kid: Some(key.kid.clone()),
+        ..Header::new(SIGNING_ALGORITHM)
+    };
+    let id_token = encode(&header, &claims, &key.encoding_key).unwrap();
+
+    Mock::given(path("/token"))
+        .and(method("POST"))
+        .and(body_string_contains(format!("code={}", PROVIDER_CODE)))
+        .and(body_string_contains("code_verifier="))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": "provider-access-token",
+            "token_type": "Bearer",
+            "id_token": id_token,
+        })))
+        .up_to_n_times(1)
+        .mount(&app.oidc_server)
+        .await;
+}
+
+fn id_token_claims(app: &TestApp, email: &str, nonce: &str) -> serde_json::Value {
+    json!({
+        "iss": app.oidc_server.uri(),
+        "aud": OIDC_CLIENT_ID,
+        "sub": "provider-user-1",
+        "exp": 4_102_444_800u64,
+        "email": email,
+        "email_verified": true,
+        "nonce": nonce,
+    })
+}
+
+fn redirect_location(response: &reqwest::Response) -> String {
+    assert_eq!(response.status().as_u16(), 303);
+
+    response
+        .headers()
+        .get("location")
+        .expect("No location header")
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+async fn start_login(app: &TestApp) -> LoginStart {
+    let response = app.get_oidc_login(OIDC_PROVIDER, &json!({})).await;
+    let location = Url::parse(&redirect_location(&response)).unwrap();
+
+    assert!(location
+        .as_str()
+        .starts_with(&format!("{}/authorize", app.oidc_server.uri())));
+    assert_eq!(
+        query_param(&location, "client_id").as_deref(),
+        Some(OIDC_CLIENT_ID)
+    );
+    assert_eq!(
+        query_param(&location, "code_challenge_method").as_deref(),
+        Some("S256")
+    );
+    assert!(query_param(&location, "redirect_uri")
+        .unwrap()
+        .ends_with("/login/oidc/mock/callback"));
+
+    LoginStart {
+        state: query_param(&location, "state").expect("No state in redirect"),
+        nonce: query_param(&location, "nonce").expect("No nonce in redirect"),
+    }
+}
+
+async fn finish_login(app: &TestApp, state: &str) -> reqwest::Response {
+    app.get_oidc_callback(
+        OIDC_PROVIDER,
+        &json!({ "code": PROVIDER_CODE, "state": state }),
+    )
+    .await
+}
+
+async fn signup(app: &TestApp, requires_2fa: bool, verify_email: bool) -> String {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    if verify_email {
+        app.verify_email(&email).await;
+    }
+
+    email
+}
+
+fn has_auth_cookie(response: &reqwest::Response) -> bool {
+    response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME && !cookie.value().is_empty())
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, error);
+}
+
+#[tokio::test]
+async fn should_list_configured_providers() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_oidc_providers().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let json = response
+        .json::<OidcProvidersResponse>()
+        .await
+        .expect("Could not deserialize response body to OidcProvidersResponse");
+    assert_eq!(json.providers, vec![OIDC_PROVIDER]);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_provider() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_oidc_login("unknown", &json!({})).await;
+    assert_error(response, 404, "Unknown identity provider").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_create_user_on_first_login() {
+    let mut app = TestApp::new().await;
+    mount_provider(&app).await;
+
+    let email = get_random_email();
+    let login = start_login(&app).await;
+    mount_token_endpoint(&app, id_token_claims(&app, &email, &login.nonce)).await;
+
+    let response = finish_login(&app, &login.state).await;
+    assert!(has_auth_cookie(&response));
+    assert_eq!(redirect_location(&response), "/");
+
+    // The created account can log in again the same way
+    let login = start_login(&app).await;
+    mount_token_endpoint(&app, id_token_claims(&app, &email, &login.nonce)).await;
+
+    let response = finish_login(&app, &login.state).await;
+    assert!(has_auth_cookie(&response));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_link_existing_user_by_verified_email() {
+    let mut app = TestApp::new().await;
+    mount_provider(&app).await;
+
+    let email = signup(&app, false, true).await;
+
+    let login = start_login(&app).await;
+    mount_token_endpoint(&app, id_token_claims(&app, &email, &login.nonce)).await;
+
+    let response = finish_login(&app, &login.state).await;
+    assert!(has_auth_cookie(&response));
+
+    // The password keeps working
+    let response = app
+        .post_login(&json!({ "email": &email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_link_user_with_unverified_email() {
+    let mut app = TestApp::new().await;
+    mount_provider(&app).await;
+
+    let email = signup(&app, false, false).await;
+
+    let login = start_login(&app).await;
+    mount_token_endpoint(&app, id_token_claims(&app, &email, &login.nonce)).await;
+
+    let response = finish_login(&app, &login.state).await;
+    assert_error(response, 403, "Email not verified").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_login_while_password_reset_is_required() {
+    let mut app = TestApp::new().await;
+    mount_provider(&app).await;
+
+    let email = signup(&app, false, true).await;
+    app.user_store
+        .write()
+        .await
+        .set_password_reset_required(&Email::parse(Secret::new(email.clone())).unwrap(), true)
+        .await
+        .unwrap();
+
+    let login = start_login(&app).await;
+    mount_token_endpoint(&app, id_token_claims(&app, &email, &login.nonce)).await;
+
+    let response = finish_login(&app, &login.state).await;
+    assert!(!has_auth_cookie(&response));
+    assert_error(response, 403, "Password reset required").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_2fa_for_users_with_2fa() {
+    let mut app = TestApp::new().await;
+    mount_provider(&app).await;
+
+    let email = signup(&app, true, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login = start_login(&app).await;
+    mount_token_endpoint(&app, id_token_claims(&app, &email, &login.nonce)).await;
+
+    let response = finish_login(&app, &login.state).await;
+    assert!(!has_auth_cookie(&response));
+
+    let location = Url::parse(&redirect_location(&response)).unwrap();
+    assert_eq!(query_param(&location, "email"), Some(email.clone()));
+    let login_attempt_id =
+        query_param(&location, "login_attempt_id").expect("No login attempt ID in redirect");
+
+    let code = app.get_emailed_token(&email, "2FA Code").await;
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": &email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_unknown_or_reused_state() {
+    let mut app = TestApp::new().await;
+    mount_provider(&app).await;
+
+    let email = get_random_email();
+    let login = start_login(&app).await;
+    mount_token_endpoint(&app, id_token_claims(&app, &email, &login.nonce)).await;
+
+    let response = finish_login(&app, &"a".repeat(64)).await;
+    assert_error(response, 401, "External login failed").await;
+
+    // The failed attempt removed the state cookie, so the real state is refused as well
+    let response = finish_login(&app, &login.state).await;
+    assert_error(response, 401, "External login failed").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_id_token_with_wrong_nonce() {
+    let mut app = TestApp::new().await;
+    mount_provider(&app).await;
+
+    let email = get_random_email();
+    let login = start_login(&app).await;
+    mount_token_endpoint(&app, id_token_claims(&app, &email, "other-nonce")).await;
+
+    let response = finish_login(&app, &login.state).await;
+    assert_error(response, 401, "External login failed").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_unverified_provider_email() {
+    let mut app = TestApp::new().await;
+    mount_provider(&app).await;
+
+    let email = get_random_email();
+    let login = start_login(&app).await;
+    let mut claims = id_token_claims(&app, &email, &login.nonce);
+    claims["email_verified"] = json!(false);
+    mount_token_endpoint(&app, claims).await;
+
+    let response = finish_login(&app, &login.state).await;
+    assert_error(response, 403, "Email not verified").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_provider_error() {
+    let mut app = TestApp::new().await;
+    mount_provider(&app).await;
+
+    let login = start_login(&app).await;
+
+    let response = app
+        .get_oidc_callback(
+            OIDC_PROVIDER,
+            &json!({ "error": "access_denied", "state": login.state }),
+        )
+        .await;
+    assert_error(response, 401, "External login failed").await;
+
+    app.clean_up().await;
+}
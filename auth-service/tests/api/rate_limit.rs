@@ -0,0 +1,102 @@
+use auth_service::{utils::constants::rate_limits, ErrorResponse};
+use reqwest::header::RETRY_AFTER;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_429_with_retry_after_once_ip_limit_is_exceeded() {
+    let mut app = TestApp::new().await;
+
+    let capacity = rate_limits::SIGNUP.per_ip.unwrap().capacity;
+
+    // Every request counts against the limit, even ones the route rejects
+    for _ in 0..capacity {
+        let response = app.post_signup(&json!({})).await;
+        assert_eq!(response.status().as_u16(), 422);
+    }
+
+    let body = json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    let retry_after: u64 = response
+        .headers()
+        .get(RETRY_AFTER)
+        .expect("No Retry-After header found")
+        .to_str()
+        .unwrap()
+        .parse()
+        .expect("Retry-After is not a number of seconds");
+    assert!(retry_after > 0);
+
+    let error_response = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Failed to deserialize error response");
+    assert_eq!(error_response.error, "Too many requests");
+
+    // Other routes have their own limits
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_once_email_limit_is_exceeded() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let capacity = rate_limits::VERIFY_2FA.per_email.unwrap().capacity;
+    let verify_body = |email: &str| {
+        json!({
+            "email": email,
+            "loginAttemptId": "123e4567-e89b-12d3-a456-426614174000",
+            "2FACode": "123456"
+        })
+    };
+
+    for _ in 0..capacity {
+        let response = app.post_verify_2fa(&verify_body(&email)).await;
+        assert_ne!(response.status().as_u16(), 429);
+    }
+
+    let response = app.post_verify_2fa(&verify_body(&email)).await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().contains_key(RETRY_AFTER));
+
+    // Emails are limited regardless of case
+    let response = app
+        .post_verify_2fa(&verify_body(&email.to_uppercase()))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    // Other emails are unaffected
+    let response = app.post_verify_2fa(&verify_body(&get_random_email())).await;
+    assert_ne!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_limit_emails_sent_to_one_address() {
+    let mut app = TestApp::new().await;
+
+    let body = json!({ "email": get_random_email() });
+    let capacity = rate_limits::FORGOT_PASSWORD.per_email.unwrap().capacity;
+
+    for _ in 0..capacity {
+        let response = app.post_forgot_password(&body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = app.post_forgot_password(&body).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
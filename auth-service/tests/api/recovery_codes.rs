@@ -0,0 +1,273 @@
+use auth_service::{
+    routes::{RecoveryCodesResponse, SignupResponse, TwoFactorAuthResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Signs up a 2FA user and returns their email with the recovery codes issued at signup
+async fn signup_with_2fa(app: &TestApp) -> (String, Vec<String>) {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let recovery_codes = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse")
+        .recovery_codes
+        .expect("No recovery codes issued at signup");
+
+    app.verify_email(&email).await;
+
+    (email, recovery_codes)
+}
+
+async fn start_login(app: &TestApp, email: &str) -> String {
+    let login_body = json!({
+        "email": email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+async fn mount_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn should_issue_recovery_codes_only_to_2fa_signups() {
+    let mut app = TestApp::new().await;
+
+    let (_, recovery_codes) = signup_with_2fa(&app).await;
+    assert_eq!(recovery_codes.len(), 10);
+
+    let signup_body = json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let signup_response = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse");
+    assert_eq!(signup_response.recovery_codes, None);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_login_with_recovery_code_only_once() {
+    let mut app = TestApp::new().await;
+
+    let (email, recovery_codes) = signup_with_2fa(&app).await;
+    mount_email_server(&app).await;
+
+    let login_attempt_id = start_login(&app, &email).await;
+
+    // Codes are accepted regardless of case and surrounding whitespace
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": login_attempt_id,
+        "recoveryCode": format!(" {} ", recovery_codes[0].to_uppercase())
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    let login_attempt_id = start_login(&app, &email).await;
+
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": login_attempt_id,
+        "recoveryCode": &recovery_codes[0]
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Incorrect credentials");
+
+    // The login attempt is still open, so another code can be used
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": login_attempt_id,
+        "recoveryCode": &recovery_codes[1]
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_recovery_code_is_malformed() {
+    let mut app = TestApp::new().await;
+
+    let verify_body = json!({
+        "email": "test@example.com",
+        "loginAttemptId": "123e4567-e89b-12d3-a456-426614174000",
+        "recoveryCode": "not-a-recovery-code"
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_recovery_code_used_without_login_attempt() {
+    let mut app = TestApp::new().await;
+
+    let (email, recovery_codes) = signup_with_2fa(&app).await;
+
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": "123e4567-e89b-12d3-a456-426614174000",
+        "recoveryCode": &recovery_codes[0]
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_invalidate_old_codes_on_regenerate() {
+    let mut app = TestApp::new().await;
+
+    let (email, old_codes) = signup_with_2fa(&app).await;
+    mount_email_server(&app).await;
+
+    // Use one code to get an auth cookie
+    let login_attempt_id = start_login(&app, &email).await;
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": login_attempt_id,
+        "recoveryCode": &old_codes[0]
+    });
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_regenerate_recovery_codes(&json!({ "password": "wrong-password" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_regenerate_recovery_codes(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+    assert_eq!(new_codes.len(), 10);
+
+    let login_attempt_id = start_login(&app, &email).await;
+
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": &login_attempt_id,
+        "recoveryCode": &old_codes[1]
+    });
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": &login_attempt_id,
+        "recoveryCode": &new_codes[0]
+    });
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_409_if_regenerating_without_2fa() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_regenerate_recovery_codes(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_regenerate_recovery_codes(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "2FA not enabled");
+
+    app.clean_up().await;
+}
@@ -0,0 +1,149 @@
+use auth_service::{
+    domain::LoginAttemptId, routes::TwoFactorAuthResponse, utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Signs up a user with 2FA enabled and logs in, returning the email and the login attempt ID.
+// `expected_emails` counts the 2FA emails sent from the login on.
+async fn start_2fa_login(app: &TestApp, expected_emails: u64) -> (String, String) {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    app.post_signup(&signup_body).await;
+    app.verify_email(&email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_emails)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let auth_response = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    (email, auth_response.login_attempt_id)
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_resend_2fa(&json!({ "email": get_random_email() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_login_attempt_id_does_not_match() {
+    let mut app = TestApp::new().await;
+
+    let (email, _) = start_2fa_login(&app, 1).await;
+
+    let resend_body = json!({
+        "email": &email,
+        "loginAttemptId": uuid::Uuid::new_v4().to_string()
+    });
+
+    let response = app.post_resend_2fa(&resend_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Incorrect credentials");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_a_new_code_for_the_same_login_attempt() {
+    let mut app = TestApp::new().await;
+
+    // One email for the login and one for the resend
+    let (random_email, login_attempt_id) = start_2fa_login(&app, 2).await;
+
+    let resend_body = json!({
+        "email": &random_email,
+        "loginAttemptId": &login_attempt_id
+    });
+
+    let response = app.post_resend_2fa(&resend_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let json = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(json.login_attempt_id, login_attempt_id);
+
+    let (_, code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(Secret::new(login_attempt_id.clone())).unwrap())
+        .await
+        .unwrap();
+
+    let verify_body = json!({
+        "email": &random_email,
+        "loginAttemptId": &login_attempt_id,
+        "2FACode": code.as_ref().expose_secret()
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_resent_too_soon() {
+    let mut app = TestApp::new().await;
+
+    let (email, login_attempt_id) = start_2fa_login(&app, 2).await;
+
+    let resend_body = json!({
+        "email": &email,
+        "loginAttemptId": &login_attempt_id
+    });
+
+    let response = app.post_resend_2fa(&resend_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_resend_2fa(&resend_body).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
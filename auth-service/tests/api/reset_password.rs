@@ -0,0 +1,221 @@
+use auth_service::{
+    domain::{Email, PasswordResetToken},
+    utils::constants::{DEFAULT_LOGIN_LOCKOUT_THRESHOLD, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = vec![
+        json!({
+            "token": PasswordResetToken::default().as_ref().expose_secret()
+        }),
+        json!({
+            "newPassword": "newpassword123"
+        }),
+        json!({}),
+    ];
+
+    for malformed_body in test_cases {
+        let response = app.post_reset_password(&malformed_body).await;
+        assert_eq!(response.status().as_u16(), 422);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_password() {
+    let mut app = TestApp::new().await;
+
+    let body = json!({
+        "token": PasswordResetToken::default().as_ref().expose_secret(),
+        "newPassword": "short"
+    });
+
+    let response = app.post_reset_password(&body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let error_response = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Failed to deserialize error response");
+    assert_eq!(error_response.error, "Invalid credentials");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_unknown_token() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = vec![
+        json!({
+            "token": "invalid",
+            "newPassword": "newpassword123"
+        }),
+        json!({
+            "token": PasswordResetToken::default().as_ref().expose_secret(),
+            "newPassword": "newpassword123"
+        }),
+    ];
+
+    for body in test_cases {
+        let response = app.post_reset_password(&body).await;
+        assert_eq!(response.status().as_u16(), 401);
+
+        let error_response = response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Failed to deserialize error response");
+        assert_eq!(error_response.error, "Invalid auth token");
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_update_password_with_emailed_token() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+    app.verify_email(&email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_forgot_password(&json!({ "email": &email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = app.get_emailed_token(&email, "Password reset").await;
+
+    let reset_body = json!({
+        "token": &token,
+        "newPassword": "newpassword123"
+    });
+
+    let response = app.post_reset_password(&reset_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&json!({ "email": &email, "password": "newpassword123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&json!({ "email": &email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The token is single-use
+    let response = app.post_reset_password(&reset_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_existing_sessions() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = json!({
+        "email": &random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+    app.verify_email(&random_email).await;
+
+    let response = app
+        .post_login(&json!({ "email": &random_email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME));
+
+    let token = PasswordResetToken::default();
+    let email = Email::parse(Secret::new(random_email.clone())).unwrap();
+    app.password_reset_token_store
+        .write()
+        .await
+        .add_token(email, token.clone())
+        .await
+        .unwrap();
+
+    let reset_body = json!({
+        "token": token.as_ref().expose_secret(),
+        "newPassword": "newpassword123"
+    });
+    let response = app.post_reset_password(&reset_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The refresh token issued before the reset no longer works
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_unlock_a_locked_account() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = json!({
+        "email": &random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+    app.verify_email(&random_email).await;
+
+    let wrong_body = json!({ "email": &random_email, "password": "wrong_password" });
+    for _ in 0..DEFAULT_LOGIN_LOCKOUT_THRESHOLD {
+        app.post_login(&wrong_body).await;
+    }
+
+    let login_body = json!({ "email": &random_email, "password": "newpassword123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 423);
+
+    let token = PasswordResetToken::default();
+    let email = Email::parse(Secret::new(random_email.clone())).unwrap();
+    app.password_reset_token_store
+        .write()
+        .await
+        .add_token(email, token.clone())
+        .await
+        .unwrap();
+
+    let reset_body = json!({
+        "token": token.as_ref().expose_secret(),
+        "newPassword": "newpassword123"
+    });
+    let response = app.post_reset_password(&reset_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
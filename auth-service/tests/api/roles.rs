@@ -0,0 +1,203 @@
+use auth_service::{
+    domain::{Email, UserStoreError},
+    routes::BearerTokenResponse,
+    utils::constants::{permissions, roles},
+};
+use secrecy::Secret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    email
+}
+
+async fn login_for_token(app: &TestApp, email: &str) -> String {
+    let login_body = json!({
+        "email": email,
+        "password": "password123",
+        "tokenDelivery": "body"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<BearerTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to BearerTokenResponse")
+        .access_token
+}
+
+fn parse_email(email: &str) -> Email {
+    Email::parse(Secret::new(email.to_owned())).unwrap()
+}
+
+#[tokio::test]
+async fn should_return_200_if_token_has_required_role_and_permission() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    app.user_store
+        .write()
+        .await
+        .add_role(&parse_email(&email), roles::ADMIN)
+        .await
+        .unwrap();
+
+    let token = login_for_token(&app, &email).await;
+
+    let body = json!({
+        "token": &token,
+        "role": roles::ADMIN
+    });
+    let response = app.post_verify_token(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = json!({
+        "token": &token,
+        "role": roles::ADMIN,
+        "permission": permissions::USERS_WRITE
+    });
+    let response = app.post_verify_token(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_token_lacks_required_role_or_permission() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    let token = login_for_token(&app, &email).await;
+
+    // The token itself is still valid
+    let response = app.post_verify_token(&json!({ "token": &token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = json!({
+        "token": &token,
+        "role": roles::ADMIN
+    });
+    let response = app.post_verify_token(&body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let body = json!({
+        "token": &token,
+        "permission": permissions::USERS_READ
+    });
+    let response = app.post_verify_token(&body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_before_checking_roles_of_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let body = json!({
+        "token": "invalid_token_12345",
+        "role": roles::ADMIN
+    });
+    let response = app.post_verify_token(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_leave_removed_role_out_of_new_tokens() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    let parsed_email = parse_email(&email);
+    {
+        let mut user_store = app.user_store.write().await;
+        user_store
+            .add_role(&parsed_email, roles::ADMIN)
+            .await
+            .unwrap();
+        user_store
+            .remove_role(&parsed_email, roles::ADMIN)
+            .await
+            .unwrap();
+    }
+
+    let token = login_for_token(&app, &email).await;
+
+    let body = json!({
+        "token": &token,
+        "permission": permissions::USERS_READ
+    });
+    let response = app.post_verify_token(&body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_tokens_that_claim_a_removed_role() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    let parsed_email = parse_email(&email);
+    app.user_store
+        .write()
+        .await
+        .add_role(&parsed_email, roles::ADMIN)
+        .await
+        .unwrap();
+
+    let token = login_for_token(&app, &email).await;
+    let response = app.get_admin_users(&token, &()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.user_store
+        .write()
+        .await
+        .remove_role(&parsed_email, roles::ADMIN)
+        .await
+        .unwrap();
+
+    // The token still lists the admin role, but was issued before it was removed
+    let response = app.get_admin_users(&token, &()).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_unknown_roles() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    let mut user_store = app.user_store.write().await;
+
+    assert_eq!(
+        user_store.add_role(&parse_email(&email), "superuser").await,
+        Err(UserStoreError::RoleNotFound)
+    );
+    assert_eq!(
+        user_store
+            .add_role(&parse_email(&get_random_email()), roles::ADMIN)
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+
+    drop(user_store);
+    app.clean_up().await;
+}
@@ -0,0 +1,242 @@
+use auth_service::{
+    routes::SessionsResponse,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Tokens issued by a login, so they can be used again after a later login replaces the cookies
+struct LoginTokens {
+    auth_token: String,
+    refresh_token: String,
+}
+
+async fn signup(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    email
+}
+
+async fn login(app: &TestApp, email: &str) -> LoginTokens {
+    let login_body = json!({
+        "email": email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let get_cookie = |name: &str| {
+        response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .expect("No cookie found")
+            .value()
+            .to_owned()
+    };
+
+    LoginTokens {
+        auth_token: get_cookie(JWT_COOKIE_NAME),
+        refresh_token: get_cookie(REFRESH_TOKEN_COOKIE_NAME),
+    }
+}
+
+fn set_cookies(app: &TestApp, tokens: &LoginTokens) {
+    let url = Url::parse(&app.address).expect("Failed to parse URL");
+
+    for (name, value) in [
+        (JWT_COOKIE_NAME, &tokens.auth_token),
+        (REFRESH_TOKEN_COOKIE_NAME, &tokens.refresh_token),
+    ] {
+        app.cookie_jar.add_cookie_str(
+            &format!("{}={}; HttpOnly; SameSite=Lax; Path=/", name, value),
+            &url,
+        );
+    }
+}
+
+async fn get_sessions(app: &TestApp) -> SessionsResponse {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.delete_session("some-session").await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_every_session_of_the_user() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    login(&app, &email).await;
+    login(&app, &email).await;
+
+    // Sessions of other users are not listed
+    let other_email = signup(&app).await;
+    let other_tokens = login(&app, &other_email).await;
+    login(&app, &email).await;
+
+    let sessions = get_sessions(&app).await.sessions;
+    assert_eq!(sessions.len(), 3);
+    assert_eq!(sessions.iter().filter(|session| session.current).count(), 1);
+    assert!(sessions
+        .iter()
+        .all(|session| session.ip.as_deref() == Some("127.0.0.1")));
+
+    set_cookies(&app, &other_tokens);
+    let sessions = get_sessions(&app).await.sessions;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_another_session() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    let first_tokens = login(&app, &email).await;
+    login(&app, &email).await;
+
+    let sessions = get_sessions(&app).await.sessions;
+    let other_session = sessions
+        .iter()
+        .find(|session| !session.current)
+        .expect("No other session found");
+
+    let response = app.delete_session(&other_session.id).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = get_sessions(&app).await.sessions;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    // Neither the auth token nor the refresh token of the revoked session work anymore
+    let response = app
+        .post_verify_token(&json!({ "token": &first_tokens.auth_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    set_cookies(&app, &first_tokens);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_sign_out_when_revoking_the_current_session() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    let tokens = login(&app, &email).await;
+
+    let sessions = get_sessions(&app).await.sessions;
+    let response = app.delete_session(&sessions[0].id).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+
+    let response = app
+        .post_verify_token(&json!({ "token": &tokens.auth_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_session_belongs_to_another_user() {
+    let mut app = TestApp::new().await;
+
+    let other_email = signup(&app).await;
+    login(&app, &other_email).await;
+    let other_session_id = get_sessions(&app).await.sessions[0].id.clone();
+
+    let email = signup(&app).await;
+    login(&app, &email).await;
+
+    for id in [other_session_id.as_str(), "unknown-session"] {
+        let response = app.delete_session(id).await;
+        assert_eq!(response.status().as_u16(), 404);
+
+        let json: ErrorResponse = response
+            .json()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse");
+        assert_eq!(json.error, "Session not found");
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_the_session_when_refreshing() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    login(&app, &email).await;
+    let session_id = get_sessions(&app).await.sessions[0].id.clone();
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = get_sessions(&app).await.sessions;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, session_id);
+    assert!(sessions[0].current);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_end_the_session_on_logout() {
+    let mut app = TestApp::new().await;
+
+    let email = signup(&app).await;
+    let first_tokens = login(&app, &email).await;
+    login(&app, &email).await;
+
+    set_cookies(&app, &first_tokens);
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    login(&app, &email).await;
+    let sessions = get_sessions(&app).await.sessions;
+    assert_eq!(sessions.len(), 2);
+
+    app.clean_up().await;
+}
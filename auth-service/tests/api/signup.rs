@@ -35,6 +35,7 @@ async fn should_return_201_if_valid_input() {
 
     let expected_response = SignupResponse {
         message: "User created successfully!".to_owned(),
+        recovery_codes: None,
     };
 
     // Assert that we are getting the correct response body!
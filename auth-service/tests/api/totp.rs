@@ -0,0 +1,291 @@
+use auth_service::{
+    routes::{EnrollTotpResponse, RecoveryCodesResponse, TwoFactorAuthResponse},
+    ErrorResponse,
+};
+use chrono::Utc;
+use serde_json::json;
+use totp_rs::{Algorithm, Secret, TOTP};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn totp_code(secret: &str, time: i64) -> String {
+    let secret = Secret::Encoded(secret.to_owned()).to_bytes().unwrap();
+    TOTP::new(Algorithm::SHA1, 6, 1, 30, secret, None, "".to_owned())
+        .unwrap()
+        .generate(time as u64)
+}
+
+fn urlencode_email(email: &str) -> String {
+    email.replace('@', "%40")
+}
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+async fn enroll(app: &TestApp) -> EnrollTotpResponse {
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<EnrollTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to EnrollTotpResponse")
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.post_totp_confirm(&json!({ "code": "123456" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Missing auth token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_secret_and_otpauth_uri_on_enroll() {
+    let mut app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    let enrollment = enroll(&app).await;
+
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment
+        .otpauth_uri
+        .contains(&format!("secret={}", enrollment.secret)));
+    assert!(enrollment
+        .otpauth_uri
+        .contains(&urlencode_email(&email)));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_confirmation_code_is_incorrect() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let enrollment = enroll(&app).await;
+
+    // A code from far outside the allowed clock skew
+    let code = totp_code(&enrollment.secret, Utc::now().timestamp() - 3600);
+
+    let response = app.post_totp_confirm(&json!({ "code": code })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_totp_confirm(&json!({ "code": "abcdef" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_login_with_totp_once_confirmed() {
+    let mut app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    let enrollment = enroll(&app).await;
+
+    let now = Utc::now().timestamp();
+    let response = app
+        .post_totp_confirm(&json!({ "code": totp_code(&enrollment.secret, now) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let recovery_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+    assert_eq!(recovery_codes.len(), 10);
+
+    // Enrolling again is not possible once TOTP is enabled
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    // No 2FA email is sent to TOTP users
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    // The confirmation code used the current time step, so use the next one
+    let code = totp_code(&enrollment.secret, now + 30);
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": &code
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_replayed_totp_code() {
+    let mut app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    let enrollment = enroll(&app).await;
+
+    let now = Utc::now().timestamp();
+    let code = totp_code(&enrollment.secret, now);
+
+    let response = app.post_totp_confirm(&json!({ "code": &code })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": &code
+    });
+
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "Incorrect credentials");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_enroll_new_secret_after_disabling_2fa() {
+    let mut app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    let enrollment = enroll(&app).await;
+
+    let now = Utc::now().timestamp();
+    let response = app
+        .post_totp_confirm(&json!({ "code": totp_code(&enrollment.secret, now) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_disable_2fa(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let confirm_body = json!({
+        "loginAttemptId": login_attempt_id,
+        "2FACode": totp_code(&enrollment.secret, now + 30)
+    });
+    let response = app.post_confirm_disable_2fa(&confirm_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The old secret is gone, so a new authenticator can be set up
+    let new_enrollment = enroll(&app).await;
+    assert_ne!(new_enrollment.secret, enrollment.secret);
+
+    let response = app
+        .post_totp_confirm(&json!({ "code": totp_code(&new_enrollment.secret, now) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    // Codes from the old authenticator are no longer accepted
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": totp_code(&enrollment.secret, now + 60)
+    });
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
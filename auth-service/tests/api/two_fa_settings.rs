@@ -0,0 +1,233 @@
+use auth_service::{
+    routes::{RecoveryCodesResponse, TwoFactorAuthResponse},
+    ErrorResponse,
+};
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// Signs up a user without 2FA and logs them in
+async fn signup_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&email).await;
+
+    let response = app.post_login(&login_body(&email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+fn login_body(email: &str) -> serde_json::Value {
+    json!({
+        "email": email,
+        "password": "password123"
+    })
+}
+
+async fn mount_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+// Starts disabling 2FA and returns the login attempt ID of the challenge
+async fn start_disable_2fa(app: &TestApp) -> String {
+    let response = app
+        .post_disable_2fa(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_enable_2fa().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_disable_2fa(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let confirm_body = json!({
+        "loginAttemptId": uuid::Uuid::new_v4().to_string(),
+        "2FACode": "123456"
+    });
+    let response = app.post_confirm_disable_2fa(&confirm_body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_enable_2fa() {
+    let mut app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    mount_email_server(&app).await;
+
+    let response = app.post_enable_2fa().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let recovery_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+    assert_eq!(recovery_codes.len(), 10);
+
+    // Panics unless the user was notified
+    app.get_emailed_token(&email, "Two-factor authentication enabled")
+        .await;
+
+    let response = app.post_enable_2fa().await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let response = app.post_login(&login_body(&email)).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_disable_2fa_with_password_and_code() {
+    let mut app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    mount_email_server(&app).await;
+
+    let response = app.post_enable_2fa().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_attempt_id = start_disable_2fa(&app).await;
+    let code = app.get_emailed_token(&email, "2FA Code").await;
+
+    let confirm_body = json!({
+        "loginAttemptId": &login_attempt_id,
+        "2FACode": code
+    });
+    let response = app.post_confirm_disable_2fa(&confirm_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.get_emailed_token(&email, "Two-factor authentication disabled")
+        .await;
+
+    // The code can't be used again
+    let response = app.post_confirm_disable_2fa(&confirm_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_login(&login_body(&email)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_disable_2fa(&json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let json: ErrorResponse = response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(json.error, "2FA not enabled");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_password_or_code() {
+    let mut app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    mount_email_server(&app).await;
+
+    let response = app.post_enable_2fa().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_disable_2fa(&json!({ "password": "wrong-password" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let login_attempt_id = start_disable_2fa(&app).await;
+    let code = app.get_emailed_token(&email, "2FA Code").await;
+
+    // Any six digits other than the real code
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    let confirm_body = json!({
+        "loginAttemptId": &login_attempt_id,
+        "2FACode": wrong_code
+    });
+    let response = app.post_confirm_disable_2fa(&confirm_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // 2FA is still required
+    let response = app.post_login(&login_body(&email)).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_confirm_with_challenge_from_disable_2fa() {
+    let mut app = TestApp::new().await;
+
+    let email = signup_and_login(&app).await;
+    mount_email_server(&app).await;
+
+    let response = app.post_enable_2fa().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Login attempts can also be started without a password, e.g. from a magic link
+    let response = app.post_login(&login_body(&email)).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let code = app.get_emailed_token(&email, "2FA Code").await;
+
+    let confirm_body = json!({
+        "loginAttemptId": &login_attempt_id,
+        "2FACode": &code
+    });
+    let response = app.post_confirm_disable_2fa(&confirm_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Likewise, the disable challenge can't complete a login
+    let login_attempt_id = start_disable_2fa(&app).await;
+    let code = app.get_emailed_token(&email, "2FA Code").await;
+
+    let verify_body = json!({
+        "email": &email,
+        "loginAttemptId": &login_attempt_id,
+        "2FACode": &code
+    });
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
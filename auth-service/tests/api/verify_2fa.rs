@@ -1,7 +1,7 @@
 use auth_service::{
-    domain::Email,
+    domain::{LoginAttemptId, TwoFACode, TwoFACodeStoreError},
     routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    utils::constants::{JWT_COOKIE_NAME, MAX_PENDING_TWO_FA_LOGINS, MAX_TWO_FA_ATTEMPTS},
     ErrorResponse,
 };
 use secrecy::{ExposeSecret, Secret};
@@ -11,6 +11,20 @@ use wiremock::{Mock, ResponseTemplate};
 
 use crate::helpers::{get_random_email, TestApp};
 
+async fn get_code(app: &TestApp, login_attempt_id: &str) -> TwoFACode {
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(login_attempt_id.to_owned())).unwrap();
+
+    let (_, code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&login_attempt_id)
+        .await
+        .unwrap();
+
+    code
+}
+
 #[tokio::test]
 async fn should_return_422_if_malformed_input() {
     let mut app = TestApp::new().await;
@@ -99,6 +113,7 @@ async fn should_return_401_if_incorrect_credentials() {
     });
 
     app.post_signup(&signup_body).await;
+    app.verify_email(&random_email).await;
 
     Mock::given(path("/email"))
         .and(method("POST"))
@@ -159,7 +174,7 @@ async fn should_return_401_if_incorrect_credentials() {
 }
 
 #[tokio::test]
-async fn should_return_401_if_old_code() {
+async fn should_accept_codes_of_concurrent_logins() {
     let mut app = TestApp::new().await;
 
     // Create a test user with 2FA enabled
@@ -171,6 +186,7 @@ async fn should_return_401_if_old_code() {
     });
 
     app.post_signup(&signup_body).await;
+    app.verify_email(&random_email).await;
 
     Mock::given(path("/email"))
         .and(method("POST"))
@@ -184,27 +200,85 @@ async fn should_return_401_if_old_code() {
         "password": "password123"
     });
 
-    // First login
-    let first_login_response = app.post_login(&login_body).await;
-    let first_auth_response = first_login_response
-        .json::<TwoFactorAuthResponse>()
-        .await
-        .unwrap();
+    // Log in on two devices, the second login doesn't replace the first one's code
+    let mut logins = Vec::new();
+    for _ in 0..2 {
+        let auth_response = app
+            .post_login(&login_body)
+            .await
+            .json::<TwoFactorAuthResponse>()
+            .await
+            .unwrap();
+        let code = get_code(&app, &auth_response.login_attempt_id).await;
+        logins.push((auth_response.login_attempt_id, code));
+    }
 
-    // Get the 2FA code from the store
-    let two_fa_code_store = app.two_fa_code_store.read().await;
-    let email = Email::parse(Secret::new(random_email.clone())).unwrap();
-    let (_, first_code) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store); // Release the lock
-
-    // Second login (this should overwrite the first 2FA code)
-    let second_login_response = app.post_login(&login_body).await;
-    let second_auth_response = second_login_response
+    // A code only works for the login attempt it was sent for
+    if logins[0].1 != logins[1].1 {
+        let verify_body = json!({
+            "email": &random_email,
+            "loginAttemptId": &logins[0].0,
+            "2FACode": logins[1].1.as_ref().expose_secret()
+        });
+
+        let response = app.post_verify_2fa(&verify_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    for (login_attempt_id, code) in &logins {
+        let verify_body = json!({
+            "email": &random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code.as_ref().expose_secret()
+        });
+
+        let response = app.post_verify_2fa(&verify_body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_login_dropped_for_newer_logins() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = json!({
+        "email": &random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    app.post_signup(&signup_body).await;
+    app.verify_email(&random_email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(MAX_PENDING_TWO_FA_LOGINS as u64 + 1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({
+        "email": &random_email,
+        "password": "password123"
+    });
+
+    let first_auth_response = app
+        .post_login(&login_body)
+        .await
         .json::<TwoFactorAuthResponse>()
         .await
         .unwrap();
+    let first_code = get_code(&app, &first_auth_response.login_attempt_id).await;
+
+    // Only so many logins can await 2FA at once, so the first one is dropped
+    for _ in 0..MAX_PENDING_TWO_FA_LOGINS {
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 206);
+    }
 
-    // Try to verify with the first login's 2FA code
     let verify_body = json!({
         "email": &random_email,
         "loginAttemptId": &first_auth_response.login_attempt_id,
@@ -236,6 +310,7 @@ async fn should_return_200_if_correct_code() {
     });
 
     app.post_signup(&signup_body).await;
+    app.verify_email(&random_email).await;
 
     Mock::given(path("/email"))
         .and(method("POST"))
@@ -257,10 +332,7 @@ async fn should_return_200_if_correct_code() {
         .unwrap();
 
     // Get the 2FA code from the store
-    let two_fa_code_store = app.two_fa_code_store.read().await;
-    let email = Email::parse(Secret::new(random_email.clone())).unwrap();
-    let (_, two_fa_code) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store); // Release the lock
+    let two_fa_code = get_code(&app, &auth_response.login_attempt_id).await;
 
     // Verify with the correct 2FA code
     let verify_body = json!({
@@ -298,6 +370,7 @@ async fn should_return_401_if_same_code_twice() {
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
+    app.verify_email(&random_email).await;
 
     Mock::given(path("/email"))
         .and(method("POST"))
@@ -319,10 +392,7 @@ async fn should_return_401_if_same_code_twice() {
         .unwrap();
 
     // Get the 2FA code from the store
-    let two_fa_code_store = app.two_fa_code_store.read().await;
-    let email = Email::parse(Secret::new(random_email.clone())).unwrap();
-    let (_, two_fa_code) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store); // Release the lock
+    let two_fa_code = get_code(&app, &auth_response.login_attempt_id).await;
 
     // Verify with the correct 2FA code (first time)
     let verify_body = json!({
@@ -346,3 +416,69 @@ async fn should_return_401_if_same_code_twice() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_invalidate_code_after_too_many_wrong_guesses() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = json!({
+        "email": &random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    app.post_signup(&signup_body).await;
+    app.verify_email(&random_email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = json!({
+        "email": &random_email,
+        "password": "password123"
+    });
+
+    let login_response = app.post_login(&login_body).await;
+    let auth_response = login_response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .unwrap();
+
+    let login_attempt_id =
+        LoginAttemptId::parse(Secret::new(auth_response.login_attempt_id.clone())).unwrap();
+    let code = get_code(&app, &auth_response.login_attempt_id).await;
+
+    // Any six digits other than the real code
+    let wrong_code = if code.as_ref().expose_secret() == "000000" {
+        "111111"
+    } else {
+        "000000"
+    };
+
+    for _ in 0..MAX_TWO_FA_ATTEMPTS {
+        let verify_body = json!({
+            "email": &random_email,
+            "loginAttemptId": &auth_response.login_attempt_id,
+            "2FACode": wrong_code
+        });
+
+        let response = app.post_verify_2fa(&verify_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The code is gone, so even the right one can't be used and the user has to log in again
+    let result = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&login_attempt_id)
+        .await;
+    assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
+    app.clean_up().await;
+}
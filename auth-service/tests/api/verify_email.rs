@@ -0,0 +1,185 @@
+use auth_service::{
+    domain::EmailVerificationToken, routes::ResendVerificationEmailResponse, ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = vec![json!({ "tok": "value" }), json!({}), json!("not an object")];
+
+    for malformed_body in test_cases {
+        let response = app.post_verify_email(&malformed_body).await;
+        assert_eq!(response.status().as_u16(), 422);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = vec![
+        json!({ "token": "invalid" }),
+        json!({ "token": EmailVerificationToken::default().as_ref().expose_secret() }),
+    ];
+
+    for body in test_cases {
+        let response = app.post_verify_email(&body).await;
+        assert_eq!(response.status().as_u16(), 401);
+
+        let error_response = response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Failed to deserialize error response");
+        assert_eq!(error_response.error, "Invalid auth token");
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_on_login_before_email_is_verified() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let error_response = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Failed to deserialize error response");
+    assert_eq!(error_response.error, "Email not verified");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_allow_login_once_verified() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let token = app.get_emailed_token(&email, "Verify your email").await;
+    let response = app.post_verify_email(&json!({ "token": &token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_body = json!({
+        "email": &email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The verification token can't be reused
+    let response = app.post_verify_email(&json!({ "token": &token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_same_resend_response_during_cooldown() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Signup already sent a verification email, which starts the cooldown
+    let cooldown_response = app
+        .post_resend_verification_email(&json!({ "email": &email }))
+        .await;
+    let unknown_response = app
+        .post_resend_verification_email(&json!({ "email": get_random_email() }))
+        .await;
+
+    assert_eq!(cooldown_response.status().as_u16(), 200);
+    assert_eq!(unknown_response.status().as_u16(), 200);
+
+    let cooldown_body = cooldown_response
+        .json::<ResendVerificationEmailResponse>()
+        .await
+        .expect("Could not deserialize response body");
+    let unknown_body = unknown_response
+        .json::<ResendVerificationEmailResponse>()
+        .await
+        .expect("Could not deserialize response body");
+
+    assert_eq!(cooldown_body, unknown_body);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_same_resend_response_for_unknown_and_verified_emails() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": &email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    app.post_signup(&signup_body).await;
+    app.verify_email(&email).await;
+
+    let verified_response = app
+        .post_resend_verification_email(&json!({ "email": &email }))
+        .await;
+    let unknown_response = app
+        .post_resend_verification_email(&json!({ "email": get_random_email() }))
+        .await;
+
+    assert_eq!(verified_response.status().as_u16(), 200);
+    assert_eq!(unknown_response.status().as_u16(), 200);
+
+    let verified_body = verified_response
+        .json::<ResendVerificationEmailResponse>()
+        .await
+        .expect("Could not deserialize response body");
+    let unknown_body = unknown_response
+        .json::<ResendVerificationEmailResponse>()
+        .await
+        .expect("Could not deserialize response body");
+
+    assert_eq!(verified_body, unknown_body);
+
+    app.clean_up().await;
+}
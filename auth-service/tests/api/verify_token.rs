@@ -37,6 +37,7 @@ async fn should_return_200_valid_token() {
 
     let signup_response = app.post_signup(&signup_body).await;
     assert_eq!(signup_response.status().as_u16(), 201);
+    app.verify_email(&email).await;
 
     let login_body = json!({
         "email": email,
@@ -100,6 +101,7 @@ async fn should_return_401_if_banned_token() {
 
     let signup_response = app.post_signup(&signup_body).await;
     assert_eq!(signup_response.status().as_u16(), 201);
+    app.verify_email(&email).await;
 
     let login_body = json!({
         "email": email,